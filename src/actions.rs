@@ -0,0 +1,392 @@
+use crate::terminal::ShellKind;
+use egui::{Color32, RichText, ScrollArea};
+use regex::Regex;
+use serde::Deserialize;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Output kept for each background run; older lines are dropped.
+const OUTPUT_LIMIT: usize = 256 * 1024;
+/// How often a background run checks whether it was cancelled.
+const CANCEL_POLL: Duration = Duration::from_millis(100);
+
+const SAMPLE_CONFIG: &str = r#"# Custom actions for the explorer's right-click menu.
+#
+# Commands run in a terminal are typed into its shell and use its syntax;
+# background commands run with `sh -c` (`cmd /C` on Windows).
+#
+# Placeholders in `command`, each replaced by a shell-quoted value:
+#   {path}   the selected entry
+#   {dir}    the directory containing it
+#   {name}   its file name
+#   {paths}  every selected entry, separated by spaces
+# Without {paths}, the command runs once for each selected entry.
+#
+# run        = "terminal" (default), "new-terminal" or "background"
+# applies-to = "any" (default), "files" or "directories"
+# extensions = only offer the action for files with these extensions
+
+[[action]]
+name = "cargo test here"
+command = "cd {path} && cargo test"
+applies-to = "directories"
+
+[[action]]
+name = "Convert to MP4"
+command = "ffmpeg -i {path} {path}.mp4"
+extensions = ["mov", "mkv", "avi", "webm"]
+run = "background"
+
+[[action]]
+name = "Open in editor"
+command = "$EDITOR {paths}"
+applies-to = "files"
+run = "new-terminal"
+"#;
+
+/// Where an action's command runs.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RunIn {
+    /// Typed into the focused terminal
+    #[default]
+    Terminal,
+    /// Typed into a new terminal tab opened in the entry's directory
+    NewTerminal,
+    /// Run without a terminal, output shown in the background actions window
+    Background,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AppliesTo {
+    #[default]
+    Any,
+    Files,
+    Directories,
+}
+
+/// A user-defined command offered in the explorer's context menu.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CustomAction {
+    pub name: String,
+    pub command: String,
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    #[serde(default)]
+    pub applies_to: AppliesTo,
+    #[serde(default)]
+    pub run: RunIn,
+}
+
+#[derive(Deserialize)]
+struct ActionsFile {
+    #[serde(default, rename = "action")]
+    actions: Vec<CustomAction>,
+}
+
+pub fn config_path() -> Option<PathBuf> {
+    Some(dirs::config_dir()?.join("rustfm").join("actions.toml"))
+}
+
+/// Read the actions file. A missing file means no actions.
+pub fn load() -> Result<Vec<CustomAction>, String> {
+    let Some(path) = config_path() else {
+        return Ok(Vec::new());
+    };
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    };
+    toml::from_str::<ActionsFile>(&text)
+        .map(|file| file.actions)
+        .map_err(|e| format!("Invalid {}: {}", path.display(), e))
+}
+
+/// The actions file, written with commented examples if it does not exist.
+pub fn ensure_config() -> std::io::Result<PathBuf> {
+    let path = config_path().ok_or_else(|| std::io::Error::other("no config directory"))?;
+    if !path.exists() {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&path, SAMPLE_CONFIG)?;
+    }
+    Ok(path)
+}
+
+impl CustomAction {
+    /// Whether the action is offered for this selection: every entry has
+    /// to pass the filters.
+    pub fn matches(&self, paths: &[PathBuf]) -> bool {
+        !paths.is_empty() && paths.iter().all(|path| self.matches_path(path))
+    }
+
+    fn matches_path(&self, path: &Path) -> bool {
+        let is_dir = path.is_dir();
+        let kind_ok = match self.applies_to {
+            AppliesTo::Any => true,
+            AppliesTo::Files => !is_dir,
+            AppliesTo::Directories => is_dir,
+        };
+        let extension_ok = self.extensions.is_empty()
+            || (!is_dir
+                && path.extension().is_some_and(|ext| {
+                    self.extensions
+                        .iter()
+                        .any(|wanted| ext.to_string_lossy().eq_ignore_ascii_case(wanted.trim_start_matches('.')))
+                }));
+        kind_ok && extension_ok
+    }
+
+    /// The command lines to run for `paths`, with placeholders quoted for
+    /// `shell`.
+    pub fn commands(&self, paths: &[PathBuf], shell: ShellKind) -> Vec<String> {
+        let all: Vec<String> = paths.iter().map(|path| shell.quote(path)).collect();
+        let all = all.join(" ");
+        // One pass, so placeholders inside substituted paths stay literal
+        let expand = |path: &Path| {
+            placeholder_regex()
+                .replace_all(&self.command, |found: &regex::Captures| match &found[1] {
+                    "paths" => all.clone(),
+                    "path" => shell.quote(path),
+                    "dir" => shell.quote(path.parent().unwrap_or(path)),
+                    _ => shell.quote(path.file_name().map(Path::new).unwrap_or(path)),
+                })
+                .into_owned()
+        };
+        if self.command.contains("{paths}") {
+            paths.first().map(|first| expand(first)).into_iter().collect()
+        } else {
+            paths.iter().map(|path| expand(path)).collect()
+        }
+    }
+}
+
+fn placeholder_regex() -> &'static Regex {
+    static PLACEHOLDER: OnceLock<Regex> = OnceLock::new();
+    PLACEHOLDER.get_or_init(|| Regex::new(r"\{(paths|path|dir|name)\}").expect("valid regex"))
+}
+
+/// Shell the background actions run in.
+pub fn background_shell() -> ShellKind {
+    if cfg!(target_os = "windows") {
+        ShellKind::Cmd
+    } else {
+        ShellKind::Sh
+    }
+}
+
+/// An action command running, or finished, without a terminal.
+struct BackgroundRun {
+    name: String,
+    command: String,
+    started: Instant,
+    output: Arc<Mutex<String>>,
+    /// Exit code, or why the command could not run, once it is over.
+    result: Arc<Mutex<Option<Result<i32, String>>>>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl BackgroundRun {
+    fn is_running(&self) -> bool {
+        self.result.lock().map(|r| r.is_none()).unwrap_or(false)
+    }
+}
+
+/// Append a line of output, dropping the oldest lines past `OUTPUT_LIMIT`.
+fn append_output(output: &mut String, line: &str) {
+    output.push_str(line);
+    output.push('\n');
+    if output.len() > OUTPUT_LIMIT {
+        let excess = output.len() - OUTPUT_LIMIT;
+        // Cut after a newline, which is always a character boundary
+        let cut = output.as_bytes()[excess..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(output.len(), |newline| excess + newline + 1);
+        output.drain(..cut);
+    }
+}
+
+/// Stop a background command along with whatever it started.
+#[cfg(unix)]
+fn kill(child: &mut std::process::Child) {
+    // The command leads its own process group, see `BackgroundRuns::start`
+    // SAFETY: kill has no memory-safety preconditions
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGTERM);
+    }
+}
+
+#[cfg(not(unix))]
+fn kill(child: &mut std::process::Child) {
+    let _ = child.kill();
+}
+
+/// Commands started from custom actions with `run = "background"`, and the
+/// window showing their output.
+#[derive(Default)]
+pub struct BackgroundRuns {
+    runs: Vec<BackgroundRun>,
+    open: bool,
+}
+
+impl BackgroundRuns {
+    pub fn start(&mut self, ctx: &egui::Context, name: &str, command: String, cwd: &Path) {
+        let output = Arc::new(Mutex::new(String::new()));
+        let result = Arc::new(Mutex::new(None));
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let mut process = if cfg!(target_os = "windows") {
+            let mut process = Command::new("cmd");
+            process.arg("/C").arg(&command);
+            process
+        } else {
+            let mut process = Command::new("sh");
+            process.arg("-c").arg(&command);
+            process
+        };
+        process
+            .current_dir(cwd)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        // Its own process group, so cancelling also stops what it started
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut process, 0);
+
+        match process.spawn() {
+            Ok(mut child) => {
+                let readers: Vec<Box<dyn Read + Send>> = [
+                    child.stdout.take().map(|out| Box::new(out) as Box<dyn Read + Send>),
+                    child.stderr.take().map(|err| Box::new(err) as Box<dyn Read + Send>),
+                ]
+                .into_iter()
+                .flatten()
+                .collect();
+                let handles: Vec<_> = readers
+                    .into_iter()
+                    .map(|reader| {
+                        let output = Arc::clone(&output);
+                        let ctx = ctx.clone();
+                        thread::spawn(move || {
+                            for line in BufReader::new(reader).lines().map_while(Result::ok) {
+                                if let Ok(mut output) = output.lock() {
+                                    append_output(&mut output, &line);
+                                }
+                                ctx.request_repaint();
+                            }
+                        })
+                    })
+                    .collect();
+                let done = Arc::clone(&result);
+                let cancel = Arc::clone(&cancelled);
+                let ctx = ctx.clone();
+                thread::spawn(move || {
+                    let status = loop {
+                        match child.try_wait() {
+                            Ok(Some(status)) => break Ok(status.code().unwrap_or(-1)),
+                            Ok(None) if cancel.load(Ordering::Relaxed) => {
+                                kill(&mut child);
+                                break child.wait().map(|status| status.code().unwrap_or(-1));
+                            }
+                            Ok(None) => thread::sleep(CANCEL_POLL),
+                            Err(e) => break Err(e),
+                        }
+                    };
+                    for handle in handles {
+                        let _ = handle.join();
+                    }
+                    let status = status.map_err(|e| e.to_string());
+                    if let Ok(mut done) = done.lock() {
+                        *done = Some(status);
+                    }
+                    ctx.request_repaint();
+                });
+            }
+            Err(e) => {
+                if let Ok(mut result) = result.lock() {
+                    *result = Some(Err(e.to_string()));
+                }
+            }
+        }
+
+        self.runs.push(BackgroundRun {
+            name: name.to_string(),
+            command,
+            started: Instant::now(),
+            output,
+            result,
+            cancelled,
+        });
+        self.open = true;
+    }
+
+    pub fn render(&mut self, ctx: &egui::Context) {
+        if !self.open {
+            return;
+        }
+        let mut open = self.open;
+        egui::Window::new("Background Actions")
+            .open(&mut open)
+            .default_size([560.0, 360.0])
+            .show(ctx, |ui| {
+                if ui.button("Clear finished").clicked() {
+                    self.runs.retain(BackgroundRun::is_running);
+                }
+                ui.separator();
+                if self.runs.is_empty() {
+                    ui.label(RichText::new("No background actions").weak());
+                }
+                ScrollArea::vertical().show(ui, |ui| {
+                    for (index, run) in self.runs.iter().enumerate().rev() {
+                        let result = run.result.lock().ok().and_then(|r| r.clone());
+                        let cancelled = run.cancelled.load(Ordering::Relaxed);
+                        let (status, color) = match &result {
+                            Some(Ok(_)) if cancelled => ("cancelled".to_string(), Color32::GRAY),
+                            None if cancelled => ("cancelling".to_string(), Color32::YELLOW),
+                            None => (
+                                format!("running for {}s", run.started.elapsed().as_secs()),
+                                Color32::YELLOW,
+                            ),
+                            Some(Ok(0)) => ("exit 0".to_string(), Color32::GREEN),
+                            Some(Ok(code)) => (format!("exit {}", code), Color32::LIGHT_RED),
+                            Some(Err(e)) => (format!("failed to start: {}", e), Color32::LIGHT_RED),
+                        };
+                        let header = RichText::new(format!("{} — {}", run.name, status)).color(color);
+                        egui::CollapsingHeader::new(header)
+                            .id_salt(("background_run", index))
+                            .default_open(true)
+                            .show(ui, |ui| {
+                                ui.horizontal(|ui| {
+                                    ui.label(RichText::new(&run.command).monospace().weak());
+                                    if result.is_none() && !cancelled && ui.small_button("⏹ Cancel").clicked() {
+                                        run.cancelled.store(true, Ordering::Relaxed);
+                                    }
+                                });
+                                let output = run.output.lock().map(|o| o.clone()).unwrap_or_default();
+                                ScrollArea::vertical()
+                                    .id_salt(("background_output", index))
+                                    .max_height(160.0)
+                                    .stick_to_bottom(true)
+                                    .show(ui, |ui| {
+                                        ui.label(RichText::new(output).monospace());
+                                    });
+                            });
+                    }
+                });
+                if self.runs.iter().any(BackgroundRun::is_running) {
+                    // Keep the elapsed time ticking
+                    ctx.request_repaint_after(std::time::Duration::from_secs(1));
+                }
+            });
+        self.open = open;
+    }
+}
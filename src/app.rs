@@ -1,47 +1,147 @@
-use crate::explorer::ExplorerPanel;
+use crate::actions::{self, BackgroundRuns, CustomAction, RunIn};
+use crate::explorer::{self, ExplorerPanel};
+use crate::history::History;
+use crate::jobs::JobQueue;
 use crate::search::SearchPanel;
-use crate::terminal::TerminalPanel;
+use crate::terminal::{LinkAction, LinkTarget, NotifySettings, TerminalManager, TerminalProfile};
+use crate::trash::TrashPanel;
 use eframe::Frame;
-use egui::{Context, CentralPanel, TopBottomPanel, SidePanel, Ui};
-use std::path::PathBuf;
+use egui::{Context, CentralPanel, TopBottomPanel, SidePanel, Ui, RichText, Color32};
+use std::path::{Path, PathBuf};
+
+const SCROLLBACK_LIMIT_KEY: &str = "terminal_scrollback_limit";
+const COMMAND_HISTORY_KEY: &str = "terminal_command_history";
+const TERMINAL_PROFILES_KEY: &str = "terminal_profiles";
+const NOTIFY_SETTINGS_KEY: &str = "terminal_notifications";
 
 pub struct FileExplorerApp {
     current_path: PathBuf,
     explorer: ExplorerPanel,
-    terminal: TerminalPanel,
+    terminals: TerminalManager,
     search: SearchPanel,
     terminal_height: f32,
     show_terminal: bool,
     status_message: String,
+    /// Why the last file operation failed, shown until dismissed or
+    /// replaced by the next operation's outcome.
+    status_error: Option<String>,
+    background_runs: BackgroundRuns,
+    jobs: JobQueue,
+    /// Explorer operations that Ctrl+Z can undo.
+    history: History,
+    trash: TrashPanel,
+    /// Whether the central panel shows the trash instead of a directory.
+    show_trash: bool,
 }
 
 impl FileExplorerApp {
     pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        let current_path = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("/"));
+        // Profiles are needed before the first terminal starts
+        let profiles = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, TERMINAL_PROFILES_KEY))
+            .unwrap_or_else(TerminalProfile::defaults);
+        let mut terminals = TerminalManager::new(current_path.clone(), profiles);
+        
         // Load previous app state if available
         if let Some(storage) = cc.storage {
-            // TODO: Load persisted state
+            if let Some(limit) = eframe::get_value(storage, SCROLLBACK_LIMIT_KEY) {
+                terminals.set_scrollback_limit(limit);
+            }
+            if let Some(history) = eframe::get_value(storage, COMMAND_HISTORY_KEY) {
+                terminals.load_history(history);
+            }
+            if let Some(settings) = eframe::get_value::<NotifySettings>(storage, NOTIFY_SETTINGS_KEY) {
+                terminals.set_notify_settings(settings);
+            }
         }
-
-        let current_path = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("/"));
         
         Self {
             current_path: current_path.clone(),
             explorer: ExplorerPanel::new(current_path.clone()),
-            terminal: TerminalPanel::new(current_path.clone()),
+            terminals,
             search: SearchPanel::new(),
             terminal_height: 250.0,
             show_terminal: true,
             status_message: String::new(),
+            status_error: None,
+            background_runs: BackgroundRuns::default(),
+            jobs: JobQueue::new(),
+            history: History::default(),
+            trash: TrashPanel::default(),
+            show_trash: false,
         }
     }
 
     fn navigate_to(&mut self, path: PathBuf) {
         self.current_path = path.clone();
+        self.show_trash = false;
         self.explorer.navigate_to(path.clone());
-        self.terminal.set_directory(path.clone());
+        self.terminals.set_directory(path.clone());
         self.status_message = format!("Navigated to: {}", path.display());
     }
 
+    /// Show a path in the explorer without moving the terminal's shell.
+    fn reveal_path(&mut self, path: &Path) {
+        self.explorer.reveal(path);
+        let dir = if path.is_dir() { path } else { path.parent().unwrap_or(path) };
+        self.current_path = dir.to_path_buf();
+    }
+
+    fn handle_link(&mut self, action: LinkAction) {
+        match action {
+            LinkAction::Reveal(target) => {
+                if let LinkTarget::Path { path, .. } = &target {
+                    self.reveal_path(path);
+                }
+                self.status_message = format!("Revealed: {}", target.describe());
+            }
+            LinkAction::Open(target) => {
+                match &target {
+                    LinkTarget::Path { path, .. } => explorer::open_file(path),
+                    LinkTarget::Url(url) => explorer::open_file(url),
+                }
+                self.status_message = format!("Opened: {}", target.describe());
+            }
+        }
+    }
+
+    /// Run a custom action from the explorer's context menu on `paths`.
+    fn run_action(&mut self, ctx: &Context, action: CustomAction, paths: Vec<PathBuf>) {
+        let dir = paths
+            .first()
+            .and_then(|path| path.parent())
+            .map_or_else(|| self.current_path.clone(), Path::to_path_buf);
+        match action.run {
+            RunIn::Terminal | RunIn::NewTerminal => {
+                // Commands are only typed into a shell
+                let shell = match (action.run, self.terminals.shell_kind()) {
+                    (RunIn::Terminal, Some(shell)) => Some(shell),
+                    _ => {
+                        self.terminals.new_tab_in(dir);
+                        self.terminals.shell_kind()
+                    }
+                };
+                let Some(shell) = shell else {
+                    self.status_message = format!("{}: the default terminal profile does not run a shell", action.name);
+                    return;
+                };
+                self.show_terminal = true;
+                for command in action.commands(&paths, shell) {
+                    self.terminals.run_command(&command);
+                }
+                self.status_message = format!("Running in terminal: {}", action.name);
+            }
+            RunIn::Background => {
+                for command in action.commands(&paths, actions::background_shell()) {
+                    self.background_runs.start(ctx, &action.name, command, &dir);
+                }
+                self.status_message = format!("Running in background: {}", action.name);
+            }
+        }
+    }
+
     fn render_toolbar(&mut self, ui: &mut Ui) {
         ui.horizontal(|ui| {
             // Back/Forward buttons
@@ -60,6 +160,21 @@ impl FileExplorerApp {
             
             ui.separator();
             
+            let undo = self.history.next_undo();
+            let button = ui.add_enabled(undo.is_some(), egui::Button::new("⟲"));
+            if button.on_hover_text(format!("Undo {}", undo.unwrap_or_default())).clicked() {
+                self.history.undo();
+                ui.ctx().request_repaint();
+            }
+            let redo = self.history.next_redo();
+            let button = ui.add_enabled(redo.is_some(), egui::Button::new("⟳"));
+            if button.on_hover_text(format!("Redo {}", redo.unwrap_or_default())).clicked() {
+                self.history.redo();
+                ui.ctx().request_repaint();
+            }
+            
+            ui.separator();
+            
             // Path breadcrumb
             ui.label("📁");
             let components: Vec<_> = self.current_path.components().collect();
@@ -86,8 +201,14 @@ impl FileExplorerApp {
                 if ui.button("🔍").clicked() {
                     self.search.toggle();
                 }
+                if ui.button("⏳").on_hover_text("File operations").clicked() {
+                    self.jobs.toggle();
+                }
+                if ui.button("🕘").on_hover_text("Undo history").clicked() {
+                    self.history.toggle();
+                }
                 // Terminal toggle
-                if ui.button(if self.show_terminal { "🖥️" } else { "🖥️" }).clicked() {
+                if ui.button("🖥️").clicked() {
                     self.show_terminal = !self.show_terminal;
                 }
             });
@@ -112,7 +233,7 @@ impl FileExplorerApp {
             for (icon, name, path) in favorites {
                 if let Some(path) = path {
                     if ui.selectable_label(
-                        self.current_path == path,
+                        !self.show_trash && self.current_path == path,
                         format!("{} {}", icon, name)
                     ).clicked() {
                         self.navigate_to(path);
@@ -120,6 +241,12 @@ impl FileExplorerApp {
                 }
             }
             
+            if ui.selectable_label(self.show_trash, "🗑 Trash").clicked() {
+                self.show_trash = true;
+                self.trash.refresh();
+                self.status_message = "Showing the trash".to_string();
+            }
+            
             ui.separator();
             ui.heading("Devices");
             // TODO: List mounted volumes
@@ -128,10 +255,33 @@ impl FileExplorerApp {
 
     fn render_status_bar(&mut self, ui: &mut Ui) {
         ui.horizontal(|ui| {
-            ui.label(&self.status_message);
+            if let Some(error) = &self.status_error {
+                ui.label(RichText::new(format!("⚠ {}", error)).color(Color32::LIGHT_RED));
+                if ui.small_button("✕").on_hover_text("Dismiss").clicked() {
+                    self.status_error = None;
+                }
+            } else {
+                ui.label(&self.status_message);
+            }
             ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if self.jobs.render_status(ui) {
+                    self.jobs.toggle();
+                }
                 let item_count = self.explorer.item_count();
                 ui.label(format!("{} items", item_count));
+                let selected = self.explorer.selected_paths().len();
+                if selected > 0 {
+                    ui.separator();
+                    if ui
+                        .small_button("⌨ Insert in terminal")
+                        .on_hover_text("Type the selected paths at the terminal's cursor")
+                        .clicked()
+                    {
+                        self.explorer.insert_selection_in_terminal();
+                        ui.ctx().request_repaint();
+                    }
+                    ui.label(format!("{} selected", selected));
+                }
             });
         });
     }
@@ -139,11 +289,11 @@ impl FileExplorerApp {
 
 impl eframe::App for FileExplorerApp {
     fn update(&mut self, ctx: &Context, _frame: &mut Frame) {
-        // Update terminal
-        self.terminal.update(ctx);
+        // Update terminals
+        self.terminals.update(ctx);
         
         // Handle search
-        if let Some(search_path) = self.search.check_search() {
+        if let Some(_search_path) = self.search.check_search() {
             // TODO: Execute search
         }
         
@@ -151,6 +301,88 @@ impl eframe::App for FileExplorerApp {
         if let Some(new_path) = self.explorer.check_navigation() {
             self.navigate_to(new_path);
         }
+        
+        // Follow the shell when it changes directory
+        if let Some(new_path) = self.terminals.check_navigation() {
+            self.navigate_to(new_path);
+        }
+
+        // Explorer entries sent to the terminal
+        if let Some(paths) = self.explorer.check_insert() {
+            self.show_terminal = true;
+            self.terminals.insert_paths(&paths);
+            self.status_message = match paths.as_slice() {
+                [path] => format!("Inserted in terminal: {}", path.display()),
+                paths => format!("Inserted {} paths in terminal", paths.len()),
+            };
+        }
+
+        // Renames and creations in the explorer can be undone
+        if let Some(operation) = self.explorer.check_operation() {
+            self.history.record(operation);
+        }
+        self.history.handle_keys(ctx);
+
+        // Restoring and undoing change entries behind the explorer's back
+        let external = self.trash.check_status().or_else(|| self.history.check_status());
+        if external.is_some() {
+            self.explorer.refresh();
+            if self.show_trash {
+                self.trash.refresh();
+            }
+        }
+        // Outcome of file operations in the explorer
+        for status in [self.explorer.check_status(), external] {
+            match status {
+                Some(Ok(message)) => {
+                    self.status_message = message;
+                    self.status_error = None;
+                }
+                Some(Err(error)) => self.status_error = Some(error),
+                None => {}
+            }
+        }
+
+        // Copies, moves and deletions run as background jobs; the
+        // explorer's own are recorded for undoing once they finish
+        if let Some(request) = self.explorer.check_job() {
+            if let Some(id) = self.jobs.submit(ctx, request) {
+                self.history.track(id);
+            }
+        }
+        if let Some(request) = self.trash.check_job() {
+            self.jobs.submit(ctx, request);
+        }
+        if let Some(request) = self.history.check_job() {
+            let id = self.jobs.submit(ctx, request);
+            self.history.job_submitted(id);
+        }
+        for job in self.jobs.take_finished() {
+            // The history may have something to say about it next frame
+            self.history.job_finished(&job);
+            ctx.request_repaint();
+            self.explorer.job_finished(&job.completed);
+            if self.show_trash {
+                self.trash.refresh();
+            }
+            match job.result {
+                Ok(message) => {
+                    self.status_message = message;
+                    self.status_error = None;
+                }
+                Err(error) => self.status_error = Some(error),
+            }
+        }
+
+        // Custom actions picked in the explorer
+        if let Some((action, paths)) = self.explorer.check_action() {
+            self.run_action(ctx, action, paths);
+        }
+
+        // Links clicked in terminal output
+        if let Some(action) = self.terminals.check_links() {
+            self.handle_link(action);
+        }
 
         // Toolbar
         TopBottomPanel::top("toolbar").show(ctx, |ui| {
@@ -169,7 +401,7 @@ impl eframe::App for FileExplorerApp {
                 .default_height(self.terminal_height)
                 .height_range(100.0..=500.0)
                 .show(ctx, |ui| {
-                    self.terminal.render(ui);
+                    self.terminals.render(ui);
                 });
         }
 
@@ -183,9 +415,17 @@ impl eframe::App for FileExplorerApp {
 
         // Main content area
         CentralPanel::default().show(ctx, |ui| {
-            self.explorer.render(ui);
+            if self.show_trash {
+                self.trash.render(ui);
+            } else {
+                self.explorer.render(ui);
+            }
         });
 
+        self.background_runs.render(ctx);
+        self.jobs.render(ctx);
+        self.history.render(ctx);
+
         // Search modal
         if self.search.is_visible() {
             self.search.render(ctx);
@@ -193,6 +433,9 @@ impl eframe::App for FileExplorerApp {
     }
 
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
-        // TODO: Persist state
+        eframe::set_value(storage, SCROLLBACK_LIMIT_KEY, &self.terminals.scrollback_limit());
+        eframe::set_value(storage, COMMAND_HISTORY_KEY, &self.terminals.history());
+        eframe::set_value(storage, TERMINAL_PROFILES_KEY, &self.terminals.profiles());
+        eframe::set_value(storage, NOTIFY_SETTINGS_KEY, &self.terminals.notify_settings());
     }
 }
@@ -1,4 +1,9 @@
-use egui::{Ui, ScrollArea, Grid, RichText, Color32, Response, Sense, Vec2};
+use crate::actions::{self, CustomAction};
+use crate::fileops::{self, ClipboardMode, FileClipboard};
+use crate::history::Operation;
+use crate::jobs::{JobItem, JobKind, JobRequest};
+use egui::{Ui, ScrollArea, Grid, RichText, Color32, Event, Key, Modifiers, Response, Sense, Vec2};
+use std::ffi::OsStr;
 use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 use chrono::{DateTime, Local};
@@ -51,7 +56,7 @@ impl FileEntry {
 }
 
 fn get_file_icon(name: &str) -> String {
-    let ext = name.split('.').last().unwrap_or("").to_lowercase();
+    let ext = name.split('.').next_back().unwrap_or("").to_lowercase();
     match ext.as_str() {
         "rs" => "🦀",
         "py" => "🐍",
@@ -71,7 +76,7 @@ fn get_file_icon(name: &str) -> String {
     }.to_string()
 }
 
-fn format_size(size: u64) -> String {
+pub fn format_size(size: u64) -> String {
     const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
     let mut size = size as f64;
     let mut unit_idx = 0;
@@ -84,14 +89,43 @@ fn format_size(size: u64) -> String {
     format!("{:.1} {}", size, UNITS[unit_idx])
 }
 
+/// Drag-and-drop payload carrying explorer entries.
+pub struct DraggedPaths(pub Vec<PathBuf>);
+
 pub struct ExplorerPanel {
     current_path: PathBuf,
     entries: Vec<FileEntry>,
-    selected: Option<PathBuf>,
+    /// Selected entries in the order they were picked.
+    selected: Vec<PathBuf>,
+    /// Where a Shift+click range starts.
+    selection_anchor: Option<PathBuf>,
+    /// Paths to insert at the terminal's input cursor.
+    pending_insert: Option<Vec<PathBuf>>,
+    custom_actions: Vec<CustomAction>,
+    /// Why the actions file could not be loaded.
+    actions_error: Option<String>,
+    pending_action: Option<(CustomAction, Vec<PathBuf>)>,
     view_mode: ViewMode,
     pending_navigation: Option<PathBuf>,
     sort_by: SortBy,
     sort_descending: bool,
+    /// Scroll the selected entry into view on the next frame.
+    scroll_to_selected: bool,
+    clipboard: Option<FileClipboard>,
+    /// Entry being renamed in place, with the name typed so far.
+    renaming: Option<(PathBuf, String)>,
+    focus_rename: bool,
+    /// Entries waiting for the user to confirm their deletion.
+    confirm_delete: Option<Vec<PathBuf>>,
+    /// Outcome of the last file operation, for the status bar.
+    pending_status: Option<Result<String, String>>,
+    /// Copy, move or delete to hand to the job queue.
+    pending_job: Option<JobRequest>,
+    /// Rename or creation to record in the undo history.
+    pending_operation: Option<Operation>,
+    /// A new entry being named. Its creation is recorded once the name is
+    /// settled, so undoing does not stop at the default name.
+    naming_new: Option<PathBuf>,
 }
 
 #[derive(Clone, Copy, PartialEq)]
@@ -112,19 +146,660 @@ impl ExplorerPanel {
         let mut panel = Self {
             current_path: initial_path.clone(),
             entries: Vec::new(),
-            selected: None,
+            selected: Vec::new(),
+            selection_anchor: None,
+            pending_insert: None,
+            custom_actions: Vec::new(),
+            actions_error: None,
+            pending_action: None,
             view_mode: ViewMode::Icons,
             pending_navigation: None,
             sort_by: SortBy::Name,
             sort_descending: false,
+            scroll_to_selected: false,
+            clipboard: None,
+            renaming: None,
+            focus_rename: false,
+            confirm_delete: None,
+            pending_status: None,
+            pending_job: None,
+            pending_operation: None,
+            naming_new: None,
         };
         panel.refresh();
+        panel.reload_actions();
         panel
     }
     
+    /// Read the custom actions file again.
+    pub fn reload_actions(&mut self) {
+        match actions::load() {
+            Ok(loaded) => {
+                self.custom_actions = loaded;
+                self.actions_error = None;
+            }
+            Err(e) => self.actions_error = Some(e),
+        }
+    }
+    
+    /// A custom action picked from the context menu since the last call,
+    /// with the entries it applies to.
+    pub fn check_action(&mut self) -> Option<(CustomAction, Vec<PathBuf>)> {
+        self.pending_action.take()
+    }
+    
     pub fn navigate_to(&mut self, path: PathBuf) {
         self.current_path = path;
+        self.selected.clear();
+        self.selection_anchor = None;
+        self.cancel_rename();
+        self.refresh();
+    }
+    
+    /// Show `path`: a directory is opened, a file is selected in its
+    /// parent directory.
+    pub fn reveal(&mut self, path: &Path) {
+        if path.is_dir() {
+            self.navigate_to(path.to_path_buf());
+            return;
+        }
+        if let Some(parent) = path.parent() {
+            self.navigate_to(parent.to_path_buf());
+        }
+        self.selected = vec![path.to_path_buf()];
+        self.selection_anchor = Some(path.to_path_buf());
+        self.scroll_to_selected = true;
+    }
+    
+    pub fn selected_paths(&self) -> &[PathBuf] {
+        &self.selected
+    }
+    
+    fn is_selected(&self, path: &Path) -> bool {
+        self.selected.iter().any(|selected| selected == path)
+    }
+    
+    /// Ask for the selected entries to be typed into the terminal.
+    pub fn insert_selection_in_terminal(&mut self) {
+        if !self.selected.is_empty() {
+            self.pending_insert = Some(self.selected.clone());
+        }
+    }
+    
+    /// Paths the user asked to insert into the terminal since the last call.
+    pub fn check_insert(&mut self) -> Option<Vec<PathBuf>> {
+        self.pending_insert.take()
+    }
+    
+    /// Update the selection for a click on the entry at `index`: Ctrl
+    /// toggles it, Shift selects the range from the last clicked entry, and
+    /// a plain click selects only it and opens directories.
+    fn click_entry(&mut self, index: usize, modifiers: Modifiers) {
+        let Some(entry) = self.entries.get(index) else {
+            return;
+        };
+        let path = entry.path.clone();
+        if modifiers.command {
+            if self.is_selected(&path) {
+                self.selected.retain(|selected| *selected != path);
+            } else {
+                self.selected.push(path.clone());
+            }
+            self.selection_anchor = Some(path);
+        } else if modifiers.shift {
+            let anchor = self
+                .selection_anchor
+                .as_ref()
+                .and_then(|anchor| self.entries.iter().position(|e| &e.path == anchor))
+                .unwrap_or(index);
+            let range = anchor.min(index)..=anchor.max(index);
+            self.selected = self.entries[range].iter().map(|e| e.path.clone()).collect();
+        } else {
+            self.selected = vec![path.clone()];
+            self.selection_anchor = Some(path.clone());
+            if entry.is_dir {
+                self.pending_navigation = Some(path);
+            }
+        }
+    }
+    
+    /// Clicks, drags and the context menu of the entry at `index`.
+    fn handle_entry_response(&mut self, ui: &Ui, response: &Response, index: usize) {
+        let Some(entry) = self.entries.get(index).cloned() else {
+            return;
+        };
+        
+        // Dragging a selected entry drags the whole selection
+        if response.drag_started() {
+            let paths = if self.is_selected(&entry.path) {
+                self.selected.clone()
+            } else {
+                vec![entry.path.clone()]
+            };
+            response.dnd_set_drag_payload(DraggedPaths(paths));
+        }
+        
+        if response.clicked() {
+            self.click_entry(index, ui.input(|i| i.modifiers));
+        }
+        if response.double_clicked() && !entry.is_dir {
+            open_file(&entry.path);
+        }
+        
+        response.context_menu(|ui| {
+            // Right-clicking outside the selection acts on that entry alone
+            if !self.is_selected(&entry.path) {
+                self.selected = vec![entry.path.clone()];
+                self.selection_anchor = Some(entry.path.clone());
+            }
+            let count = self.selected.len();
+            let label = if count == 1 {
+                "Insert path in terminal".to_string()
+            } else {
+                format!("Insert {} paths in terminal", count)
+            };
+            if ui.button(label).clicked() {
+                self.insert_selection_in_terminal();
+                ui.ctx().request_repaint();
+                ui.close();
+            }
+            if ui.button("Copy path").clicked() {
+                let paths: Vec<String> = self.selected.iter().map(|p| p.display().to_string()).collect();
+                ui.ctx().copy_text(paths.join("\n"));
+                ui.close();
+            }
+            ui.separator();
+            self.render_file_operations(ui);
+            self.render_custom_actions(ui);
+        });
+    }
+    
+    /// Context menu entries acting on the selected files.
+    fn render_file_operations(&mut self, ui: &mut Ui) {
+        if ui.add(egui::Button::new("Cut").shortcut_text("Ctrl+X")).clicked() {
+            self.copy_selection(ui.ctx(), ClipboardMode::Cut);
+            ui.close();
+        }
+        if ui.add(egui::Button::new("Copy").shortcut_text("Ctrl+C")).clicked() {
+            self.copy_selection(ui.ctx(), ClipboardMode::Copy);
+            ui.close();
+        }
+        self.render_paste_button(ui);
+        if ui.add(egui::Button::new("Duplicate").shortcut_text("Ctrl+D")).clicked() {
+            self.duplicate_selection();
+            ui.close();
+        }
+        let single = self.selected.len() == 1;
+        if ui
+            .add_enabled(single, egui::Button::new("Rename").shortcut_text("F2"))
+            .clicked()
+        {
+            self.start_rename();
+            ui.close();
+        }
+        ui.separator();
+        if ui.add(egui::Button::new("Move to Trash").shortcut_text("Del")).clicked() {
+            self.trash_selection();
+            ui.close();
+        }
+        let delete = RichText::new("Delete permanently").color(Color32::LIGHT_RED);
+        if ui.add(egui::Button::new(delete).shortcut_text("Shift+Del")).clicked() {
+            self.confirm_delete = Some(self.selected.clone());
+            ui.close();
+        }
+    }
+    
+    fn render_paste_button(&mut self, ui: &mut Ui) {
+        let label = match &self.clipboard {
+            Some(clipboard) if clipboard.paths.len() > 1 => format!("Paste {} items", clipboard.paths.len()),
+            _ => "Paste".to_string(),
+        };
+        if ui
+            .add_enabled(self.clipboard.is_some(), egui::Button::new(label).shortcut_text("Ctrl+V"))
+            .clicked()
+        {
+            self.paste();
+            ui.close();
+        }
+    }
+    
+    /// Context menu of the empty space around the entries.
+    fn render_background_menu(&mut self, ui: &mut Ui) {
+        if ui.add(egui::Button::new("New file").shortcut_text("Ctrl+N")).clicked() {
+            self.create_entry(false);
+            ui.close();
+        }
+        if ui.add(egui::Button::new("New folder").shortcut_text("Ctrl+Shift+N")).clicked() {
+            self.create_entry(true);
+            ui.close();
+        }
+        ui.separator();
+        self.render_paste_button(ui);
+    }
+    
+    /// Space below the entries: clicking it clears the selection and
+    /// right-clicking it offers to create entries.
+    fn render_background(&mut self, ui: &mut Ui) {
+        let size = Vec2::new(ui.available_width(), ui.available_height().max(40.0));
+        let response = ui.allocate_response(size, Sense::click());
+        if response.clicked() {
+            self.selected.clear();
+            self.selection_anchor = None;
+        }
+        response.context_menu(|ui| self.render_background_menu(ui));
+    }
+    
+    /// Outcome of the last file operation, once.
+    pub fn check_status(&mut self) -> Option<Result<String, String>> {
+        self.pending_status.take()
+    }
+    
+    /// A rename or creation done since the last call, for the undo history.
+    pub fn check_operation(&mut self) -> Option<Operation> {
+        self.pending_operation.take()
+    }
+    
+    /// Report what an operation did: `done` succeeded, `errors` did not.
+    fn report(&mut self, done: String, errors: Vec<String>) {
+        self.pending_status = Some(match errors.as_slice() {
+            [] => Ok(done),
+            [error] => Err(error.clone()),
+            [first, rest @ ..] => Err(format!("{} (and {} more errors)", first, rest.len())),
+        });
+    }
+    
+    /// Select `paths` after the listing has been refreshed.
+    fn select_after_refresh(&mut self, paths: Vec<PathBuf>) {
         self.refresh();
+        self.selection_anchor = paths.first().cloned();
+        self.selected = paths;
+        self.scroll_to_selected = true;
+    }
+    
+    /// Create an empty file or folder with a free name and start renaming it.
+    fn create_entry(&mut self, folder: bool) {
+        let name = if folder { "New Folder" } else { "New File" };
+        let path = fileops::unique_path(&self.current_path, name);
+        let result = if folder {
+            fileops::create_dir(&path)
+        } else {
+            fileops::create_file(&path)
+        };
+        match result {
+            Ok(()) => {
+                self.select_after_refresh(vec![path.clone()]);
+                self.start_rename();
+                self.naming_new = Some(path);
+                self.report(format!("Created {}", name.to_lowercase()), Vec::new());
+            }
+            Err(e) => self.report(String::new(), vec![fileops::describe_error("create", &path, &e)]),
+        }
+    }
+    
+    /// Put the selection on the clipboard. The paths also go on the system
+    /// clipboard as text, which is also what makes Ctrl+V reach the explorer.
+    fn copy_selection(&mut self, ctx: &egui::Context, mode: ClipboardMode) {
+        if self.selected.is_empty() {
+            return;
+        }
+        let paths: Vec<String> = self.selected.iter().map(|p| p.display().to_string()).collect();
+        ctx.copy_text(paths.join("\n"));
+        let verb = match mode {
+            ClipboardMode::Copy => "Copied",
+            ClipboardMode::Cut => "Cut",
+        };
+        let done = match self.selected.as_slice() {
+            [path] => format!("{} {}", verb, path.display()),
+            paths => format!("{} {} items", verb, paths.len()),
+        };
+        self.clipboard = Some(FileClipboard {
+            mode,
+            paths: self.selected.clone(),
+        });
+        self.report(done, Vec::new());
+    }
+    
+    /// Queue copying or moving the clipboard entries into the current
+    /// directory.
+    fn paste(&mut self) {
+        let Some(clipboard) = self.clipboard.clone() else {
+            return;
+        };
+        let kind = match clipboard.mode {
+            ClipboardMode::Copy => JobKind::Copy,
+            ClipboardMode::Cut => JobKind::Move,
+        };
+        let items: Vec<JobItem> = clipboard
+            .paths
+            .iter()
+            .filter_map(|source| {
+                let target = fileops::paste_target(source, &self.current_path, clipboard.mode)?;
+                // Cutting and pasting in the same directory changes nothing
+                (target != *source).then(|| JobItem {
+                    source: source.clone(),
+                    target: Some(target),
+                })
+            })
+            .collect();
+        if clipboard.mode == ClipboardMode::Cut {
+            // The originals are about to move, so they cannot be pasted again
+            self.clipboard = None;
+        }
+        self.queue_job(kind, items);
+    }
+    
+    /// Queue copying each selected entry next to itself under a free name.
+    fn duplicate_selection(&mut self) {
+        let items = self
+            .selected
+            .iter()
+            .filter_map(|source| {
+                let (dir, name) = (source.parent()?, source.file_name()?);
+                Some(JobItem {
+                    source: source.clone(),
+                    target: Some(fileops::unique_path(dir, &name.to_string_lossy())),
+                })
+            })
+            .collect();
+        self.queue_job(JobKind::Copy, items);
+    }
+    
+    fn queue_job(&mut self, kind: JobKind, items: Vec<JobItem>) {
+        if !items.is_empty() {
+            self.pending_job = Some(JobRequest { kind, items });
+        }
+    }
+    
+    /// A copy, move or delete the user started since the last call.
+    pub fn check_job(&mut self) -> Option<JobRequest> {
+        self.pending_job.take()
+    }
+    
+    /// Show the outcome of a finished job: the listing is read again and
+    /// new entries in this directory are selected.
+    pub fn job_finished(&mut self, completed: &[JobItem]) {
+        let created: Vec<PathBuf> = completed
+            .iter()
+            .filter_map(|item| item.target.clone())
+            .filter(|path| path.parent() == Some(self.current_path.as_path()) && path.exists())
+            .collect();
+        if created.is_empty() {
+            self.selected.retain(|selected| selected.exists());
+            self.refresh();
+        } else {
+            self.select_after_refresh(created);
+        }
+    }
+    
+    /// Edit the name of the single selected entry in place.
+    fn start_rename(&mut self) {
+        if let [path] = self.selected.as_slice() {
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            self.renaming = Some((path.clone(), name));
+            self.focus_rename = true;
+        }
+    }
+    
+    fn finish_rename(&mut self) {
+        let Some((path, name)) = self.renaming.take() else {
+            return;
+        };
+        let target = self.rename_entry(&path, &name);
+        if self.naming_new.take().is_some_and(|new| new == path) {
+            self.pending_operation = Some(Operation::Create {
+                path: target.unwrap_or(path),
+            });
+        } else if let Some(target) = target {
+            self.pending_operation = Some(Operation::Rename { from: path, to: target });
+        }
+    }
+    
+    fn cancel_rename(&mut self) {
+        self.renaming = None;
+        if let Some(path) = self.naming_new.take() {
+            self.pending_operation = Some(Operation::Create { path });
+        }
+    }
+    
+    /// Give `path` the name typed by the user. Returns its new path if it
+    /// was renamed.
+    fn rename_entry(&mut self, path: &Path, name: &str) -> Option<PathBuf> {
+        let name = match fileops::validate_name(name) {
+            Ok(name) => name,
+            Err(e) => {
+                self.report(String::new(), vec![e]);
+                return None;
+            }
+        };
+        if path.file_name().is_some_and(|old| old.to_string_lossy() == name) {
+            return None;
+        }
+        let target = path.with_file_name(name);
+        match fileops::rename(path, &target) {
+            Ok(()) => {
+                self.select_after_refresh(vec![target.clone()]);
+                self.report(format!("Renamed to {}", name), Vec::new());
+                Some(target)
+            }
+            Err(e) => {
+                self.report(String::new(), vec![fileops::describe_error("rename", path, &e)]);
+                None
+            }
+        }
+    }
+    
+    /// The text field replacing the name of the entry being renamed, shown
+    /// over `rect`.
+    fn render_rename_field(&mut self, ctx: &egui::Context, rect: egui::Rect) {
+        let focus = std::mem::take(&mut self.focus_rename);
+        let Some((_, name)) = &mut self.renaming else {
+            return;
+        };
+        let id = egui::Id::new("explorer_rename");
+        let mut commit = false;
+        let mut cancel = false;
+        egui::Area::new(id.with("area"))
+            .order(egui::Order::Foreground)
+            .fixed_pos(rect.min)
+            .show(ctx, |ui| {
+                let response = ui.add(
+                    egui::TextEdit::singleline(name)
+                        .id(id)
+                        .desired_width(rect.width().max(160.0)),
+                );
+                if focus {
+                    response.request_focus();
+                    // Select the name without its extension, ready to type over
+                    if let Some(mut state) = egui::TextEdit::load_state(ctx, id) {
+                        let stem = match name.rfind('.') {
+                            Some(dot) if dot > 0 => name[..dot].chars().count(),
+                            _ => name.chars().count(),
+                        };
+                        state.cursor.set_char_range(Some(egui::text::CCursorRange::two(
+                            egui::text::CCursor::new(0),
+                            egui::text::CCursor::new(stem),
+                        )));
+                        state.store(ctx, id);
+                    }
+                }
+                if response.lost_focus() {
+                    if ui.input(|i| i.key_pressed(Key::Escape)) {
+                        cancel = true;
+                    } else {
+                        commit = true;
+                    }
+                }
+            });
+        if cancel {
+            self.cancel_rename();
+        } else if commit {
+            self.finish_rename();
+        }
+    }
+    
+    /// Move the selection to the trash. Unlike permanent deletion this
+    /// needs no confirmation, as it can be restored.
+    fn trash_selection(&mut self) {
+        if self.selected.is_empty() {
+            return;
+        }
+        let items = self
+            .selected
+            .iter()
+            .map(|source| JobItem {
+                source: source.clone(),
+                target: None,
+            })
+            .collect();
+        self.queue_job(JobKind::Trash, items);
+    }
+    
+    fn render_delete_confirmation(&mut self, ctx: &egui::Context) {
+        let Some(paths) = &self.confirm_delete else {
+            return;
+        };
+        let question = match paths.as_slice() {
+            [path] => format!(
+                "Delete \"{}\" permanently?",
+                path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default()
+            ),
+            paths => format!("Delete {} items permanently?", paths.len()),
+        };
+        let mut confirmed = false;
+        let mut cancelled = false;
+        let modal = egui::Modal::new(egui::Id::new("explorer_confirm_delete")).show(ctx, |ui| {
+            ui.label(RichText::new(question).strong());
+            ui.label("This cannot be undone.");
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                let delete = egui::Button::new(RichText::new("Delete").color(Color32::LIGHT_RED));
+                if ui.add(delete).clicked() || ui.input(|i| i.key_pressed(Key::Enter)) {
+                    confirmed = true;
+                }
+                if ui.button("Cancel").clicked() {
+                    cancelled = true;
+                }
+            });
+        });
+        if confirmed {
+            if let Some(paths) = self.confirm_delete.take() {
+                let items = paths
+                    .into_iter()
+                    .map(|source| JobItem { source, target: None })
+                    .collect();
+                self.queue_job(JobKind::Delete, items);
+            }
+        } else if cancelled || modal.should_close() {
+            self.confirm_delete = None;
+        }
+    }
+    
+    /// Keyboard shortcuts for file operations, while no text field or
+    /// terminal has the keyboard.
+    fn handle_keys(&mut self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() || self.renaming.is_some() || self.confirm_delete.is_some() {
+            return;
+        }
+        let (copy, cut, paste) = ctx.input(|i| {
+            let shift = i.modifiers.shift;
+            // Some platforms also report Shift+Delete as cut
+            let shift_delete = shift && i.key_pressed(Key::Delete);
+            (
+                !shift && i.events.contains(&Event::Copy),
+                !shift_delete && i.events.contains(&Event::Cut),
+                i.events.iter().any(|event| matches!(event, Event::Paste(_))),
+            )
+        });
+        if copy {
+            self.copy_selection(ctx, ClipboardMode::Copy);
+        }
+        if cut {
+            self.copy_selection(ctx, ClipboardMode::Cut);
+        }
+        if paste {
+            self.paste();
+        }
+        ctx.input_mut(|i| {
+            if i.consume_key(Modifiers::NONE, Key::F2) {
+                self.start_rename();
+            }
+            if i.consume_key(Modifiers::SHIFT, Key::Delete) && !self.selected.is_empty() {
+                self.confirm_delete = Some(self.selected.clone());
+            } else if i.consume_key(Modifiers::NONE, Key::Delete) {
+                self.trash_selection();
+            }
+            if i.consume_key(Modifiers::COMMAND, Key::D) {
+                self.duplicate_selection();
+            }
+            if i.consume_key(Modifiers::COMMAND | Modifiers::SHIFT, Key::N) {
+                self.create_entry(true);
+            } else if i.consume_key(Modifiers::COMMAND, Key::N) {
+                self.create_entry(false);
+            }
+        });
+    }
+    
+    /// Context menu entries for the custom actions matching the selection.
+    fn render_custom_actions(&mut self, ui: &mut Ui) {
+        let matching: Vec<CustomAction> = self
+            .custom_actions
+            .iter()
+            .filter(|action| action.matches(&self.selected))
+            .cloned()
+            .collect();
+        if !matching.is_empty() {
+            ui.separator();
+        }
+        for action in matching {
+            if ui.button(&action.name).on_hover_text(&action.command).clicked() {
+                self.pending_action = Some((action, self.selected.clone()));
+                ui.ctx().request_repaint();
+                ui.close();
+            }
+        }
+        ui.separator();
+        ui.menu_button("Custom actions", |ui| {
+            if let Some(error) = &self.actions_error {
+                ui.label(RichText::new(error).color(Color32::LIGHT_RED));
+            }
+            if ui.button("Edit actions file").clicked() {
+                match actions::ensure_config() {
+                    Ok(path) => open_file(&path),
+                    Err(e) => self.actions_error = Some(format!("Failed to create the actions file: {}", e)),
+                }
+                ui.close();
+            }
+            if ui.button("Reload actions").clicked() {
+                self.reload_actions();
+                ui.close();
+            }
+        });
+    }
+    
+    /// Show what is being dragged next to the pointer.
+    fn render_drag_preview(&self, ctx: &egui::Context) {
+        let Some(payload) = egui::DragAndDrop::payload::<DraggedPaths>(ctx) else {
+            return;
+        };
+        let Some(pos) = ctx.pointer_interact_pos() else {
+            return;
+        };
+        let text = match payload.0.as_slice() {
+            [path] => path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| path.display().to_string()),
+            paths => format!("{} items", paths.len()),
+        };
+        let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Tooltip, egui::Id::new("explorer_drag")));
+        let galley = painter.layout_no_wrap(text, egui::FontId::proportional(13.0), ctx.style().visuals.text_color());
+        let rect = egui::Rect::from_min_size(pos + Vec2::new(14.0, 10.0), galley.size()).expand(4.0);
+        painter.rect_filled(rect, 4.0, ctx.style().visuals.window_fill);
+        painter.galley(rect.min + Vec2::splat(4.0), galley, Color32::WHITE);
+        ctx.set_cursor_icon(egui::CursorIcon::Grabbing);
     }
     
     pub fn check_navigation(&mut self) -> Option<PathBuf> {
@@ -162,10 +837,10 @@ impl ExplorerPanel {
                 });
             }
             SortBy::Size => {
-                self.entries.sort_by(|a, b| a.size.cmp(&b.size));
+                self.entries.sort_by_key(|e| e.size);
             }
             SortBy::Modified => {
-                self.entries.sort_by(|a, b| a.modified.cmp(&b.modified));
+                self.entries.sort_by_key(|e| e.modified);
             }
         }
         
@@ -188,7 +863,7 @@ impl ExplorerPanel {
             
             // Sort controls
             ui.label("Sort:");
-            egui::ComboBox::from_id_source("sort_by")
+            egui::ComboBox::from_id_salt("sort_by")
                 .selected_text(match self.sort_by {
                     SortBy::Name => "Name",
                     SortBy::Size => "Size",
@@ -218,16 +893,30 @@ impl ExplorerPanel {
                 if ui.button("🔄").clicked() {
                     self.refresh();
                 }
+                if ui
+                    .add_enabled(self.clipboard.is_some(), egui::Button::new("📋"))
+                    .on_hover_text("Paste (Ctrl+V)")
+                    .clicked()
+                {
+                    self.paste();
+                }
+                ui.menu_button("➕", |ui| self.render_background_menu(ui))
+                    .response
+                    .on_hover_text("New file or folder");
             });
         });
         
         ui.separator();
         
+        self.handle_keys(ui.ctx());
+        
         // Content area
         match self.view_mode {
             ViewMode::Icons => self.render_icon_view(ui),
             ViewMode::List => self.render_list_view(ui),
         }
+        self.render_drag_preview(ui.ctx());
+        self.render_delete_confirmation(ui.ctx());
     }
     
     fn render_icon_view(&mut self, ui: &mut Ui) {
@@ -238,8 +927,6 @@ impl ExplorerPanel {
         let columns = columns.max(1);
         
         let entries = self.entries.clone();
-        let mut clicked_entry: Option<usize> = None;
-        let mut double_clicked_entry: Option<usize> = None;
         
         ScrollArea::vertical().show(ui, |ui| {
             Grid::new("icon_grid")
@@ -253,40 +940,29 @@ impl ExplorerPanel {
                         }
                         
                         let response = self.render_icon_item(ui, entry, icon_size);
-                        if response.clicked() {
-                            clicked_entry = Some(i);
+                        if self.scroll_to_selected && self.is_selected(&entry.path) {
+                            response.scroll_to_me(Some(egui::Align::Center));
+                            self.scroll_to_selected = false;
                         }
-                        if response.double_clicked() {
-                            double_clicked_entry = Some(i);
+                        if self.renaming.as_ref().is_some_and(|(path, _)| *path == entry.path) {
+                            let name_rect = egui::Rect::from_min_max(
+                                response.rect.left_bottom() - Vec2::new(0.0, 22.0),
+                                response.rect.right_bottom(),
+                            );
+                            self.render_rename_field(ui.ctx(), name_rect);
                         }
+                        self.handle_entry_response(ui, &response, i);
                     }
                 });
+            self.render_background(ui);
         });
-        
-        // Apply interactions after the loop
-        if let Some(idx) = clicked_entry {
-            if let Some(entry) = entries.get(idx) {
-                self.selected = Some(entry.path.clone());
-                if entry.is_dir {
-                    self.pending_navigation = Some(entry.path.clone());
-                }
-            }
-        }
-        
-        if let Some(idx) = double_clicked_entry {
-            if let Some(entry) = entries.get(idx) {
-                if !entry.is_dir {
-                    open_file(&entry.path);
-                }
-            }
-        }
     }
     
     fn render_icon_item(&self, ui: &mut Ui, entry: &FileEntry, size: f32) -> Response {
-        let is_selected = self.selected.as_ref() == Some(&entry.path);
+        let is_selected = self.is_selected(&entry.path);
         let (rect, response) = ui.allocate_exact_size(
             Vec2::new(size, size + 30.0),
-            Sense::click(),
+            Sense::click_and_drag(),
         );
         
         let visuals = ui.style().interact(&response);
@@ -341,38 +1017,42 @@ impl ExplorerPanel {
                     ui.strong("Kind");
                     ui.end_row();
                     
-                    for entry in &self.entries {
-                        let is_selected = self.selected.as_ref() == Some(&entry.path);
+                    let entries = self.entries.clone();
+                    for (i, entry) in entries.iter().enumerate() {
+                        let is_selected = self.is_selected(&entry.path);
                         
                         let mut name_text = RichText::new(format!("{} {}", entry.icon, entry.name));
                         if is_selected {
                             name_text = name_text.color(ui.visuals().selection.stroke.color);
                         }
                         
-                        let response = ui.selectable_label(is_selected, name_text);
+                        let response = ui
+                            .selectable_label(is_selected, name_text)
+                            .interact(Sense::drag());
+                        if is_selected && self.scroll_to_selected {
+                            response.scroll_to_me(Some(egui::Align::Center));
+                            self.scroll_to_selected = false;
+                        }
                         ui.label(entry.format_size());
                         ui.label(entry.format_modified());
                         ui.label(if entry.is_dir { "Folder" } else { "File" });
                         
-                        if response.clicked() {
-                            self.selected = Some(entry.path.clone());
-                            if entry.is_dir {
-                                self.pending_navigation = Some(entry.path.clone());
-                            }
-                        }
-                        
-                        if response.double_clicked() && !entry.is_dir {
-                            open_file(&entry.path);
+                        if self.renaming.as_ref().is_some_and(|(path, _)| *path == entry.path) {
+                            self.render_rename_field(ui.ctx(), response.rect);
                         }
+                        self.handle_entry_response(ui, &response, i);
                         
                         ui.end_row();
                     }
                 });
+            self.render_background(ui);
         });
     }
 }
 
-fn open_file(path: &Path) {
+/// Open a file or URL with the system's default application.
+pub fn open_file(path: impl AsRef<OsStr>) {
+    let path = path.as_ref();
     #[cfg(target_os = "macos")]
     {
         let _ = std::process::Command::new("open")
@@ -388,7 +1068,8 @@ fn open_file(path: &Path) {
     #[cfg(target_os = "windows")]
     {
         let _ = std::process::Command::new("cmd")
-            .args(["/C", "start", "", path.to_str().unwrap_or("")])
+            .args(["/C", "start", ""])
+            .arg(path)
             .spawn();
     }
 }
@@ -0,0 +1,339 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Whether pasting copies the clipboard entries or moves them.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ClipboardMode {
+    Copy,
+    Cut,
+}
+
+/// Entries copied or cut in the explorer, waiting to be pasted.
+#[derive(Clone, Debug)]
+pub struct FileClipboard {
+    pub mode: ClipboardMode,
+    pub paths: Vec<PathBuf>,
+}
+
+/// Check a name typed by the user for a new or renamed entry.
+pub fn validate_name(name: &str) -> Result<&str, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("The name cannot be empty".to_string());
+    }
+    if name == "." || name == ".." {
+        return Err(format!("\"{}\" is not a valid name", name));
+    }
+    if name.contains('/') || (cfg!(target_os = "windows") && name.contains('\\')) {
+        return Err(format!("\"{}\" cannot contain a path separator", name));
+    }
+    Ok(name)
+}
+
+/// A path in `dir` named after `name` that does not exist yet:
+/// `name`, then `stem copy.ext`, `stem copy 2.ext` and so on.
+pub fn unique_path(dir: &Path, name: &str) -> PathBuf {
+    let candidate = dir.join(name);
+    if fs::symlink_metadata(&candidate).is_err() {
+        return candidate;
+    }
+    let (stem, extension) = split_extension(name);
+    (1..)
+        .map(|n| {
+            let suffix = if n == 1 { " copy".to_string() } else { format!(" copy {}", n) };
+            dir.join(format!("{}{}{}", stem, suffix, extension))
+        })
+        .find(|path| fs::symlink_metadata(path).is_err())
+        .expect("some copy name is free")
+}
+
+/// The first free path of `name (2).ext`, `name (3).ext`... next to
+/// `path`, for keeping both entries when one would overwrite the other.
+pub fn numbered_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let (stem, extension) = split_extension(&name);
+    (2..)
+        .map(|n| dir.join(format!("{} ({}){}", stem, n, extension)))
+        .find(|path| fs::symlink_metadata(path).is_err())
+        .expect("some numbered name is free")
+}
+
+/// The temporary file a copy to `path` is written to before it takes its
+/// place.
+pub fn partial_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{}.part", name))
+}
+
+/// A free hidden path next to `path` to keep an entry being overwritten
+/// until its replacement is complete.
+pub fn backup_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    (1..)
+        .map(|n| match n {
+            1 => path.with_file_name(format!(".{}.old", name)),
+            n => path.with_file_name(format!(".{}.old{}", name, n)),
+        })
+        .find(|path| fs::symlink_metadata(path).is_err())
+        .expect("some backup name is free")
+}
+
+/// Split `name` before its extension, keeping the dot with the extension.
+/// Hidden files without an extension keep their leading dot in the stem.
+fn split_extension(name: &str) -> (&str, &str) {
+    match name.rfind('.') {
+        Some(dot) if dot > 0 => name.split_at(dot),
+        _ => (name, ""),
+    }
+}
+
+/// Create an empty file, failing if something already has that name.
+pub fn create_file(path: &Path) -> io::Result<()> {
+    fs::OpenOptions::new().write(true).create_new(true).open(path).map(|_| ())
+}
+
+pub fn create_dir(path: &Path) -> io::Result<()> {
+    fs::create_dir(path)
+}
+
+/// Rename an entry within its directory, refusing to replace another one.
+pub fn rename(from: &Path, to: &Path) -> io::Result<()> {
+    // On case-insensitive systems a case-only rename finds the entry itself
+    if fs::symlink_metadata(to).is_ok() && !same_entry(from, to) {
+        return Err(already_exists(to));
+    }
+    fs::rename(from, to)
+}
+
+/// Whether both paths name the same entry, not just equal-looking ones.
+#[cfg(unix)]
+fn same_entry(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (fs::symlink_metadata(a), fs::symlink_metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn same_entry(a: &Path, b: &Path) -> bool {
+    // Both resolve to the entry's real name when they refer to the same one
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Copy a file, a symbolic link or a whole directory tree to `to`, which
+/// must not exist.
+pub fn copy(from: &Path, to: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(to).is_ok() {
+        return Err(already_exists(to));
+    }
+    if to.starts_with(from) {
+        return Err(io::Error::other(format!(
+            "Cannot copy {} into itself",
+            from.display()
+        )));
+    }
+    copy_entry(from, to)
+}
+
+fn copy_entry(from: &Path, to: &Path) -> io::Result<()> {
+    let metadata = fs::symlink_metadata(from)?;
+    if metadata.file_type().is_symlink() {
+        copy_symlink(from, to)
+    } else if metadata.is_dir() {
+        fs::create_dir(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_entry(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        fs::set_permissions(to, metadata.permissions())
+    } else {
+        fs::copy(from, to).map(|_| ())
+    }
+}
+
+#[cfg(unix)]
+fn copy_symlink(from: &Path, to: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(fs::read_link(from)?, to)
+}
+
+#[cfg(not(unix))]
+fn copy_symlink(from: &Path, to: &Path) -> io::Result<()> {
+    // Creating links needs privileges on Windows; copy what it points to
+    copy_entry(&fs::canonicalize(from)?, to)
+}
+
+/// Delete a file, a symbolic link or a directory with everything in it.
+pub fn delete(path: &Path) -> io::Result<()> {
+    let metadata = fs::symlink_metadata(path)?;
+    if metadata.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+/// Where pasting `source` into `dir` puts it. Copying into the source's own
+/// directory picks a free "copy" name instead of clashing with the original.
+pub fn paste_target(source: &Path, dir: &Path, mode: ClipboardMode) -> Option<PathBuf> {
+    let name = source.file_name()?;
+    if mode == ClipboardMode::Copy && source.parent() == Some(dir) {
+        return Some(unique_path(dir, &name.to_string_lossy()));
+    }
+    Some(dir.join(name))
+}
+
+fn already_exists(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        format!("{} already exists", path.display()),
+    )
+}
+
+/// A short description of an error on `path` for the status bar.
+pub fn describe_error(action: &str, path: &Path, error: &io::Error) -> String {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string());
+    format!("Failed to {} {}: {}", action, name, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(path: &Path, contents: &str) {
+        fs::write(path, contents).unwrap();
+    }
+
+    fn read(path: &Path) -> String {
+        fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn rename_refuses_a_case_variant_of_another_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let (lower, upper) = (dir.path().join("a"), dir.path().join("A"));
+        write(&lower, "lower");
+        write(&upper, "upper");
+        if same_entry(&lower, &upper) {
+            // A case-insensitive file system cannot hold both names
+            return;
+        }
+
+        let error = rename(&lower, &upper).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(read(&lower), "lower");
+        assert_eq!(read(&upper), "upper");
+    }
+
+    #[test]
+    fn rename_changes_only_the_case_of_an_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let (lower, upper) = (dir.path().join("a"), dir.path().join("A"));
+        write(&lower, "lower");
+
+        rename(&lower, &upper).unwrap();
+        assert_eq!(read(&upper), "lower");
+        let names: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(names, ["A"]);
+    }
+
+    #[test]
+    fn rename_refuses_an_existing_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let (from, to) = (dir.path().join("from.txt"), dir.path().join("to.txt"));
+        write(&from, "from");
+        write(&to, "to");
+
+        let error = rename(&from, &to).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(read(&to), "to");
+    }
+
+    #[test]
+    fn copy_refuses_an_existing_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let (from, to) = (dir.path().join("from.txt"), dir.path().join("to.txt"));
+        write(&from, "from");
+        write(&to, "to");
+
+        let error = copy(&from, &to).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(read(&to), "to");
+    }
+
+    #[test]
+    fn copy_refuses_to_copy_a_folder_into_itself() {
+        let dir = tempfile::tempdir().unwrap();
+        let folder = dir.path().join("folder");
+        fs::create_dir(&folder).unwrap();
+
+        assert!(copy(&folder, &folder.join("inner")).is_err());
+        assert!(fs::symlink_metadata(folder.join("inner")).is_err());
+    }
+
+    #[test]
+    fn copy_copies_a_folder_tree() {
+        let dir = tempfile::tempdir().unwrap();
+        let from = dir.path().join("from");
+        fs::create_dir_all(from.join("sub")).unwrap();
+        write(&from.join("sub").join("file.txt"), "contents");
+
+        let to = dir.path().join("to");
+        copy(&from, &to).unwrap();
+        assert_eq!(read(&to.join("sub").join("file.txt")), "contents");
+        assert_eq!(read(&from.join("sub").join("file.txt")), "contents");
+    }
+
+    #[test]
+    fn unique_path_adds_copy_before_the_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(unique_path(dir.path(), "notes.txt"), dir.path().join("notes.txt"));
+
+        write(&dir.path().join("notes.txt"), "");
+        assert_eq!(unique_path(dir.path(), "notes.txt"), dir.path().join("notes copy.txt"));
+
+        write(&dir.path().join("notes copy.txt"), "");
+        assert_eq!(unique_path(dir.path(), "notes.txt"), dir.path().join("notes copy 2.txt"));
+    }
+
+    #[test]
+    fn numbered_path_skips_taken_numbers() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("photo.jpg");
+        assert_eq!(numbered_path(&path), dir.path().join("photo (2).jpg"));
+
+        write(&dir.path().join("photo (2).jpg"), "");
+        assert_eq!(numbered_path(&path), dir.path().join("photo (3).jpg"));
+        assert_eq!(numbered_path(&dir.path().join(".bashrc")), dir.path().join(".bashrc (2)"));
+    }
+
+    #[test]
+    fn validate_name_rejects_empty_dots_and_separators() {
+        assert_eq!(validate_name("  report.pdf "), Ok("report.pdf"));
+        assert!(validate_name("   ").is_err());
+        assert!(validate_name(".").is_err());
+        assert!(validate_name("..").is_err());
+        assert!(validate_name("a/b").is_err());
+        assert_eq!(validate_name("...hidden"), Ok("...hidden"));
+    }
+}
@@ -0,0 +1,708 @@
+use crate::fileops;
+use crate::jobs::{FinishedJob, JobItem, JobKind, JobRequest};
+use crate::trash::{self, TrashItem};
+use chrono::{DateTime, Local};
+use egui::{Key, Modifiers, RichText, ScrollArea};
+use std::path::{Path, PathBuf};
+
+/// Operations kept for undoing; older ones are forgotten.
+const HISTORY_LIMIT: usize = 100;
+
+/// A file operation done through the explorer.
+#[derive(Clone, Debug)]
+pub enum Operation {
+    Rename { from: PathBuf, to: PathBuf },
+    Create { path: PathBuf },
+    /// Each source with the copy made of it.
+    Copy { items: Vec<(PathBuf, PathBuf)> },
+    /// Each entry's old and new path.
+    Move { items: Vec<(PathBuf, PathBuf)> },
+    Trash { items: Vec<TrashItem> },
+}
+
+impl Operation {
+    /// Build the operation a finished explorer job did. Deleting for good
+    /// cannot be undone, so it is not recorded.
+    fn from_job(job: &FinishedJob) -> Option<Self> {
+        let pairs = || -> Vec<(PathBuf, PathBuf)> {
+            job.completed
+                .iter()
+                .filter_map(|item| Some((item.source.clone(), item.target.clone()?)))
+                .collect()
+        };
+        let operation = match job.kind {
+            JobKind::Copy => Operation::Copy { items: pairs() },
+            JobKind::Move => Operation::Move { items: pairs() },
+            JobKind::Trash => Operation::Trash {
+                items: pairs().iter().filter_map(|(_, target)| TrashItem::at(target)).collect(),
+            },
+            JobKind::Delete => return None,
+        };
+        (operation.len() > 0).then_some(operation)
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Operation::Rename { .. } | Operation::Create { .. } => 1,
+            Operation::Copy { items } | Operation::Move { items } => items.len(),
+            Operation::Trash { items } => items.len(),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        let what = |paths: &mut dyn Iterator<Item = &Path>| match (paths.next(), paths.count()) {
+            (Some(path), 0) => format!("\"{}\"", name(path)),
+            (_, rest) => format!("{} items", rest + 1),
+        };
+        match self {
+            Operation::Rename { from, to } => format!("Rename \"{}\" to \"{}\"", name(from), name(to)),
+            Operation::Create { path } => format!("Create \"{}\"", name(path)),
+            Operation::Copy { items } => {
+                format!("Copy {}", what(&mut items.iter().map(|(source, _)| source.as_path())))
+            }
+            Operation::Move { items } => {
+                let into = items
+                    .first()
+                    .and_then(|(_, target)| target.parent())
+                    .map(|dir| format!(" to {}", dir.display()))
+                    .unwrap_or_default();
+                format!("Move {}{}", what(&mut items.iter().map(|(source, _)| source.as_path())), into)
+            }
+            Operation::Trash { items } => {
+                format!("Trash {}", what(&mut items.iter().map(|item| item.original.as_path())))
+            }
+        }
+    }
+}
+
+fn name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+struct Entry {
+    operation: Operation,
+    time: DateTime<Local>,
+    /// Entries put in the trash by undoing a create or a copy, restored
+    /// when it is redone.
+    trashed: Vec<TrashItem>,
+}
+
+/// What undoing or redoing an entry got done.
+struct Step {
+    /// A move to run on the job queue. The entry only counts as undone or
+    /// redone once the job has moved something.
+    job: Option<JobRequest>,
+    errors: Vec<String>,
+    /// Whether anything was done at all; if not, the entry stays where it
+    /// is in the history.
+    applied: bool,
+}
+
+impl Step {
+    /// The outcome of doing something to each of `count` entries.
+    fn of(count: usize, errors: Vec<String>) -> Self {
+        Step {
+            job: None,
+            applied: errors.len() < count,
+            errors,
+        }
+    }
+
+    fn job(request: JobRequest) -> Self {
+        Step {
+            job: Some(request),
+            errors: Vec::new(),
+            applied: false,
+        }
+    }
+}
+
+/// Why the history waits for a job.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Tracked {
+    /// An explorer operation, recorded once it finishes.
+    Operation,
+    /// Undoing the entry at this index.
+    Undo(usize),
+    /// Redoing the entry at this index.
+    Redo(usize),
+}
+
+/// The journal of file operations done through the explorer, for Ctrl+Z
+/// and Ctrl+Shift+Z, and the window listing it.
+#[derive(Default)]
+pub struct History {
+    entries: Vec<Entry>,
+    /// How many entries are in effect; the ones after were undone and can
+    /// be redone.
+    done: usize,
+    /// Jobs whose outcome the history needs, by id.
+    tracked: Vec<(u64, Tracked)>,
+    open: bool,
+    pending_job: Option<JobRequest>,
+    /// The undo or redo that `pending_job` does, until it is submitted.
+    pending_step: Option<Tracked>,
+    pending_status: Option<Result<String, String>>,
+}
+
+impl History {
+    /// Add an operation, dropping whatever was undone before it.
+    pub fn record(&mut self, operation: Operation) {
+        self.forget_steps();
+        self.entries.truncate(self.done);
+        self.entries.push(Entry {
+            operation,
+            time: Local::now(),
+            trashed: Vec::new(),
+        });
+        if self.entries.len() > HISTORY_LIMIT {
+            self.entries.remove(0);
+        }
+        self.done = self.entries.len();
+    }
+
+    /// Record what the job `id`, started from the explorer, did once it
+    /// finishes.
+    pub fn track(&mut self, id: u64) {
+        self.tracked.push((id, Tracked::Operation));
+    }
+
+    /// The job `check_job` handed out was queued as `id`, or not at all.
+    pub fn job_submitted(&mut self, id: Option<u64>) {
+        if let (Some(id), Some(step)) = (id, self.pending_step.take()) {
+            self.tracked.push((id, step));
+        }
+    }
+
+    /// Whether an undone or redone move is still waiting to run.
+    fn stepping(&self) -> bool {
+        self.pending_step.is_some() || self.tracked.iter().any(|(_, tracked)| *tracked != Tracked::Operation)
+    }
+
+    /// Stop waiting for undone or redone moves, whose entries may no longer
+    /// be where they were.
+    fn forget_steps(&mut self) {
+        self.tracked.retain(|(_, tracked)| *tracked == Tracked::Operation);
+        self.pending_step = None;
+    }
+
+    pub fn job_finished(&mut self, job: &FinishedJob) {
+        let Some(index) = self.tracked.iter().position(|(id, _)| *id == job.id) else {
+            return;
+        };
+        let (_, tracked) = self.tracked.remove(index);
+        let done = match tracked {
+            Tracked::Operation => return self.record_job(job),
+            Tracked::Undo(index) => index,
+            Tracked::Redo(index) => index + 1,
+        };
+        // A move that moved nothing leaves its entry where it was
+        if !job.completed.is_empty() {
+            self.done = done;
+        }
+    }
+
+    fn record_job(&mut self, job: &FinishedJob) {
+        let Some(operation) = Operation::from_job(job) else {
+            return;
+        };
+        // Undoing could not bring back what was overwritten
+        if job.replaced > 0 {
+            if let Ok(message) = &job.result {
+                self.pending_status = Some(Ok(format!(
+                    "{}; it replaced existing entries, so it cannot be undone",
+                    message
+                )));
+            }
+            return;
+        }
+        self.record(operation);
+    }
+
+    /// A move undone or redone, to hand to the job queue.
+    pub fn check_job(&mut self) -> Option<JobRequest> {
+        self.pending_job.take()
+    }
+
+    pub fn check_status(&mut self) -> Option<Result<String, String>> {
+        self.pending_status.take()
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    /// What Ctrl+Z would undo.
+    pub fn next_undo(&self) -> Option<String> {
+        let entry = self.entries.get(self.done.checked_sub(1)?)?;
+        Some(entry.operation.describe())
+    }
+
+    /// What Ctrl+Shift+Z would redo.
+    pub fn next_redo(&self) -> Option<String> {
+        Some(self.entries.get(self.done)?.operation.describe())
+    }
+
+    pub fn undo(&mut self) {
+        let Some(index) = self.done.checked_sub(1) else {
+            return;
+        };
+        if self.refuse_while_stepping() {
+            return;
+        }
+        let entry = &mut self.entries[index];
+        let step = undo(entry);
+        let description = entry.operation.describe();
+        if step.applied {
+            self.done = index;
+        }
+        self.finish(step, Tracked::Undo(index), "Undid", description);
+    }
+
+    pub fn redo(&mut self) {
+        let index = self.done;
+        if index >= self.entries.len() || self.refuse_while_stepping() {
+            return;
+        }
+        let entry = &mut self.entries[index];
+        let step = redo(entry);
+        let description = entry.operation.describe();
+        if step.applied {
+            self.done = index + 1;
+        }
+        self.finish(step, Tracked::Redo(index), "Redid", description);
+    }
+
+    /// Undoing or redoing further has to wait until a queued move is done,
+    /// since the history only moves along once it has run.
+    fn refuse_while_stepping(&mut self) -> bool {
+        let stepping = self.stepping();
+        if stepping {
+            self.pending_status = Some(Err("Wait until the last undo or redo has finished".to_string()));
+        }
+        stepping
+    }
+
+    fn finish(&mut self, step: Step, tracked: Tracked, verb: &str, description: String) {
+        let queued = step.job.is_some();
+        if queued {
+            self.pending_step = Some(tracked);
+        }
+        self.pending_job = step.job;
+        self.pending_status = Some(match step.errors.as_slice() {
+            [] if queued => Ok(format!("{} (queued): {}", verb, description)),
+            [] => Ok(format!("{}: {}", verb, description)),
+            [error] => Err(error.clone()),
+            [first, rest @ ..] => Err(format!("{} (and {} more errors)", first, rest.len())),
+        });
+    }
+
+    /// Ctrl+Z and Ctrl+Shift+Z (or Ctrl+Y), unless a text field or the
+    /// terminal has the keyboard.
+    pub fn handle_keys(&mut self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+        let (undo, redo) = ctx.input_mut(|i| {
+            let redo = i.consume_key(Modifiers::COMMAND | Modifiers::SHIFT, Key::Z)
+                || i.consume_key(Modifiers::COMMAND, Key::Y);
+            (!redo && i.consume_key(Modifiers::COMMAND, Key::Z), redo)
+        });
+        if undo {
+            self.undo();
+        }
+        if redo {
+            self.redo();
+        }
+    }
+
+    pub fn render(&mut self, ctx: &egui::Context) {
+        if !self.open {
+            return;
+        }
+        let mut open = self.open;
+        egui::Window::new("History")
+            .open(&mut open)
+            .default_size([420.0, 320.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let undo = self.next_undo();
+                    let button = ui.add_enabled(undo.is_some(), egui::Button::new("⟲ Undo").shortcut_text("Ctrl+Z"));
+                    if button.on_hover_text(undo.unwrap_or_default()).clicked() {
+                        self.undo();
+                        ctx.request_repaint();
+                    }
+                    let redo = self.next_redo();
+                    let button =
+                        ui.add_enabled(redo.is_some(), egui::Button::new("⟳ Redo").shortcut_text("Ctrl+Shift+Z"));
+                    if button.on_hover_text(redo.unwrap_or_default()).clicked() {
+                        self.redo();
+                        ctx.request_repaint();
+                    }
+                    if ui.add_enabled(!self.entries.is_empty(), egui::Button::new("Clear")).clicked() {
+                        self.forget_steps();
+                        self.entries.clear();
+                        self.done = 0;
+                    }
+                });
+                ui.separator();
+                if self.entries.is_empty() {
+                    ui.label(RichText::new("Nothing to undo").weak());
+                }
+                ScrollArea::vertical().show(ui, |ui| {
+                    for (index, entry) in self.entries.iter().enumerate().rev() {
+                        let undone = index >= self.done;
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new(entry.time.format("%H:%M:%S").to_string()).weak().monospace());
+                            let text = RichText::new(entry.operation.describe());
+                            if undone {
+                                ui.label(text.weak().strikethrough());
+                                ui.label(RichText::new("undone").weak().italics());
+                            } else {
+                                ui.label(text);
+                            }
+                        });
+                    }
+                });
+            });
+        self.open = open;
+    }
+}
+
+/// Reverse an entry: renames go back, created entries go to the trash,
+/// moves are queued the other way and trashed entries are restored.
+fn undo(entry: &mut Entry) -> Step {
+    match &mut entry.operation {
+        Operation::Rename { from, to } => rename(to, from),
+        Operation::Create { path } => {
+            let (trashed, errors) = trash_all([path.as_path()]);
+            entry.trashed = trashed;
+            Step::of(1, errors)
+        }
+        Operation::Copy { items } => {
+            let (trashed, errors) = trash_all(items.iter().map(|(_, copy)| copy.as_path()));
+            entry.trashed = trashed;
+            Step::of(items.len(), errors)
+        }
+        Operation::Move { items } => move_back(items.iter().map(|(from, to)| (to, from))),
+        Operation::Trash { items } => {
+            let errors = restore_all(items);
+            Step::of(items.len(), errors)
+        }
+    }
+}
+
+/// Do an undone entry again.
+fn redo(entry: &mut Entry) -> Step {
+    match &mut entry.operation {
+        Operation::Rename { from, to } => rename(from, to),
+        Operation::Create { .. } | Operation::Copy { .. } => {
+            let count = entry.trashed.len();
+            let errors = restore_all(&entry.trashed);
+            entry.trashed.clear();
+            Step::of(count, errors)
+        }
+        Operation::Move { items } => move_back(items.iter().map(|(from, to)| (from, to))),
+        Operation::Trash { items } => {
+            let count = items.len();
+            let (trashed, errors) = trash_all(items.iter().map(|item| item.original.as_path()));
+            *items = trashed;
+            Step::of(count, errors)
+        }
+    }
+}
+
+fn rename(from: &Path, to: &Path) -> Step {
+    let errors = match fileops::rename(from, to) {
+        Ok(()) => Vec::new(),
+        Err(e) => vec![fileops::describe_error("rename", from, &e)],
+    };
+    Step::of(1, errors)
+}
+
+/// Queue moving each entry from the first path to the second. The job
+/// recreates directories that were emptied and removed by the original move.
+fn move_back<'a>(moves: impl Iterator<Item = (&'a PathBuf, &'a PathBuf)>) -> Step {
+    let items = moves
+        .map(|(source, target)| JobItem {
+            source: source.clone(),
+            target: Some(target.clone()),
+        })
+        .collect();
+    Step::job(JobRequest {
+        kind: JobKind::Move,
+        items,
+    })
+}
+
+fn trash_all<'a>(paths: impl IntoIterator<Item = &'a Path>) -> (Vec<TrashItem>, Vec<String>) {
+    let mut trashed = Vec::new();
+    let mut errors = Vec::new();
+    for path in paths {
+        match trash::trash(path) {
+            Ok(item) => trashed.push(item),
+            Err(e) => errors.push(fileops::describe_error("trash", path, &e)),
+        }
+    }
+    (trashed, errors)
+}
+
+fn restore_all(items: &[TrashItem]) -> Vec<String> {
+    items
+        .iter()
+        .filter_map(|item| {
+            let error = item.restore().err()?;
+            Some(fileops::describe_error("restore", &item.original, &error))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn write(path: &Path, contents: &str) {
+        fs::write(path, contents).unwrap();
+    }
+
+    fn exists(path: &Path) -> bool {
+        fs::symlink_metadata(path).is_ok()
+    }
+
+    fn entry(operation: Operation) -> Entry {
+        Entry {
+            operation,
+            time: Local::now(),
+            trashed: Vec::new(),
+        }
+    }
+
+    /// Delete trashed items for good, so tests leave the trash as it was.
+    fn discard(items: &[TrashItem]) {
+        for item in items {
+            fileops::delete(&item.path()).unwrap();
+            trash::forget(&item.path());
+        }
+    }
+
+    fn finished(id: u64, kind: JobKind, completed: Vec<JobItem>, replaced: usize) -> FinishedJob {
+        FinishedJob {
+            id,
+            kind,
+            result: Ok("done".to_string()),
+            completed,
+            replaced,
+        }
+    }
+
+    fn moved(from: &Path, to: &Path) -> JobItem {
+        JobItem {
+            source: from.to_path_buf(),
+            target: Some(to.to_path_buf()),
+        }
+    }
+
+    #[test]
+    fn rename_is_undone_and_redone() {
+        let dir = tempfile::tempdir().unwrap();
+        let (from, to) = (dir.path().join("old.txt"), dir.path().join("new.txt"));
+        write(&to, "contents");
+        let mut entry = entry(Operation::Rename {
+            from: from.clone(),
+            to: to.clone(),
+        });
+
+        assert!(undo(&mut entry).applied);
+        assert!(exists(&from) && !exists(&to));
+        assert!(redo(&mut entry).applied);
+        assert!(!exists(&from) && exists(&to));
+    }
+
+    #[test]
+    fn rename_is_not_undone_over_another_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let (from, to) = (dir.path().join("old.txt"), dir.path().join("new.txt"));
+        write(&to, "renamed");
+        write(&from, "newer");
+        let mut entry = entry(Operation::Rename {
+            from: from.clone(),
+            to: to.clone(),
+        });
+
+        let step = undo(&mut entry);
+        assert!(!step.applied);
+        assert_eq!(step.errors.len(), 1);
+        assert_eq!(fs::read_to_string(&from).unwrap(), "newer");
+    }
+
+    #[test]
+    fn create_is_undone_into_the_trash_and_redone_from_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("created");
+        fs::create_dir(&path).unwrap();
+        let mut entry = entry(Operation::Create { path: path.clone() });
+
+        assert!(undo(&mut entry).applied);
+        assert!(!exists(&path));
+        assert_eq!(entry.trashed.len(), 1);
+        assert!(exists(&entry.trashed[0].path()));
+
+        assert!(redo(&mut entry).applied);
+        assert!(path.is_dir());
+        assert!(entry.trashed.is_empty());
+    }
+
+    #[test]
+    fn copy_is_undone_into_the_trash_and_redone_from_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source.txt");
+        write(&source, "contents");
+        let copies = [dir.path().join("copy 1.txt"), dir.path().join("copy 2.txt")];
+        for copy in &copies {
+            write(copy, "contents");
+        }
+        let items = copies.iter().map(|copy| (source.clone(), copy.clone())).collect();
+        let mut entry = entry(Operation::Copy { items });
+
+        assert!(undo(&mut entry).applied);
+        assert!(copies.iter().all(|copy| !exists(copy)));
+        assert!(exists(&source));
+        assert_eq!(entry.trashed.len(), 2);
+
+        assert!(redo(&mut entry).applied);
+        assert!(copies.iter().all(|copy| exists(copy)));
+        assert!(entry.trashed.is_empty());
+    }
+
+    #[test]
+    fn move_is_undone_and_redone_through_the_job_queue() {
+        let dir = tempfile::tempdir().unwrap();
+        let (from, to) = (dir.path().join("a/file.txt"), dir.path().join("b/file.txt"));
+        let mut entry = entry(Operation::Move {
+            items: vec![(from.clone(), to.clone())],
+        });
+
+        let step = undo(&mut entry);
+        assert!(!step.applied);
+        let request = step.job.unwrap();
+        assert_eq!(request.kind, JobKind::Move);
+        assert_eq!(request.items.len(), 1);
+        assert_eq!((&request.items[0].source, &request.items[0].target), (&to, &Some(from.clone())));
+        // The job creates the folder it goes back into
+        assert!(!exists(&dir.path().join("a")));
+
+        let request = redo(&mut entry).job.unwrap();
+        assert_eq!((&request.items[0].source, &request.items[0].target), (&from, &Some(to)));
+    }
+
+    #[test]
+    fn trash_is_undone_by_restoring_and_redone_by_trashing_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("trashed.txt");
+        write(&path, "contents");
+        let item = trash::trash(&path).unwrap();
+        let mut entry = entry(Operation::Trash { items: vec![item] });
+
+        assert!(undo(&mut entry).applied);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "contents");
+
+        assert!(redo(&mut entry).applied);
+        assert!(!exists(&path));
+        let Operation::Trash { items } = &entry.operation else {
+            unreachable!();
+        };
+        assert_eq!(items.len(), 1);
+        assert!(exists(&items[0].path()));
+        discard(items);
+    }
+
+    #[test]
+    fn history_keeps_only_the_latest_operations() {
+        let mut history = History::default();
+        for n in 0..HISTORY_LIMIT + 5 {
+            history.record(Operation::Create {
+                path: PathBuf::from(format!("/tmp/{}", n)),
+            });
+        }
+        assert_eq!(history.entries.len(), HISTORY_LIMIT);
+        assert_eq!(history.done, HISTORY_LIMIT);
+        assert_eq!(history.next_undo().unwrap(), format!("Create \"{}\"", HISTORY_LIMIT + 4));
+        let Operation::Create { path } = &history.entries[0].operation else {
+            unreachable!();
+        };
+        assert_eq!(path, Path::new("/tmp/5"));
+    }
+
+    #[test]
+    fn recording_drops_what_was_undone() {
+        let dir = tempfile::tempdir().unwrap();
+        let (a, b) = (dir.path().join("a"), dir.path().join("b"));
+        write(&b, "");
+        let mut history = History::default();
+        history.record(Operation::Rename { from: a.clone(), to: b.clone() });
+        history.undo();
+        assert!(history.next_redo().is_some());
+
+        history.record(Operation::Rename { from: b, to: a });
+        assert!(history.next_redo().is_none());
+        assert_eq!(history.entries.len(), 1);
+    }
+
+    #[test]
+    fn a_moved_back_entry_counts_as_undone_once_its_job_moved_something() {
+        let dir = tempfile::tempdir().unwrap();
+        let (from, to) = (dir.path().join("a.txt"), dir.path().join("b.txt"));
+        let mut history = History::default();
+        history.record(Operation::Move {
+            items: vec![(from.clone(), to.clone())],
+        });
+
+        history.undo();
+        assert!(history.check_job().is_some());
+        history.job_submitted(Some(7));
+        assert_eq!(history.done, 1);
+        // Nothing else can be undone or redone in the meantime
+        history.check_status();
+        history.undo();
+        assert!(matches!(history.check_status(), Some(Err(_))));
+
+        // A job that moved nothing leaves the entry in effect
+        history.job_finished(&finished(7, JobKind::Move, Vec::new(), 0));
+        assert_eq!(history.done, 1);
+
+        history.undo();
+        let id = history.check_job().map(|_| 8);
+        history.job_submitted(id);
+        history.job_finished(&finished(8, JobKind::Move, vec![moved(&to, &from)], 0));
+        assert_eq!(history.done, 0);
+
+        history.redo();
+        let id = history.check_job().map(|_| 9);
+        history.job_submitted(id);
+        assert_eq!(history.done, 0);
+        history.job_finished(&finished(9, JobKind::Move, vec![moved(&from, &to)], 0));
+        assert_eq!(history.done, 1);
+    }
+
+    #[test]
+    fn finished_jobs_are_recorded_unless_they_overwrote_something() {
+        let dir = tempfile::tempdir().unwrap();
+        let (from, to) = (dir.path().join("a.txt"), dir.path().join("b.txt"));
+        let mut history = History::default();
+
+        history.track(1);
+        history.job_finished(&finished(1, JobKind::Copy, vec![moved(&from, &to)], 1));
+        assert!(history.entries.is_empty());
+        assert!(matches!(history.check_status(), Some(Ok(message)) if message.contains("cannot be undone")));
+
+        history.track(2);
+        history.job_finished(&finished(2, JobKind::Copy, vec![moved(&from, &to)], 0));
+        // Jobs nobody tracked are not recorded
+        history.job_finished(&finished(3, JobKind::Copy, vec![moved(&from, &to)], 0));
+        assert_eq!(history.entries.len(), 1);
+        assert!(history.tracked.is_empty());
+    }
+}
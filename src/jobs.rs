@@ -0,0 +1,1490 @@
+use crate::explorer::{format_size, FileEntry};
+use crate::fileops;
+use crate::trash;
+use egui::{Color32, Grid, ProgressBar, RichText, ScrollArea};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use walkdir::WalkDir;
+
+/// Bytes copied between progress updates and cancellation checks.
+const CHUNK_SIZE: usize = 1 << 20;
+const PAUSE_POLL: Duration = Duration::from_millis(50);
+/// How often the UI redraws while a job is running.
+const REFRESH_INTERVAL: Duration = Duration::from_millis(200);
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum JobKind {
+    Copy,
+    Move,
+    /// Move to the trash, where it can be restored from.
+    Trash,
+    Delete,
+}
+
+impl JobKind {
+    fn verb(self) -> &'static str {
+        match self {
+            JobKind::Copy => "Copying",
+            JobKind::Move => "Moving",
+            JobKind::Trash => "Trashing",
+            JobKind::Delete => "Deleting",
+        }
+    }
+
+    fn past(self) -> &'static str {
+        match self {
+            JobKind::Copy => "Copied",
+            JobKind::Move => "Moved",
+            JobKind::Trash => "Trashed",
+            JobKind::Delete => "Deleted",
+        }
+    }
+
+    fn action(self) -> &'static str {
+        match self {
+            JobKind::Copy => "copy",
+            JobKind::Move => "move",
+            JobKind::Trash => "trash",
+            JobKind::Delete => "delete",
+        }
+    }
+}
+
+/// One entry a job works on. Deletions have no target.
+#[derive(Clone, Debug)]
+pub struct JobItem {
+    pub source: PathBuf,
+    pub target: Option<PathBuf>,
+}
+
+/// A file operation for the job queue.
+#[derive(Clone, Debug)]
+pub struct JobRequest {
+    pub kind: JobKind,
+    pub items: Vec<JobItem>,
+}
+
+impl JobRequest {
+    fn describe(&self) -> String {
+        let what = match self.items.as_slice() {
+            [item] => item
+                .source
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| item.source.display().to_string()),
+            list => items(list.len()),
+        };
+        let destination = self
+            .items
+            .first()
+            .and_then(|item| item.target.as_deref())
+            .and_then(Path::parent);
+        match destination {
+            Some(dir) => format!("{} {} to {}", self.kind.verb(), what, dir.display()),
+            None => format!("{} {}", self.kind.verb(), what),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum JobState {
+    Queued,
+    /// Adding up the sizes of the entries.
+    Scanning,
+    Running,
+    Done,
+    /// Finished, but some entries could not be processed.
+    Failed,
+    Cancelled,
+}
+
+impl JobState {
+    fn is_finished(self) -> bool {
+        matches!(self, JobState::Done | JobState::Failed | JobState::Cancelled)
+    }
+}
+
+/// How to deal with an entry that already exists where another one goes.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Resolution {
+    Overwrite,
+    /// Overwrite when the incoming entry was modified more recently.
+    OverwriteIfNewer,
+    Skip,
+    /// Keep both, giving the incoming entry a numbered name.
+    Rename,
+}
+
+/// An entry that would overwrite another one.
+#[derive(Clone, Debug)]
+struct Conflict {
+    source: PathBuf,
+    target: PathBuf,
+}
+
+/// Whether `from` was modified after `to`. Unknown times count as older.
+fn is_newer(from: &Path, to: &Path) -> bool {
+    let modified = |path: &Path| fs::symlink_metadata(path).and_then(|m| m.modified()).ok();
+    match (modified(from), modified(to)) {
+        (Some(from), Some(to)) => from > to,
+        _ => false,
+    }
+}
+
+/// What a worker reports besides the counters.
+struct Status {
+    state: JobState,
+    /// Entry being processed.
+    current: Option<PathBuf>,
+    errors: Vec<String>,
+    /// Entries done, each with where it is now. Merging into a directory
+    /// lists what was put into it; deleted entries have no target.
+    completed: Vec<JobItem>,
+    started: Option<Instant>,
+    /// Time spent paused, left out of the throughput.
+    paused_for: Duration,
+    /// A conflict waiting for the user's decision.
+    conflict: Option<Conflict>,
+    /// Entries left alone because of conflicts.
+    skipped: usize,
+    /// Existing entries overwritten and deleted.
+    replaced: usize,
+}
+
+impl Status {
+    /// Forget `conflict` once answered, unless the worker has already moved
+    /// on to the next one.
+    fn clear_conflict(&mut self, conflict: &Conflict) {
+        let same = self
+            .conflict
+            .as_ref()
+            .is_some_and(|c| c.source == conflict.source && c.target == conflict.target);
+        if same {
+            self.conflict = None;
+        }
+    }
+}
+
+/// Progress shared between a job's worker and the UI.
+struct Progress {
+    bytes_total: AtomicU64,
+    bytes_done: AtomicU64,
+    files_total: AtomicU64,
+    files_done: AtomicU64,
+    paused: AtomicBool,
+    cancelled: AtomicBool,
+    status: Mutex<Status>,
+}
+
+impl Progress {
+    fn new() -> Self {
+        Self {
+            bytes_total: AtomicU64::new(0),
+            bytes_done: AtomicU64::new(0),
+            files_total: AtomicU64::new(0),
+            files_done: AtomicU64::new(0),
+            paused: AtomicBool::new(false),
+            cancelled: AtomicBool::new(false),
+            status: Mutex::new(Status {
+                state: JobState::Queued,
+                current: None,
+                errors: Vec::new(),
+                completed: Vec::new(),
+                started: None,
+                paused_for: Duration::ZERO,
+                conflict: None,
+                skipped: 0,
+                replaced: 0,
+            }),
+        }
+    }
+
+    fn update(&self, f: impl FnOnce(&mut Status)) {
+        if let Ok(mut status) = self.status.lock() {
+            f(&mut status);
+        }
+    }
+
+    fn state(&self) -> JobState {
+        self.status.lock().map(|s| s.state).unwrap_or(JobState::Failed)
+    }
+
+    fn cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+fn cancelled_error() -> io::Error {
+    io::Error::new(io::ErrorKind::Interrupted, "cancelled")
+}
+
+/// Carries out one job on a blocking thread.
+struct Worker {
+    request: JobRequest,
+    progress: Arc<Progress>,
+    ctx: egui::Context,
+    last_repaint: Instant,
+    /// Decisions about conflicts, with whether they apply to the rest of
+    /// the job.
+    answers: Receiver<(Resolution, bool)>,
+    /// The decision applied to every further conflict.
+    policy: Option<Resolution>,
+}
+
+impl Worker {
+    fn run(mut self) {
+        if self.progress.cancelled() {
+            self.progress.update(|s| s.state = JobState::Cancelled);
+            self.ctx.request_repaint();
+            return;
+        }
+        self.progress.update(|s| {
+            s.state = JobState::Scanning;
+            s.started = Some(Instant::now());
+        });
+        self.ctx.request_repaint();
+
+        for item in &self.request.items {
+            // Trashing renames whole entries, so count entries instead
+            let (files, bytes) = match self.request.kind {
+                JobKind::Trash => (1, 0),
+                _ => measure(&item.source),
+            };
+            self.progress.files_total.fetch_add(files, Ordering::Relaxed);
+            self.progress.bytes_total.fetch_add(bytes, Ordering::Relaxed);
+        }
+        self.progress.update(|s| s.state = JobState::Running);
+
+        let kind = self.request.kind;
+        for item in self.request.items.clone() {
+            if self.progress.cancelled() {
+                break;
+            }
+            self.progress.update(|s| s.current = Some(item.source.clone()));
+            let result = match (kind, &item.target) {
+                (JobKind::Copy, Some(target)) => self.copy(&item.source, target),
+                (JobKind::Move, Some(target)) => self.move_to(&item.source, target),
+                (JobKind::Trash, _) => self.trash(&item.source),
+                (JobKind::Delete, _) => self.delete(&item.source).map(|()| {
+                    trash::forget(&item.source);
+                    vec![JobItem {
+                        source: item.source.clone(),
+                        target: None,
+                    }]
+                }),
+                (_, None) => continue,
+            };
+            match result {
+                Ok(completed) => self.progress.update(|s| s.completed.extend(completed)),
+                Err(_) if self.progress.cancelled() => break,
+                Err(e) => {
+                    let error = fileops::describe_error(kind.action(), &item.source, &e);
+                    self.progress.update(|s| s.errors.push(error));
+                }
+            }
+        }
+
+        let cancelled = self.progress.cancelled();
+        self.progress.update(|s| {
+            s.current = None;
+            s.state = if cancelled {
+                JobState::Cancelled
+            } else if s.errors.is_empty() {
+                JobState::Done
+            } else {
+                JobState::Failed
+            };
+        });
+        self.ctx.request_repaint();
+    }
+
+    /// Block while the job is paused. Fails once it is cancelled.
+    fn checkpoint(&mut self) -> io::Result<()> {
+        if self.progress.paused.load(Ordering::Relaxed) {
+            let paused_at = Instant::now();
+            self.ctx.request_repaint();
+            while self.progress.paused.load(Ordering::Relaxed) && !self.progress.cancelled() {
+                thread::sleep(PAUSE_POLL);
+            }
+            self.progress.update(|s| s.paused_for += paused_at.elapsed());
+        }
+        if self.progress.cancelled() {
+            return Err(cancelled_error());
+        }
+        if self.last_repaint.elapsed() >= REFRESH_INTERVAL {
+            self.last_repaint = Instant::now();
+            self.ctx.request_repaint();
+        }
+        Ok(())
+    }
+
+    /// How to handle `to` already existing when `from` goes there, or
+    /// `None` to skip. A file that can take the place of an existing file
+    /// replaces it in one step; anything else is moved aside until the new
+    /// entry is complete, see `settle`.
+    fn claim(&mut self, from: &Path, to: &Path, in_place: bool) -> io::Result<Option<Claim>> {
+        if fs::symlink_metadata(to).is_err() {
+            return Ok(Some(Claim::free(to)));
+        }
+        let resolution = match self.policy {
+            Some(resolution) => resolution,
+            None => {
+                let (resolution, apply_to_all) = self.ask(from, to)?;
+                if apply_to_all {
+                    self.policy = Some(resolution);
+                }
+                resolution
+            }
+        };
+        let replace = match resolution {
+            Resolution::Skip => false,
+            Resolution::Rename => return Ok(Some(Claim::free(&fileops::numbered_path(to)))),
+            Resolution::Overwrite => true,
+            Resolution::OverwriteIfNewer => is_newer(from, to),
+        };
+        if !replace {
+            self.skip(from);
+            return Ok(None);
+        }
+        // It may have gone while the user was deciding
+        let Ok(existing) = fs::symlink_metadata(to) else {
+            return Ok(Some(Claim::free(to)));
+        };
+        if in_place && existing.is_file() {
+            return Ok(Some(Claim {
+                target: to.to_path_buf(),
+                replace: true,
+                backup: None,
+            }));
+        }
+        let backup = fileops::backup_path(to);
+        fs::rename(to, &backup)?;
+        Ok(Some(Claim {
+            target: to.to_path_buf(),
+            replace: false,
+            backup: Some(backup),
+        }))
+    }
+
+    /// Finish what `claim` started once the new entry is written: drop the
+    /// old one if `result` succeeded, otherwise put it back in place of the
+    /// incomplete new one.
+    fn settle<T>(&self, claim: &Claim, result: io::Result<T>) -> io::Result<T> {
+        if let Some(backup) = &claim.backup {
+            if result.is_ok() {
+                fileops::delete(backup)?;
+            } else {
+                if fs::symlink_metadata(&claim.target).is_ok() {
+                    fileops::delete(&claim.target)?;
+                }
+                fs::rename(backup, &claim.target)?;
+            }
+        }
+        if result.is_ok() && (claim.replace || claim.backup.is_some()) {
+            self.progress.update(|s| s.replaced += 1);
+        }
+        result
+    }
+
+    /// Wait for the user to decide about a conflict.
+    fn ask(&mut self, from: &Path, to: &Path) -> io::Result<(Resolution, bool)> {
+        self.progress.update(|s| {
+            s.conflict = Some(Conflict {
+                source: from.to_path_buf(),
+                target: to.to_path_buf(),
+            })
+        });
+        self.ctx.request_repaint();
+        let asked_at = Instant::now();
+        let answer = loop {
+            if self.progress.cancelled() {
+                break Err(cancelled_error());
+            }
+            match self.answers.recv_timeout(PAUSE_POLL) {
+                Ok(answer) => break Ok(answer),
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => break Err(cancelled_error()),
+            }
+        };
+        self.progress.update(|s| {
+            s.conflict = None;
+            s.paused_for += asked_at.elapsed();
+        });
+        answer
+    }
+
+    /// Count a skipped entry as done so the progress still adds up.
+    fn skip(&mut self, path: &Path) {
+        let (files, bytes) = measure(path);
+        self.progress.files_done.fetch_add(files, Ordering::Relaxed);
+        self.progress.bytes_done.fetch_add(bytes, Ordering::Relaxed);
+        self.progress.update(|s| s.skipped += 1);
+    }
+
+    fn copy(&mut self, from: &Path, to: &Path) -> io::Result<Vec<JobItem>> {
+        if to.starts_with(from) {
+            return Err(io::Error::other(format!("Cannot copy {} into itself", from.display())));
+        }
+        self.copy_entry(from, to)
+    }
+
+    /// Copy `from` to `to`, merging into a directory that is already there.
+    /// Returns the entries created: this one, what went into the directory
+    /// it was merged with, or nothing if it was skipped.
+    fn copy_entry(&mut self, from: &Path, to: &Path) -> io::Result<Vec<JobItem>> {
+        self.checkpoint()?;
+        let metadata = fs::symlink_metadata(from)?;
+        if metadata.is_dir() {
+            if fs::symlink_metadata(to).is_ok_and(|existing| existing.is_dir()) {
+                return self.copy_dir(from, to, &metadata);
+            }
+            let Some(claim) = self.claim(from, to, false)? else {
+                return Ok(Vec::new());
+            };
+            let result = self.copy_dir(from, &claim.target, &metadata);
+            self.settle(&claim, result)?;
+            return Ok(vec![JobItem {
+                source: from.to_path_buf(),
+                target: Some(claim.target),
+            }]);
+        }
+
+        let is_symlink = metadata.file_type().is_symlink();
+        let Some(claim) = self.claim(from, to, !is_symlink)? else {
+            return Ok(Vec::new());
+        };
+        let result = if is_symlink {
+            fileops::copy(from, &claim.target).map(|()| {
+                self.progress.files_done.fetch_add(1, Ordering::Relaxed);
+            })
+        } else {
+            self.progress.update(|s| s.current = Some(from.to_path_buf()));
+            self.copy_file(from, &claim.target, &metadata)
+        };
+        self.settle(&claim, result)?;
+        Ok(vec![JobItem {
+            source: from.to_path_buf(),
+            target: Some(claim.target),
+        }])
+    }
+
+    /// Copy the contents of the directory `from` into `to`, creating `to`
+    /// unless it is there to merge with. Returns what was created inside.
+    fn copy_dir(&mut self, from: &Path, to: &Path, metadata: &fs::Metadata) -> io::Result<Vec<JobItem>> {
+        if fs::symlink_metadata(to).is_err() {
+            fs::create_dir(to)?;
+        }
+        let mut created = Vec::new();
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            created.extend(self.copy_entry(&entry.path(), &to.join(entry.file_name()))?);
+        }
+        fs::set_permissions(to, metadata.permissions())?;
+        Ok(created)
+    }
+
+    /// Copy a file in chunks, so progress is reported and pausing or
+    /// cancelling takes effect in the middle of large files. The data goes
+    /// to a temporary file first, which then takes the place of `to`: a
+    /// file being overwritten is only replaced once the copy is complete.
+    fn copy_file(&mut self, from: &Path, to: &Path, metadata: &fs::Metadata) -> io::Result<()> {
+        let partial = fileops::partial_path(to);
+        let mut reader = File::open(from)?;
+        let mut writer = OpenOptions::new().write(true).create(true).truncate(true).open(&partial)?;
+        let mut buffer = vec![0; CHUNK_SIZE];
+        let result = (|| {
+            loop {
+                self.checkpoint()?;
+                let read = match reader.read(&mut buffer) {
+                    Ok(0) => break,
+                    Ok(read) => read,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(e),
+                };
+                writer.write_all(&buffer[..read])?;
+                self.progress.bytes_done.fetch_add(read as u64, Ordering::Relaxed);
+            }
+            writer.flush()?;
+            if let Ok(modified) = metadata.modified() {
+                writer.set_modified(modified)?;
+            }
+            writer.set_permissions(metadata.permissions())?;
+            drop(writer);
+            fs::rename(&partial, to)
+        })();
+        if result.is_err() {
+            let _ = fs::remove_file(&partial);
+        }
+        result?;
+        self.progress.files_done.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn move_to(&mut self, from: &Path, to: &Path) -> io::Result<Vec<JobItem>> {
+        if to.starts_with(from) {
+            return Err(io::Error::other(format!("Cannot move {} into itself", from.display())));
+        }
+        // Undoing a move can put entries back into a folder that has gone
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        self.move_entry(from, to)
+    }
+
+    /// Rename when possible; across file systems copy and then delete the
+    /// original. A directory moved onto another one is merged into it, and
+    /// only removed if nothing in it was skipped.
+    fn move_entry(&mut self, from: &Path, to: &Path) -> io::Result<Vec<JobItem>> {
+        self.checkpoint()?;
+        let metadata = fs::symlink_metadata(from)?;
+        let merge = metadata.is_dir() && fs::symlink_metadata(to).is_ok_and(|existing| existing.is_dir());
+        if merge {
+            let mut moved = Vec::new();
+            for entry in fs::read_dir(from)? {
+                let entry = entry?;
+                moved.extend(self.move_entry(&entry.path(), &to.join(entry.file_name()))?);
+            }
+            let _ = fs::remove_dir(from);
+            return Ok(moved);
+        }
+
+        let Some(claim) = self.claim(from, to, metadata.is_file())? else {
+            return Ok(Vec::new());
+        };
+        let (files, bytes) = measure(from);
+        let copied = match fs::rename(from, &claim.target) {
+            Ok(()) => {
+                self.progress.files_done.fetch_add(files, Ordering::Relaxed);
+                self.progress.bytes_done.fetch_add(bytes, Ordering::Relaxed);
+                self.settle(&claim, Ok(()))?;
+                false
+            }
+            Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+                let result = if claim.replace {
+                    self.copy_file(from, &claim.target, &metadata)
+                } else {
+                    self.copy_entry(from, &claim.target).map(|_| ())
+                };
+                self.settle(&claim, result)?;
+                true
+            }
+            Err(e) => return self.settle(&claim, Err(e)),
+        };
+        // The original only goes once its copy is complete
+        if copied {
+            fileops::delete(from)?;
+        }
+        Ok(vec![JobItem {
+            source: from.to_path_buf(),
+            target: Some(claim.target),
+        }])
+    }
+
+    fn trash(&mut self, path: &Path) -> io::Result<Vec<JobItem>> {
+        self.checkpoint()?;
+        let item = trash::trash(path)?;
+        self.progress.files_done.fetch_add(1, Ordering::Relaxed);
+        Ok(vec![JobItem {
+            source: item.original.clone(),
+            target: Some(item.path()),
+        }])
+    }
+
+    fn delete(&mut self, path: &Path) -> io::Result<()> {
+        self.checkpoint()?;
+        let metadata = fs::symlink_metadata(path)?;
+        if metadata.is_dir() {
+            for entry in fs::read_dir(path)? {
+                self.delete(&entry?.path())?;
+            }
+            fs::remove_dir(path)
+        } else {
+            self.progress.update(|s| s.current = Some(path.to_path_buf()));
+            fs::remove_file(path)?;
+            self.progress.files_done.fetch_add(1, Ordering::Relaxed);
+            self.progress.bytes_done.fetch_add(metadata.len(), Ordering::Relaxed);
+            Ok(())
+        }
+    }
+}
+
+/// Number of files and their total size under `path`, links not followed.
+fn measure(path: &Path) -> (u64, u64) {
+    WalkDir::new(path)
+        .follow_links(false)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| !entry.file_type().is_dir())
+        .fold((0, 0), |(files, bytes), entry| {
+            let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+            (files + 1, bytes + size)
+        })
+}
+
+/// Where an entry goes, as decided by `Worker::claim`.
+struct Claim {
+    target: PathBuf,
+    /// The new file takes the place of an existing one in one step.
+    replace: bool,
+    /// Where the entry being overwritten waits until the new one is done.
+    backup: Option<PathBuf>,
+}
+
+impl Claim {
+    /// A path nothing is in the way of.
+    fn free(path: &Path) -> Self {
+        Claim {
+            target: path.to_path_buf(),
+            replace: false,
+            backup: None,
+        }
+    }
+}
+
+/// A job as the queue sees it.
+struct Job {
+    id: u64,
+    kind: JobKind,
+    description: String,
+    progress: Arc<Progress>,
+    answers: Sender<(Resolution, bool)>,
+    /// Whether `take_finished` has returned it.
+    reported: bool,
+}
+
+impl Job {
+    fn throughput(&self, status: &Status) -> Option<f64> {
+        let started = status.started?;
+        let active = started.elapsed().saturating_sub(status.paused_for).as_secs_f64();
+        let done = self.progress.bytes_done.load(Ordering::Relaxed) as f64;
+        (active > 0.5 && done > 0.0).then(|| done / active)
+    }
+}
+
+/// A job that ended since the last `JobQueue::take_finished`.
+pub struct FinishedJob {
+    /// What `JobQueue::submit` returned for it.
+    pub id: u64,
+    pub kind: JobKind,
+    /// A summary for the status bar, or the errors.
+    pub result: Result<String, String>,
+    pub completed: Vec<JobItem>,
+    /// How many existing entries were overwritten; they are gone for good.
+    pub replaced: usize,
+}
+
+/// File operations waiting for, or running on, the tokio runtime. Jobs run
+/// one at a time in the order they were queued.
+pub struct JobQueue {
+    jobs: Vec<Job>,
+    next_id: u64,
+    sender: UnboundedSender<Worker>,
+    open: bool,
+    /// The conflict dialog's "apply to all" choice.
+    apply_to_all: bool,
+}
+
+impl JobQueue {
+    /// Start the queue's worker on the runtime entered in `main`.
+    pub fn new() -> Self {
+        let (sender, mut receiver) = unbounded_channel::<Worker>();
+        tokio::runtime::Handle::current().spawn(async move {
+            while let Some(worker) = receiver.recv().await {
+                let _ = tokio::task::spawn_blocking(move || worker.run()).await;
+            }
+        });
+        Self {
+            jobs: Vec::new(),
+            next_id: 0,
+            sender,
+            open: false,
+            apply_to_all: false,
+        }
+    }
+
+    /// Queue a job. Returns its id, which its `FinishedJob` carries, or
+    /// `None` if there is nothing to do and no job was queued.
+    pub fn submit(&mut self, ctx: &egui::Context, request: JobRequest) -> Option<u64> {
+        if request.items.is_empty() {
+            return None;
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        let progress = Arc::new(Progress::new());
+        let (answers, receiver) = mpsc::channel();
+        self.jobs.push(Job {
+            id,
+            kind: request.kind,
+            description: request.describe(),
+            progress: Arc::clone(&progress),
+            answers,
+            reported: false,
+        });
+        let worker = Worker {
+            request,
+            progress,
+            ctx: ctx.clone(),
+            last_repaint: Instant::now(),
+            answers: receiver,
+            policy: None,
+        };
+        if self.sender.send(worker).is_err() {
+            if let Some(job) = self.jobs.last() {
+                job.progress.update(|s| {
+                    s.state = JobState::Failed;
+                    s.errors.push("The job queue has stopped".to_string());
+                });
+            }
+        }
+        Some(id)
+    }
+
+    /// Jobs that ended since the last call.
+    pub fn take_finished(&mut self) -> Vec<FinishedJob> {
+        let mut finished = Vec::new();
+        for job in &mut self.jobs {
+            if job.reported {
+                continue;
+            }
+            let Ok(status) = job.progress.status.lock() else {
+                continue;
+            };
+            if !status.state.is_finished() {
+                continue;
+            }
+            let count = status.completed.len();
+            let skipped = match status.skipped {
+                0 => String::new(),
+                skipped => format!(", skipped {}", items(skipped)),
+            };
+            let result = match (status.state, status.errors.as_slice()) {
+                (JobState::Cancelled, _) => Ok(format!("Cancelled: {} ({} done)", job.description, items(count))),
+                (_, []) => Ok(format!("{} {}{}", job.kind.past(), items(count), skipped)),
+                (_, [error]) => Err(error.clone()),
+                (_, [first, rest @ ..]) => Err(format!("{} (and {} more errors)", first, rest.len())),
+            };
+            finished.push(FinishedJob {
+                id: job.id,
+                kind: job.kind,
+                result,
+                completed: status.completed.clone(),
+                replaced: status.replaced,
+            });
+            drop(status);
+            job.reported = true;
+        }
+        finished
+    }
+
+    fn active(&self) -> impl Iterator<Item = &Job> {
+        self.jobs.iter().filter(|job| !job.progress.state().is_finished())
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    /// A compact indicator of the running job for the status bar. Returns
+    /// whether it was clicked.
+    pub fn render_status(&self, ui: &mut egui::Ui) -> bool {
+        let mut active = self.active();
+        let Some(job) = active.next() else {
+            return false;
+        };
+        let queued = active.count();
+        let mut text = format!("⏳ {} {:.0}%", job.kind.verb(), fraction(job) * 100.0);
+        if queued > 0 {
+            text.push_str(&format!(" (+{} queued)", queued));
+        }
+        ui.ctx().request_repaint_after(REFRESH_INTERVAL);
+        ui.small_button(text).on_hover_text(&job.description).clicked()
+    }
+
+    pub fn render(&mut self, ctx: &egui::Context) {
+        self.render_conflict(ctx);
+        if !self.open {
+            return;
+        }
+        let mut open = self.open;
+        egui::Window::new("File Operations")
+            .open(&mut open)
+            .default_size([480.0, 320.0])
+            .show(ctx, |ui| {
+                if ui.button("Clear finished").clicked() {
+                    self.jobs.retain(|job| !job.reported || !job.progress.state().is_finished());
+                }
+                ui.separator();
+                if self.jobs.is_empty() {
+                    ui.label(RichText::new("No file operations").weak());
+                }
+                ScrollArea::vertical().show(ui, |ui| {
+                    for job in self.jobs.iter().rev() {
+                        render_job(ui, job);
+                        ui.separator();
+                    }
+                });
+            });
+        if self.active().next().is_some() {
+            ctx.request_repaint_after(REFRESH_INTERVAL);
+        }
+        self.open = open;
+    }
+
+    /// Ask about the first conflict a job is waiting on.
+    fn render_conflict(&mut self, ctx: &egui::Context) {
+        let Some((job, conflict)) = self.jobs.iter().find_map(|job| {
+            let conflict = job.progress.status.lock().ok()?.conflict.clone()?;
+            Some((job, conflict))
+        }) else {
+            return;
+        };
+        let gone = |path: &Path| matches!(fs::symlink_metadata(path), Err(e) if e.kind() == io::ErrorKind::NotFound);
+        if gone(&conflict.source) || gone(&conflict.target) {
+            // Nothing left to copy, or nothing left in the way: skipping, or
+            // writing to a path that `claim` finds free, deletes nothing
+            let resolution = if gone(&conflict.source) { Resolution::Skip } else { Resolution::Overwrite };
+            let _ = job.answers.send((resolution, false));
+            job.progress.update(|s| s.clear_conflict(&conflict));
+            return;
+        }
+        // Either may be missing for entries that cannot be read or followed
+        let source = FileEntry::from_path(conflict.source.clone());
+        let existing = FileEntry::from_path(conflict.target.clone());
+        let mut answer = None;
+        let mut cancel = false;
+        let apply_to_all = &mut self.apply_to_all;
+        egui::Modal::new(egui::Id::new(("job_conflict", job.id))).show(ctx, |ui| {
+            ui.set_max_width(440.0);
+            ui.label(RichText::new("File Already Exists").strong().size(15.0));
+            let dir = conflict.target.parent().unwrap_or(&conflict.target);
+            let name = conflict.target.file_name().unwrap_or_default().to_string_lossy();
+            ui.label(format!("\"{}\" is already in {}.", name, dir.display()));
+            ui.add_space(6.0);
+
+            let modified = |entry: &Option<FileEntry>| entry.as_ref().and_then(|entry| entry.modified);
+            let size = |entry: &Option<FileEntry>| entry.as_ref().filter(|entry| !entry.is_dir).map(|entry| entry.size);
+            let newer = match (modified(&source), modified(&existing)) {
+                (Some(a), Some(b)) if a > b => Some(true),
+                (Some(a), Some(b)) if a < b => Some(false),
+                _ => None,
+            };
+            let highlight = |text: String, strong: bool| {
+                if strong {
+                    RichText::new(text).color(Color32::GREEN)
+                } else {
+                    RichText::new(text)
+                }
+            };
+            Grid::new("conflict_compare").num_columns(3).spacing([16.0, 4.0]).show(ui, |ui| {
+                ui.label("");
+                ui.label(RichText::new("Incoming").strong());
+                ui.label(RichText::new("Existing").strong());
+                ui.end_row();
+
+                let unknown = || "--".to_string();
+                ui.label("Size:");
+                let (source_size, existing_size) = (size(&source), size(&existing));
+                let format = |entry: &Option<FileEntry>| entry.as_ref().map_or_else(unknown, FileEntry::format_size);
+                ui.label(highlight(format(&source), source_size > existing_size && existing_size.is_some()));
+                ui.label(highlight(format(&existing), existing_size > source_size && source_size.is_some()));
+                ui.end_row();
+
+                let format = |entry: &Option<FileEntry>| entry.as_ref().map_or_else(unknown, FileEntry::format_modified);
+                ui.label("Modified:");
+                ui.label(highlight(format(&source), newer == Some(true)));
+                ui.label(highlight(format(&existing), newer == Some(false)));
+                ui.end_row();
+
+                ui.label("Kind:");
+                ui.label(entry_kind(&conflict.source));
+                ui.label(entry_kind(&conflict.target));
+                ui.end_row();
+            });
+            ui.add_space(6.0);
+            ui.checkbox(apply_to_all, "Apply to all remaining conflicts in this operation");
+            ui.add_space(6.0);
+
+            ui.horizontal(|ui| {
+                if ui.button("Overwrite").clicked() {
+                    answer = Some(Resolution::Overwrite);
+                }
+                if ui
+                    .button("Overwrite if newer")
+                    .on_hover_text("Overwrite only if the incoming one was modified more recently, otherwise skip")
+                    .clicked()
+                {
+                    answer = Some(Resolution::OverwriteIfNewer);
+                }
+                if ui.button("Skip").clicked() {
+                    answer = Some(Resolution::Skip);
+                }
+                let renamed = fileops::numbered_path(&conflict.target);
+                let renamed = renamed
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                if ui
+                    .button("Keep both")
+                    .on_hover_text(format!("Copy the incoming one as \"{}\"", renamed))
+                    .clicked()
+                {
+                    answer = Some(Resolution::Rename);
+                }
+                if ui.button("Cancel").on_hover_text("Stop the whole operation").clicked() {
+                    cancel = true;
+                }
+            });
+        });
+        if cancel {
+            job.progress.cancelled.store(true, Ordering::Relaxed);
+        } else if let Some(resolution) = answer {
+            let _ = job.answers.send((resolution, *apply_to_all));
+            // Close the dialog now rather than when the worker wakes up
+            job.progress.update(|s| s.clear_conflict(&conflict));
+        }
+        if cancel || answer.is_some() {
+            self.apply_to_all = false;
+        }
+    }
+}
+
+/// What an entry is, without following links.
+fn entry_kind(path: &Path) -> &'static str {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_symlink() => {
+            if fs::metadata(path).is_ok() {
+                "Link"
+            } else {
+                "Broken link"
+            }
+        }
+        Ok(metadata) if metadata.is_dir() => "Folder",
+        Ok(_) => "File",
+        Err(_) => "Unknown",
+    }
+}
+
+/// How far along a job is, by bytes, or by files for empty ones.
+fn fraction(job: &Job) -> f32 {
+    let progress = &job.progress;
+    let bytes_total = progress.bytes_total.load(Ordering::Relaxed);
+    let files_total = progress.files_total.load(Ordering::Relaxed);
+    if bytes_total > 0 {
+        progress.bytes_done.load(Ordering::Relaxed) as f32 / bytes_total as f32
+    } else if files_total > 0 {
+        progress.files_done.load(Ordering::Relaxed) as f32 / files_total as f32
+    } else {
+        0.0
+    }
+}
+
+fn render_job(ui: &mut egui::Ui, job: &Job) {
+    let Ok(status) = job.progress.status.lock() else {
+        return;
+    };
+    let paused = job.progress.paused.load(Ordering::Relaxed);
+    let (state, color) = match status.state {
+        JobState::Queued => ("queued", Color32::GRAY),
+        JobState::Scanning => ("counting files", Color32::YELLOW),
+        JobState::Running if status.conflict.is_some() => ("waiting for a decision", Color32::LIGHT_BLUE),
+        JobState::Running if paused => ("paused", Color32::GRAY),
+        JobState::Running => ("running", Color32::YELLOW),
+        JobState::Done => ("done", Color32::GREEN),
+        JobState::Failed => ("finished with errors", Color32::LIGHT_RED),
+        JobState::Cancelled => ("cancelled", Color32::GRAY),
+    };
+    ui.horizontal(|ui| {
+        ui.label(RichText::new(&job.description).strong());
+        ui.label(RichText::new(state).color(color));
+        if !status.state.is_finished() {
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui.small_button("✕").on_hover_text("Cancel").clicked() {
+                    job.progress.cancelled.store(true, Ordering::Relaxed);
+                }
+                let (icon, hover) = if paused { ("▶", "Resume") } else { ("⏸", "Pause") };
+                if ui.small_button(icon).on_hover_text(hover).clicked() {
+                    job.progress.paused.store(!paused, Ordering::Relaxed);
+                }
+            });
+        }
+    });
+
+    let progress = &job.progress;
+    let bytes_done = progress.bytes_done.load(Ordering::Relaxed);
+    let bytes_total = progress.bytes_total.load(Ordering::Relaxed);
+    let files_done = progress.files_done.load(Ordering::Relaxed);
+    let files_total = progress.files_total.load(Ordering::Relaxed);
+    if status.state != JobState::Queued {
+        let text = format!(
+            "{} of {} · {} of {} files",
+            format_size(bytes_done),
+            format_size(bytes_total),
+            files_done,
+            files_total
+        );
+        ui.add(ProgressBar::new(fraction(job)).text(text).animate(status.state == JobState::Running && !paused));
+    }
+    if !status.state.is_finished() {
+        if let Some(rate) = job.throughput(&status) {
+            let remaining = bytes_total.saturating_sub(bytes_done) as f64 / rate;
+            ui.label(format!(
+                "{}/s · about {} left",
+                format_size(rate as u64),
+                format_eta(Duration::from_secs_f64(remaining))
+            ));
+        }
+        if let Some(current) = &status.current {
+            ui.label(RichText::new(current.display().to_string()).weak().small());
+        }
+    }
+    if !status.errors.is_empty() {
+        egui::CollapsingHeader::new(
+            RichText::new(format!("{} errors", status.errors.len())).color(Color32::LIGHT_RED),
+        )
+        .id_salt(("job_errors", job.id))
+        .show(ui, |ui| {
+            for error in &status.errors {
+                ui.label(RichText::new(error).small());
+            }
+        });
+    }
+}
+
+fn items(count: usize) -> String {
+    if count == 1 {
+        "1 item".to_string()
+    } else {
+        format!("{} items", count)
+    }
+}
+
+fn format_eta(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}h {}m", secs / 3600, secs % 3600 / 60)
+    } else if secs >= 60 {
+        format!("{}m {}s", secs / 60, secs % 60)
+    } else {
+        format!("{}s", secs.max(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(path: &Path, contents: &str) {
+        fs::write(path, contents).unwrap();
+    }
+
+    fn read(path: &Path) -> String {
+        fs::read_to_string(path).unwrap()
+    }
+
+    fn exists(path: &Path) -> bool {
+        fs::symlink_metadata(path).is_ok()
+    }
+
+    fn item(source: &Path, target: &Path) -> JobItem {
+        JobItem {
+            source: source.to_path_buf(),
+            target: Some(target.to_path_buf()),
+        }
+    }
+
+    /// A worker for `items` whose conflicts get `answers`, in order. Once
+    /// they run out, a further question cancels the job.
+    fn worker(kind: JobKind, items: Vec<JobItem>, answers: &[(Resolution, bool)]) -> Worker {
+        let (sender, receiver) = mpsc::channel();
+        for answer in answers {
+            sender.send(*answer).unwrap();
+        }
+        Worker {
+            request: JobRequest { kind, items },
+            progress: Arc::new(Progress::new()),
+            ctx: egui::Context::default(),
+            last_repaint: Instant::now(),
+            answers: receiver,
+            policy: None,
+        }
+    }
+
+    /// Run a job to the end and return its progress.
+    fn run(kind: JobKind, items: Vec<JobItem>, answers: &[(Resolution, bool)]) -> Arc<Progress> {
+        let worker = worker(kind, items, answers);
+        let progress = Arc::clone(&worker.progress);
+        worker.run();
+        progress
+    }
+
+    fn targets(progress: &Progress) -> Vec<PathBuf> {
+        let status = progress.status.lock().unwrap();
+        status.completed.iter().filter_map(|item| item.target.clone()).collect()
+    }
+
+    #[test]
+    fn copy_copies_files_and_folders() {
+        let dir = tempfile::tempdir().unwrap();
+        let (file, folder) = (dir.path().join("file.txt"), dir.path().join("folder"));
+        write(&file, "file");
+        fs::create_dir(&folder).unwrap();
+        write(&folder.join("inner.txt"), "inner");
+        let dest = dir.path().join("dest");
+        fs::create_dir(&dest).unwrap();
+
+        let items = vec![item(&file, &dest.join("file.txt")), item(&folder, &dest.join("folder"))];
+        let progress = run(JobKind::Copy, items, &[]);
+        assert_eq!(progress.state(), JobState::Done);
+        assert_eq!(read(&dest.join("file.txt")), "file");
+        assert_eq!(read(&dest.join("folder/inner.txt")), "inner");
+        assert_eq!(read(&file), "file");
+        assert_eq!(progress.files_done.load(Ordering::Relaxed), 2);
+        assert_eq!(targets(&progress), [dest.join("file.txt"), dest.join("folder")]);
+    }
+
+    #[test]
+    fn copy_merges_into_an_existing_folder() {
+        let dir = tempfile::tempdir().unwrap();
+        let (from, to) = (dir.path().join("from"), dir.path().join("to"));
+        fs::create_dir_all(from.join("sub")).unwrap();
+        write(&from.join("a.txt"), "a");
+        write(&from.join("sub/b.txt"), "b");
+        fs::create_dir_all(to.join("sub")).unwrap();
+        write(&to.join("c.txt"), "c");
+
+        let progress = run(JobKind::Copy, vec![item(&from, &to)], &[]);
+        assert_eq!(progress.state(), JobState::Done);
+        assert_eq!(read(&to.join("a.txt")), "a");
+        assert_eq!(read(&to.join("sub/b.txt")), "b");
+        assert_eq!(read(&to.join("c.txt")), "c");
+        // Only what was put into the folder counts as created
+        let mut created = targets(&progress);
+        created.sort();
+        assert_eq!(created, [to.join("a.txt"), to.join("sub/b.txt")]);
+    }
+
+    #[test]
+    fn copy_refuses_to_copy_a_folder_into_itself() {
+        let dir = tempfile::tempdir().unwrap();
+        let folder = dir.path().join("folder");
+        fs::create_dir(&folder).unwrap();
+
+        let progress = run(JobKind::Copy, vec![item(&folder, &folder.join("folder"))], &[]);
+        assert_eq!(progress.state(), JobState::Failed);
+        assert!(!exists(&folder.join("folder")));
+    }
+
+    #[test]
+    fn move_renames_and_recreates_missing_folders() {
+        let dir = tempfile::tempdir().unwrap();
+        let from = dir.path().join("file.txt");
+        write(&from, "file");
+        let to = dir.path().join("gone/again/file.txt");
+
+        let progress = run(JobKind::Move, vec![item(&from, &to)], &[]);
+        assert_eq!(progress.state(), JobState::Done);
+        assert!(!exists(&from));
+        assert_eq!(read(&to), "file");
+    }
+
+    #[test]
+    fn move_merges_into_an_existing_folder_and_removes_the_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let (from, to) = (dir.path().join("from"), dir.path().join("to"));
+        fs::create_dir(&from).unwrap();
+        write(&from.join("a.txt"), "a");
+        fs::create_dir(&to).unwrap();
+        write(&to.join("b.txt"), "b");
+
+        let progress = run(JobKind::Move, vec![item(&from, &to)], &[]);
+        assert_eq!(progress.state(), JobState::Done);
+        assert!(!exists(&from));
+        assert_eq!(read(&to.join("a.txt")), "a");
+        assert_eq!(read(&to.join("b.txt")), "b");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn move_copies_across_file_systems() {
+        use std::os::unix::fs::MetadataExt;
+        let dir = tempfile::tempdir().unwrap();
+        // Needs a second file system; /dev/shm usually is one
+        let Ok(other) = tempfile::tempdir_in("/dev/shm") else {
+            return;
+        };
+        let device = |path: &Path| fs::metadata(path).unwrap().dev();
+        if device(dir.path()) == device(other.path()) {
+            return;
+        }
+        let file = dir.path().join("file.txt");
+        write(&file, "file");
+        let folder = dir.path().join("folder");
+        fs::create_dir(&folder).unwrap();
+        write(&folder.join("inner.txt"), "inner");
+
+        let items = vec![
+            item(&file, &other.path().join("file.txt")),
+            item(&folder, &other.path().join("folder")),
+        ];
+        let progress = run(JobKind::Move, items, &[]);
+        assert_eq!(progress.state(), JobState::Done);
+        assert!(!exists(&file));
+        assert!(!exists(&folder));
+        assert_eq!(read(&other.path().join("file.txt")), "file");
+        assert_eq!(read(&other.path().join("folder/inner.txt")), "inner");
+    }
+
+    #[test]
+    fn delete_removes_a_whole_tree() {
+        let dir = tempfile::tempdir().unwrap();
+        let folder = dir.path().join("folder");
+        fs::create_dir_all(folder.join("sub")).unwrap();
+        write(&folder.join("sub/file.txt"), "file");
+
+        let items = vec![JobItem {
+            source: folder.clone(),
+            target: None,
+        }];
+        let progress = run(JobKind::Delete, items, &[]);
+        assert_eq!(progress.state(), JobState::Done);
+        assert!(!exists(&folder));
+    }
+
+    #[test]
+    fn cancelling_a_file_copy_leaves_no_partial_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let (from, to) = (dir.path().join("big.bin"), dir.path().join("copy.bin"));
+        fs::write(&from, vec![7u8; 3 * CHUNK_SIZE]).unwrap();
+
+        // The partial file is already open when the first chunk sees the
+        // job is cancelled
+        let mut worker = worker(JobKind::Copy, Vec::new(), &[]);
+        worker.progress.cancelled.store(true, Ordering::Relaxed);
+        let metadata = fs::metadata(&from).unwrap();
+        let error = worker.copy_file(&from, &to, &metadata).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::Interrupted);
+        assert!(!exists(&fileops::partial_path(&to)));
+        assert!(!exists(&to));
+    }
+
+    #[test]
+    fn cancelling_a_paused_job_copies_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let (from, to) = (dir.path().join("file.txt"), dir.path().join("copy.txt"));
+        write(&from, "file");
+
+        let worker = worker(JobKind::Copy, vec![item(&from, &to)], &[]);
+        let progress = Arc::clone(&worker.progress);
+        progress.paused.store(true, Ordering::Relaxed);
+        let running = thread::spawn(move || worker.run());
+        thread::sleep(PAUSE_POLL * 3);
+        assert_eq!(progress.state(), JobState::Running);
+        progress.cancelled.store(true, Ordering::Relaxed);
+        running.join().unwrap();
+
+        assert_eq!(progress.state(), JobState::Cancelled);
+        assert!(!exists(&to));
+        assert!(!exists(&fileops::partial_path(&to)));
+    }
+
+    #[test]
+    fn a_failed_overwrite_restores_the_original() {
+        let dir = tempfile::tempdir().unwrap();
+        let (from, to) = (dir.path().join("from"), dir.path().join("to"));
+        fs::create_dir(&from).unwrap();
+        write(&to, "original");
+
+        let mut worker = worker(JobKind::Copy, Vec::new(), &[(Resolution::Overwrite, false)]);
+        let claim = worker.claim(&from, &to, false).unwrap().unwrap();
+        let backup = claim.backup.clone().unwrap();
+        assert_eq!(read(&backup), "original");
+        // Half of the new entry is there when the copy fails
+        fs::create_dir(&claim.target).unwrap();
+        write(&claim.target.join("half.txt"), "half");
+
+        let result: io::Result<()> = Err(io::Error::other("disk full"));
+        assert!(worker.settle(&claim, result).is_err());
+        assert_eq!(read(&to), "original");
+        assert!(!exists(&backup));
+        assert_eq!(worker.progress.status.lock().unwrap().replaced, 0);
+    }
+
+    #[test]
+    fn a_completed_overwrite_drops_the_original() {
+        let dir = tempfile::tempdir().unwrap();
+        let (from, to) = (dir.path().join("from"), dir.path().join("to"));
+        fs::create_dir(&from).unwrap();
+        write(&from.join("new.txt"), "new");
+        write(&to, "original");
+
+        let progress = run(JobKind::Copy, vec![item(&from, &to)], &[(Resolution::Overwrite, false)]);
+        assert_eq!(progress.state(), JobState::Done);
+        assert_eq!(read(&to.join("new.txt")), "new");
+        assert!(!exists(&dir.path().join(".to.old")));
+        assert_eq!(progress.status.lock().unwrap().replaced, 1);
+    }
+
+    fn set_modified(path: &Path, ago: Duration) {
+        let file = OpenOptions::new().write(true).open(path).unwrap();
+        file.set_modified(std::time::SystemTime::now() - ago).unwrap();
+    }
+
+    /// A source and an existing target, both files.
+    fn conflicting_files(dir: &Path) -> (PathBuf, PathBuf) {
+        let (from, to) = (dir.join("from.txt"), dir.join("to.txt"));
+        write(&from, "new");
+        write(&to, "old");
+        (from, to)
+    }
+
+    #[test]
+    fn overwrite_replaces_the_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let (from, to) = conflicting_files(dir.path());
+
+        let progress = run(JobKind::Copy, vec![item(&from, &to)], &[(Resolution::Overwrite, false)]);
+        assert_eq!(progress.state(), JobState::Done);
+        assert_eq!(read(&to), "new");
+        assert_eq!(progress.status.lock().unwrap().replaced, 1);
+        assert!(!exists(&fileops::partial_path(&to)));
+    }
+
+    #[test]
+    fn overwrite_if_newer_keeps_a_newer_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let (from, to) = conflicting_files(dir.path());
+        set_modified(&from, Duration::from_secs(3600));
+
+        let progress = run(JobKind::Copy, vec![item(&from, &to)], &[(Resolution::OverwriteIfNewer, false)]);
+        assert_eq!(progress.state(), JobState::Done);
+        assert_eq!(read(&to), "old");
+        assert_eq!(progress.status.lock().unwrap().skipped, 1);
+
+        set_modified(&to, Duration::from_secs(7200));
+        let progress = run(JobKind::Copy, vec![item(&from, &to)], &[(Resolution::OverwriteIfNewer, false)]);
+        assert_eq!(read(&to), "new");
+        assert_eq!(progress.status.lock().unwrap().replaced, 1);
+    }
+
+    #[test]
+    fn rename_keeps_both_under_a_numbered_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let (from, to) = conflicting_files(dir.path());
+        write(&dir.path().join("to (2).txt"), "taken");
+
+        let progress = run(JobKind::Move, vec![item(&from, &to)], &[(Resolution::Rename, false)]);
+        assert_eq!(progress.state(), JobState::Done);
+        assert_eq!(read(&to), "old");
+        assert_eq!(read(&dir.path().join("to (3).txt")), "new");
+        assert_eq!(targets(&progress), [dir.path().join("to (3).txt")]);
+        assert!(!exists(&from));
+    }
+
+    #[test]
+    fn skip_leaves_both_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let (from, to) = conflicting_files(dir.path());
+
+        let progress = run(JobKind::Move, vec![item(&from, &to)], &[(Resolution::Skip, false)]);
+        assert_eq!(progress.state(), JobState::Done);
+        assert_eq!(read(&from), "new");
+        assert_eq!(read(&to), "old");
+        assert!(targets(&progress).is_empty());
+    }
+
+    #[test]
+    fn apply_to_all_answers_later_conflicts() {
+        let dir = tempfile::tempdir().unwrap();
+        let (from, to) = (dir.path().join("from"), dir.path().join("to"));
+        fs::create_dir(&from).unwrap();
+        fs::create_dir(&to).unwrap();
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            write(&from.join(name), "new");
+            write(&to.join(name), "old");
+        }
+
+        // A second question would find no answer and cancel the job
+        let progress = run(JobKind::Copy, vec![item(&from, &to)], &[(Resolution::Overwrite, true)]);
+        assert_eq!(progress.state(), JobState::Done);
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            assert_eq!(read(&to.join(name)), "new");
+        }
+    }
+
+    #[test]
+    fn each_conflict_is_asked_about_without_apply_to_all() {
+        let dir = tempfile::tempdir().unwrap();
+        let (from, to) = (dir.path().join("from"), dir.path().join("to"));
+        fs::create_dir(&from).unwrap();
+        fs::create_dir(&to).unwrap();
+        for name in ["a.txt", "b.txt"] {
+            write(&from.join(name), "new");
+            write(&to.join(name), "old");
+        }
+
+        // The second question finds no answer, which ends the job
+        let progress = run(JobKind::Copy, vec![item(&from, &to)], &[(Resolution::Overwrite, false)]);
+        assert_eq!(progress.state(), JobState::Failed);
+        let overwritten = ["a.txt", "b.txt"].iter().filter(|name| read(&to.join(name)) == "new").count();
+        assert_eq!(overwritten, 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn overwriting_a_link_replaces_the_link_not_what_it_points_to() {
+        let dir = tempfile::tempdir().unwrap();
+        let (from, to) = conflicting_files(dir.path());
+        let link = dir.path().join("link.txt");
+        std::os::unix::fs::symlink(&to, &link).unwrap();
+
+        let progress = run(JobKind::Copy, vec![item(&from, &link)], &[(Resolution::Overwrite, false)]);
+        assert_eq!(progress.state(), JobState::Done);
+        assert!(!fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(read(&link), "new");
+        assert_eq!(read(&to), "old");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn a_broken_link_in_the_way_is_a_conflict() {
+        let dir = tempfile::tempdir().unwrap();
+        let from = dir.path().join("from.txt");
+        write(&from, "new");
+        let link = dir.path().join("link.txt");
+        std::os::unix::fs::symlink(dir.path().join("missing"), &link).unwrap();
+
+        let progress = run(JobKind::Copy, vec![item(&from, &link)], &[(Resolution::Skip, false)]);
+        assert_eq!(progress.status.lock().unwrap().skipped, 1);
+        assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert!(!exists(&dir.path().join("missing")));
+    }
+
+    #[test]
+    fn a_target_gone_while_asking_is_just_written() {
+        let dir = tempfile::tempdir().unwrap();
+        let (from, to) = conflicting_files(dir.path());
+
+        let mut worker = worker(JobKind::Copy, Vec::new(), &[]);
+        // The target goes before the answer comes
+        let (sender, receiver) = mpsc::channel();
+        worker.answers = receiver;
+        let target = to.clone();
+        let answering = thread::spawn(move || {
+            thread::sleep(PAUSE_POLL);
+            fs::remove_file(&target).unwrap();
+            sender.send((Resolution::Overwrite, false)).unwrap();
+        });
+        let claim = worker.claim(&from, &to, true).unwrap().unwrap();
+        answering.join().unwrap();
+        assert!(!claim.replace);
+        assert!(claim.backup.is_none());
+        assert_eq!(claim.target, to);
+    }
+}
@@ -1,7 +1,12 @@
+mod actions;
 mod app;
 mod explorer;
+mod fileops;
+mod history;
+mod jobs;
 mod search;
 mod terminal;
+mod trash;
 
 use eframe::NativeOptions;
 
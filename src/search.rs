@@ -8,6 +8,7 @@ pub struct SearchResult {
     pub path: PathBuf,
     pub line_number: usize,
     pub line_content: String,
+    #[allow(dead_code)]
     pub matched_text: String,
 }
 
@@ -48,12 +49,13 @@ impl SearchPanel {
         self.visible
     }
     
+    #[allow(dead_code)]
     pub fn set_search_path(&mut self, path: PathBuf) {
         self.search_path = Some(path);
     }
     
     pub fn check_search(&mut self) -> Option<PathBuf> {
-        self.pending_search.take().map(|_| self.search_path.clone()).flatten()
+        self.pending_search.take().and_then(|_| self.search_path.clone())
     }
     
     fn execute_search(&mut self) {
@@ -116,10 +118,8 @@ impl SearchPanel {
                             execute_search = true;
                         }
                         
-                        if self.search_in_progress {
-                            if ui.button("⏹").clicked() {
-                                // TODO: Cancel search
-                            }
+                        if self.search_in_progress && ui.button("⏹").clicked() {
+                            // TODO: Cancel search
                         }
                     });
                     
@@ -1,94 +1,276 @@
-use egui::{Ui, ScrollArea, TextEdit, Color32, RichText, Key, Modifiers};
-use portable_pty::{CommandBuilder, NativePtySystem, PtyPair, PtySize, PtySystem};
+mod emulator;
+mod export;
+mod find;
+mod history;
+mod input;
+mod integration;
+mod links;
+mod manager;
+mod notify;
+mod profile;
+mod scrollback;
+mod selection;
+mod session_log;
+mod shell;
+mod style;
+
+use egui::{Ui, ScrollArea, TextEdit, Color32, RichText, Key, Modifiers, Sense, Vec2, Event, EventFilter, Response};
+use crate::explorer::DraggedPaths;
+use emulator::{CommandRecord, Emulator, MouseTracking, ShellState};
+use export::{ExportAction, ExportDialog, ExportFormat};
+use find::{FindAction, FindBar};
+use history::{ReverseSearch, SearchAction, SharedHistory};
+use links::Link;
+use selection::{Selection, SelectionMode};
+use session_log::{LogMode, SessionLog};
+use portable_pty::{Child, ExitStatus, NativePtySystem, PtyPair, PtySize, PtySystem};
 use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
 use std::thread;
-use termwiz::surface::Surface;
+use std::time::{Duration, Instant};
+
+pub use links::{LinkAction, LinkTarget};
+pub use manager::TerminalManager;
+pub use notify::NotifySettings;
+pub use profile::TerminalProfile;
+pub use scrollback::ScrollbackLimit;
+pub use shell::ShellKind;
 
 const TERMINAL_COLS: u16 = 80;
 const TERMINAL_ROWS: u16 = 24;
+const MIN_COLS: u16 = 20;
+const MIN_ROWS: u16 = 2;
+const CWD_POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// Space left of the grid for command status markers.
+const GUTTER_WIDTH: f32 = 12.0;
+/// How long the grid flashes when the program rings the bell.
+const BELL_FLASH: Duration = Duration::from_millis(200);
 
 pub struct TerminalPanel {
     current_dir: PathBuf,
-    _surface: Surface,
-    scrollback: Vec<String>,
+    emulator: Emulator,
     input_buffer: String,
     pty_pair: Option<Box<PtyPair>>,
     pty_writer: Option<Box<dyn Write + Send>>,
-    output_receiver: std::sync::mpsc::Receiver<String>,
-    output_sender: std::sync::mpsc::Sender<String>,
-    command_history: Vec<String>,
+    output_receiver: std::sync::mpsc::Receiver<Vec<u8>>,
+    output_sender: std::sync::mpsc::Sender<Vec<u8>>,
+    /// Set on the first frame so the reader thread can wake the UI.
+    repaint_ctx: Arc<OnceLock<egui::Context>>,
+    child: Option<Box<dyn Child + Send + Sync>>,
+    exit_status: Option<ExitStatus>,
+    history: SharedHistory,
+    /// Commands being stepped through with the arrow keys, in recall order.
+    history_view: Vec<String>,
     history_index: Option<usize>,
+    reverse_search: ReverseSearch,
     focus_input: bool,
+    raw_input: bool,
+    focus_grid: bool,
+    shell_pid: Option<u32>,
+    profile: TerminalProfile,
+    /// `None` when the profile runs something other than a known shell.
+    shell_kind: Option<ShellKind>,
+    osc7_seen: bool,
+    last_cwd_poll: Instant,
+    last_polled_cwd: Option<PathBuf>,
+    pending_navigation: Option<PathBuf>,
+    queued_dir: Option<PathBuf>,
+    /// Command lines from custom actions, typed once the shell is idle.
+    queued_commands: Vec<String>,
+    line_dirty: bool,
+    selected_command: Option<usize>,
+    prompt_anchor: Option<usize>,
+    scroll_to_line: Option<(usize, egui::Align)>,
+    find: FindBar,
+    selection: Option<Selection>,
+    /// Button of the last press reported to a mouse-aware application.
+    mouse_button: Option<u8>,
+    mouse_cell: Option<(usize, usize)>,
+    /// Wheel movement in lines not yet sent to the program, so small
+    /// trackpad deltas add up instead of each sending a whole step.
+    wheel_lines: f32,
+    /// Raw input was switched on because a program entered the alternate
+    /// screen, and is switched off again when it leaves.
+    raw_for_alternate: bool,
+    /// Links found in the hovered line, with its number and the buffer
+    /// revision they were found at.
+    hovered_links: Option<(usize, usize, Vec<Link>)>,
+    pending_link: Option<LinkAction>,
+    session_log: Option<SessionLog>,
+    export_dialog: Option<ExportDialog>,
+    /// Text to insert at the input line's cursor on the next frame.
+    pending_insert: Option<String>,
+    /// When the bell last rang, while the grid is flashing.
+    bell_flash: Option<Instant>,
+    /// Output and bells since the terminal was last on screen.
+    unseen_output: bool,
+    unseen_bell: bool,
 }
 
 impl TerminalPanel {
-    pub fn new(initial_dir: PathBuf) -> Self {
+    pub fn new(initial_dir: PathBuf, profile: TerminalProfile) -> Self {
         let (tx, rx) = std::sync::mpsc::channel();
+        let initial_dir = profile.start_dir(&initial_dir);
         
         let mut terminal = Self {
             current_dir: initial_dir.clone(),
-            _surface: Surface::new(TERMINAL_COLS as usize, TERMINAL_ROWS as usize),
-            scrollback: Vec::new(),
+            emulator: Emulator::new(TERMINAL_COLS as usize, TERMINAL_ROWS as usize),
             input_buffer: String::new(),
             pty_pair: None,
             pty_writer: None,
             output_receiver: rx,
             output_sender: tx,
-            command_history: Vec::new(),
+            repaint_ctx: Arc::new(OnceLock::new()),
+            child: None,
+            exit_status: None,
+            history: SharedHistory::default(),
+            history_view: Vec::new(),
             history_index: None,
+            reverse_search: ReverseSearch::default(),
             focus_input: true,
+            raw_input: false,
+            focus_grid: false,
+            shell_pid: None,
+            profile,
+            shell_kind: None,
+            osc7_seen: false,
+            last_cwd_poll: Instant::now(),
+            last_polled_cwd: None,
+            pending_navigation: None,
+            queued_dir: None,
+            queued_commands: Vec::new(),
+            line_dirty: false,
+            selected_command: None,
+            prompt_anchor: None,
+            scroll_to_line: None,
+            find: FindBar::default(),
+            selection: None,
+            mouse_button: None,
+            mouse_cell: None,
+            wheel_lines: 0.0,
+            raw_for_alternate: false,
+            hovered_links: None,
+            pending_link: None,
+            session_log: None,
+            export_dialog: None,
+            pending_insert: None,
+            bell_flash: None,
+            unseen_output: false,
+            unseen_bell: false,
         };
         
         terminal.spawn_shell(initial_dir);
         terminal
     }
     
+    pub fn current_dir(&self) -> &Path {
+        &self.current_dir
+    }
+    
+    /// The shell running in the terminal, if the profile runs one.
+    pub fn shell_kind(&self) -> Option<ShellKind> {
+        self.shell_kind
+    }
+    
+    pub fn profile(&self) -> &TerminalProfile {
+        &self.profile
+    }
+    
+    /// Window title set by the running program, if any.
+    pub fn title(&self) -> &str {
+        self.emulator.title()
+    }
+    
+    /// Change the shell's directory to follow the explorer. The `cd` is only
+    /// typed when the shell is idle at an empty prompt; otherwise it is
+    /// queued and sent once the foreground job finishes.
     pub fn set_directory(&mut self, path: PathBuf) {
-        if self.current_dir != path {
-            self.current_dir = path.clone();
-            // Send cd command to terminal
-            let cd_command = format!("cd \"{}\"\n", path.display());
-            if let Some(writer) = &mut self.pty_writer {
-                let _ = writer.write_all(cd_command.as_bytes());
-                let _ = writer.flush();
-            }
+        // Only shells understand the cd we would type
+        if self.current_dir == path || self.shell_kind.is_none() {
+            self.queued_dir = None;
+            return;
+        }
+        self.queued_dir = Some(path);
+        self.flush_queued_dir();
+    }
+    
+    fn flush_queued_dir(&mut self) {
+        if self.queued_dir.is_none() || !self.shell_is_idle() {
+            return;
+        }
+        if let (Some(path), Some(kind)) = (self.queued_dir.take(), self.shell_kind) {
+            let cd_command = kind.cd_command(&path);
+            self.current_dir = path;
+            self.write_pty(cd_command.as_bytes());
+        }
+    }
+    
+    /// True when the shell itself owns the terminal (no foreground job such
+    /// as vim is running) and the user has nothing typed at the prompt.
+    fn shell_is_idle(&self) -> bool {
+        if let Some(idle) = self.emulator.at_empty_prompt() {
+            return idle;
+        }
+        if self.line_dirty {
+            return false;
+        }
+        foreground_is_shell(self.pty_pair.as_deref(), self.shell_pid)
+    }
+    
+    /// Resize the screen and the PTY, which delivers SIGWINCH to the shell.
+    fn resize(&mut self, cols: u16, rows: u16) {
+        let cols = cols.max(MIN_COLS);
+        let rows = rows.max(MIN_ROWS);
+        if self.emulator.size() == (cols as usize, rows as usize) {
+            return;
+        }
+        self.emulator.resize(cols as usize, rows as usize);
+        if let Some(pair) = &self.pty_pair {
+            let _ = pair.master.resize(PtySize {
+                rows,
+                cols,
+                pixel_width: 0,
+                pixel_height: 0,
+            });
+        }
+    }
+    
+    fn write_pty(&mut self, bytes: &[u8]) {
+        if let Some(writer) = &mut self.pty_writer {
+            let _ = writer.write_all(bytes);
+            let _ = writer.flush();
         }
     }
     
     fn spawn_shell(&mut self, working_dir: PathBuf) {
         let pty_system = NativePtySystem::default();
         
+        let (cols, rows) = self.emulator.size();
         let pair = match pty_system.openpty(PtySize {
-            rows: TERMINAL_ROWS,
-            cols: TERMINAL_COLS,
+            rows: rows as u16,
+            cols: cols as u16,
             pixel_width: 0,
             pixel_height: 0,
         }) {
             Ok(p) => p,
             Err(e) => {
-                self.scrollback.push(format!("Failed to open PTY: {}", e));
+                self.emulator.print_notice(&format!("Failed to open PTY: {}", e));
                 return;
             }
         };
         
-        // Get the shell
-        let shell = std::env::var("SHELL").unwrap_or_else(|_| {
-            if cfg!(target_os = "windows") {
-                "cmd.exe".to_string()
-            } else {
-                "/bin/sh".to_string()
-            }
-        });
-        
-        let mut cmd = CommandBuilder::new(&shell);
-        cmd.cwd(working_dir);
+        let (cmd, shell_kind) = self.profile.command(&working_dir);
+        self.shell_kind = shell_kind;
         
         // Spawn the slave
         match pair.slave.spawn_command(cmd) {
-            Ok(_) => {}
+            Ok(child) => {
+                self.shell_pid = child.process_id();
+                self.child = Some(child);
+            }
             Err(e) => {
-                self.scrollback.push(format!("Failed to spawn shell: {}", e));
+                self.emulator.print_notice(&format!("Failed to start {}: {}", self.profile.program(), e));
                 return;
             }
         }
@@ -97,7 +279,7 @@ impl TerminalPanel {
         let writer = match pair.master.take_writer() {
             Ok(w) => w,
             Err(e) => {
-                self.scrollback.push(format!("Failed to get PTY writer: {}", e));
+                self.emulator.print_notice(&format!("Failed to get PTY writer: {}", e));
                 return;
             }
         };
@@ -108,108 +290,371 @@ impl TerminalPanel {
         let mut reader = match pair.master.try_clone_reader() {
             Ok(r) => r,
             Err(e) => {
-                self.scrollback.push(format!("Failed to get PTY reader: {}", e));
+                self.emulator.print_notice(&format!("Failed to get PTY reader: {}", e));
                 return;
             }
         };
         
         let sender = self.output_sender.clone();
+        let repaint_ctx = Arc::clone(&self.repaint_ctx);
         thread::spawn(move || {
             let mut buf = [0u8; 1024];
             loop {
                 match reader.read(&mut buf) {
                     Ok(0) => break, // EOF
                     Ok(n) => {
-                        let output = String::from_utf8_lossy(&buf[..n]);
-                        if sender.send(output.to_string()).is_err() {
+                        if sender.send(buf[..n].to_vec()).is_err() {
                             break;
                         }
                     }
                     Err(_) => break,
                 }
+                if let Some(ctx) = repaint_ctx.get() {
+                    ctx.request_repaint();
+                }
+            }
+            // Wake the UI so it notices the shell has gone
+            if let Some(ctx) = repaint_ctx.get() {
+                ctx.request_repaint();
             }
         });
         
         self.pty_pair = Some(Box::new(pair));
         
         // Display welcome message
-        self.scrollback.push(format!(
-            "🖥️  Terminal ready in: {}\n",
+        self.emulator.print_notice(&format!(
+            "🖥️  Terminal ready in: {}",
             self.current_dir.display()
         ));
     }
     
-    pub fn update(&mut self, _ctx: &egui::Context) {
+    /// A directory the shell has moved to since the last call, so the
+    /// explorer can follow it.
+    pub fn check_navigation(&mut self) -> Option<PathBuf> {
+        self.pending_navigation.take()
+    }
+    
+    fn shell_moved_to(&mut self, dir: PathBuf) {
+        if dir != self.current_dir {
+            self.current_dir = dir.clone();
+            self.pending_navigation = Some(dir);
+        }
+    }
+    
+    /// Whether output arrived since `mark_seen`.
+    pub fn has_unseen_output(&self) -> bool {
+        self.unseen_output
+    }
+    
+    /// Whether the bell rang since `mark_seen`.
+    pub fn has_unseen_bell(&self) -> bool {
+        self.unseen_bell
+    }
+    
+    /// Called while the terminal is on screen.
+    pub fn mark_seen(&mut self) {
+        self.unseen_output = false;
+        self.unseen_bell = false;
+    }
+    
+    /// Commands that finished since the last call, from shell integration
+    /// marks.
+    pub fn take_finished_commands(&mut self) -> Vec<CommandRecord> {
+        self.emulator.take_finished_commands()
+    }
+    
+    /// Whether the shell is still running.
+    pub fn is_alive(&self) -> bool {
+        self.child.is_some()
+    }
+    
+    /// Kill the shell if it is still running and start a fresh one in the
+    /// current directory.
+    pub fn restart(&mut self) {
+        self.stop_shell();
+        self.drain_output();
+        self.emulator.shell_exited();
+        self.exit_status = None;
+        self.osc7_seen = false;
+        self.last_polled_cwd = None;
+        self.queued_dir = None;
+        self.queued_commands.clear();
+        self.line_dirty = false;
+        let dir = self.current_dir.clone();
+        self.spawn_shell(dir);
+        self.focus_input = !self.raw_input;
+        self.focus_grid = self.raw_input;
+    }
+    
+    /// Hang up on the shell and reap it. A shell that does not exit right
+    /// away is waited on in the background so it never lingers as a zombie.
+    fn stop_shell(&mut self) {
+        self.pty_writer = None;
+        self.pty_pair = None;
+        self.shell_pid = None;
+        if let Some(mut child) = self.child.take() {
+            let _ = child.kill();
+            if !matches!(child.try_wait(), Ok(Some(_))) {
+                thread::spawn(move || {
+                    let _ = child.wait();
+                });
+            }
+        }
+    }
+    
+    /// Reap the shell once it has exited and report how it ended.
+    fn check_child(&mut self) {
+        let Some(child) = &mut self.child else {
+            return;
+        };
+        let status = match child.try_wait() {
+            Ok(Some(status)) => status,
+            Ok(None) => return,
+            Err(e) => {
+                self.emulator.print_notice(&format!("Failed to wait for shell: {}", e));
+                ExitStatus::with_exit_code(1)
+            }
+        };
+        
+        self.child = None;
+        self.shell_pid = None;
+        self.pty_writer = None;
+        self.pty_pair = None;
+        // Output written just before exiting belongs before the notice
+        self.drain_output();
+        self.emulator.shell_exited();
+        self.emulator.print_notice(&format!("[{}]", describe_exit(&status)));
+        self.exit_status = Some(status);
+    }
+    
+    pub fn update(&mut self, ctx: &egui::Context) {
+        self.repaint_ctx.get_or_init(|| ctx.clone());
+        
         // Read any available output
+        if self.drain_output() {
+            self.unseen_output = true;
+        }
+        if self.emulator.take_bell() {
+            self.bell_flash = Some(Instant::now());
+            self.unseen_bell = true;
+        }
+        
+        self.check_child();
+        
+        // Full-screen programs need every keystroke
+        if self.emulator.alternate_screen() && !self.raw_input {
+            self.raw_input = true;
+            self.raw_for_alternate = true;
+            self.focus_grid = true;
+        } else if !self.emulator.alternate_screen() && self.raw_for_alternate {
+            self.raw_input = false;
+            self.raw_for_alternate = false;
+            self.focus_input = true;
+        }
+        
+        if self.child.is_some() && self.emulator.shell_state().is_none() {
+            // Without shell integration, keep polling so an exit is noticed
+            // even if the PTY stays open
+            ctx.request_repaint_after(CWD_POLL_INTERVAL);
+        }
+        
+        // Track the shell's working directory: OSC 7 when the shell reports
+        // it, otherwise poll /proc/<pid>/cwd
+        if let Some(dir) = self.emulator.take_working_dir() {
+            self.osc7_seen = true;
+            self.shell_moved_to(dir);
+        } else if !self.osc7_seen && self.last_cwd_poll.elapsed() >= CWD_POLL_INTERVAL {
+            self.last_cwd_poll = Instant::now();
+            // Only react when the polled directory itself changes, so a cd we
+            // have just typed is not undone before the shell runs it
+            let polled = self.shell_pid.and_then(process_cwd);
+            if polled != self.last_polled_cwd {
+                self.last_polled_cwd = polled.clone();
+                if let Some(dir) = polled {
+                    self.shell_moved_to(dir);
+                }
+            }
+            ctx.request_repaint_after(CWD_POLL_INTERVAL);
+        }
+        
+        if self.queued_dir.is_some() {
+            self.flush_queued_dir();
+            ctx.request_repaint_after(CWD_POLL_INTERVAL);
+        }
+        if !self.queued_commands.is_empty() {
+            self.flush_queued_commands();
+            ctx.request_repaint_after(CWD_POLL_INTERVAL);
+        }
+        
+        // Answer terminal queries (cursor position, device attributes)
+        let responses = self.emulator.take_responses();
+        if !responses.is_empty() {
+            self.write_pty(&responses);
+        }
+    }
+    
+    /// Feed everything the shell has written so far to the emulator and the
+    /// session log. Returns whether there was any output.
+    fn drain_output(&mut self) -> bool {
+        let mut any = false;
         while let Ok(output) = self.output_receiver.try_recv() {
-            self.process_output(&output);
+            self.emulator.advance(&output);
+            self.log_output(&output);
+            any = true;
         }
+        any
     }
     
-    fn process_output(&mut self, output: &str) {
-        // Process ANSI escape sequences and add to scrollback
-        // For now, we'll do simple line-based processing
-        let lines: Vec<&str> = output.split('\n').collect();
-        for (i, line) in lines.iter().enumerate() {
-            if i == lines.len() - 1 && !output.ends_with('\n') {
-                // Last line without newline - append to last scrollback entry
-                if let Some(last) = self.scrollback.last_mut() {
-                    last.push_str(line);
-                } else {
-                    self.scrollback.push(line.to_string());
+    fn log_output(&mut self, output: &[u8]) {
+        let Some(log) = &mut self.session_log else {
+            return;
+        };
+        if let Err(e) = log.write(output) {
+            let message = format!("Logging to {} stopped: {}", log.path().display(), e);
+            self.session_log = None;
+            self.emulator.print_notice(&message);
+        }
+    }
+    
+    /// Start recording program output to a new log file.
+    pub fn start_logging(&mut self, mode: LogMode) {
+        match SessionLog::start(mode) {
+            Ok(log) => self.session_log = Some(log),
+            Err(e) => self.emulator.print_notice(&format!("Failed to start logging: {}", e)),
+        }
+    }
+    
+    pub fn stop_logging(&mut self) {
+        self.session_log = None;
+    }
+    
+    /// Write everything from the oldest scrollback line to the cursor line
+    /// to `path`, returning a message for the export window.
+    fn export_scrollback(&self, format: ExportFormat, path: &Path) -> Result<String, String> {
+        let count = self.emulator.cursor_line() + 1 - self.emulator.first_line_number();
+        let text = export::render(self.emulator.lines().take(count), format);
+        std::fs::write(path, text)
+            .map(|_| format!("Exported {} lines to {}", count, path.display()))
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+    
+    fn render_export_dialog(&mut self, ui: &Ui) {
+        let Some(dialog) = &mut self.export_dialog else {
+            return;
+        };
+        match dialog.render(ui.ctx(), ui.id().with("export")) {
+            ExportAction::None => {}
+            ExportAction::Export(format, path) => {
+                let result = self.export_scrollback(format, &path);
+                if let Some(dialog) = &mut self.export_dialog {
+                    dialog.set_result(result);
                 }
-            } else {
-                self.scrollback.push(line.to_string());
             }
+            ExportAction::Close => self.export_dialog = None,
         }
-        
-        // Limit scrollback size
-        while self.scrollback.len() > 1000 {
-            self.scrollback.remove(0);
+    }
+    
+    fn render_log_menu(&mut self, ui: &mut Ui) {
+        if let Some(log) = &self.session_log {
+            let path = log.path().to_path_buf();
+            ui.label(RichText::new(path.display().to_string()).weak().small());
+            if ui.button("⏹ Stop logging").clicked() {
+                self.stop_logging();
+                ui.close();
+            }
+            if ui.button("Copy log path").clicked() {
+                ui.ctx().copy_text(path.display().to_string());
+                ui.close();
+            }
+        } else {
+            if ui
+                .button("⏺ Log text")
+                .on_hover_text("Record printed text, without colors or control sequences")
+                .clicked()
+            {
+                self.start_logging(LogMode::Text);
+                ui.close();
+            }
+            if ui
+                .button("⏺ Log raw output")
+                .on_hover_text("Record everything the program writes, escape sequences included")
+                .clicked()
+            {
+                self.start_logging(LogMode::Raw);
+                ui.close();
+            }
+        }
+        ui.separator();
+        if ui.button("Export scrollback...").clicked() {
+            self.export_dialog = Some(ExportDialog::new(&self.current_dir));
+            ui.close();
         }
     }
     
+    /// Run a command line as if entered in the input line. Like a `cd`, it
+    /// waits until the shell is idle, so it is never typed into a running
+    /// program or appended to what the user has typed.
+    pub fn run_command(&mut self, command: &str) {
+        if self.exit_status.is_some() {
+            return;
+        }
+        self.history.borrow_mut().record(command, &self.current_dir);
+        self.queued_commands.push(command.to_string());
+    }
+    
+    /// Type the queued commands as one line once the shell is idle. They
+    /// are only flushed from `update`, so the commands one action queues
+    /// together run one after the other.
+    fn flush_queued_commands(&mut self) {
+        if self.queued_commands.is_empty() || !self.shell_is_idle() {
+            return;
+        }
+        let kind = self.shell_kind.unwrap_or(ShellKind::Sh);
+        let line = kind.sequence(&std::mem::take(&mut self.queued_commands));
+        self.write_pty(line.as_bytes());
+    }
+    
     fn execute_command(&mut self) {
         let command = self.input_buffer.clone();
         if command.trim().is_empty() {
             // Just send newline
-            if let Some(writer) = &mut self.pty_writer {
-                let _ = writer.write_all(b"\n");
-                let _ = writer.flush();
-            }
+            self.write_pty(b"\n");
             return;
         }
         
         // Add to history
-        self.command_history.push(command.clone());
-        if self.command_history.len() > 100 {
-            self.command_history.remove(0);
-        }
+        self.history.borrow_mut().record(&command, &self.current_dir);
         self.history_index = None;
         
         // Send to PTY
-        if let Some(writer) = &mut self.pty_writer {
-            let cmd_with_newline = format!("{}\n", command);
-            let _ = writer.write_all(cmd_with_newline.as_bytes());
-            let _ = writer.flush();
-        }
+        let cmd_with_newline = format!("{}\n", command);
+        self.write_pty(cmd_with_newline.as_bytes());
         
         self.input_buffer.clear();
     }
     
+    /// Use a history shared with other terminals instead of this one's own.
+    pub fn set_history(&mut self, history: SharedHistory) {
+        self.history = history;
+        self.history_index = None;
+    }
+    
     fn history_prev(&mut self) {
-        if self.command_history.is_empty() {
+        if self.history_index.is_none() {
+            self.history_view = self.history.borrow().recall(&self.current_dir);
+        }
+        if self.history_view.is_empty() {
             return;
         }
         
         match self.history_index {
             None => {
-                self.history_index = Some(self.command_history.len() - 1);
-                self.input_buffer = self.command_history.last().unwrap().clone();
+                self.history_index = Some(self.history_view.len() - 1);
+                self.input_buffer = self.history_view.last().unwrap().clone();
             }
             Some(idx) if idx > 0 => {
                 self.history_index = Some(idx - 1);
-                self.input_buffer = self.command_history[idx - 1].clone();
+                self.input_buffer = self.history_view[idx - 1].clone();
             }
             _ => {}
         }
@@ -217,9 +662,9 @@ impl TerminalPanel {
     
     fn history_next(&mut self) {
         match self.history_index {
-            Some(idx) if idx < self.command_history.len() - 1 => {
+            Some(idx) if idx < self.history_view.len() - 1 => {
                 self.history_index = Some(idx + 1);
-                self.input_buffer = self.command_history[idx + 1].clone();
+                self.input_buffer = self.history_view[idx + 1].clone();
             }
             Some(_) => {
                 self.history_index = None;
@@ -229,52 +674,794 @@ impl TerminalPanel {
         }
     }
     
+    /// Scroll to the previous (`forward == false`) or next prompt recorded
+    /// by shell integration.
+    fn jump_to_prompt(&mut self, forward: bool) {
+        let mut prompts = self.emulator.commands().iter().map(|c| c.prompt_line);
+        let target = match (forward, self.prompt_anchor) {
+            (false, anchor) => prompts.rfind(|&line| line < anchor.unwrap_or(usize::MAX)),
+            (true, Some(anchor)) => prompts.find(|&line| line > anchor),
+            (true, None) => None,
+        };
+        self.prompt_anchor = target;
+        self.scroll_to_line = target.map(|line| (line, egui::Align::TOP));
+    }
+    
+    /// Paint the scrollback followed by the live screen as a grid of
+    /// monospace cells, with a block cursor on the screen.
+    fn render_grid(&mut self, ui: &mut Ui) -> Response {
+        let font_id = egui::FontId::monospace(12.0);
+        let (char_width, row_height) = cell_size(ui, &font_id);
+        
+        let (cols, _) = self.emulator.size();
+        let line_count = self.emulator.line_count();
+        
+        let (rect, response) = ui.allocate_exact_size(
+            Vec2::new(
+                (GUTTER_WIDTH + cols as f32 * char_width).max(ui.available_width()),
+                line_count as f32 * row_height,
+            ),
+            Sense::click_and_drag(),
+        );
+        let painter = ui.painter_at(rect);
+        let clip = ui.clip_rect();
+        let text_left = rect.left() + GUTTER_WIDTH;
+        let first_line = self.emulator.first_line_number();
+        let row_rect = |line: usize| {
+            let top = rect.top() + line.saturating_sub(first_line) as f32 * row_height;
+            egui::Rect::from_min_size(egui::pos2(rect.left(), top), Vec2::new(rect.width(), row_height))
+        };
+        
+        // Highlight the output of the command picked in the gutter
+        if let Some(command) = self
+            .emulator
+            .commands()
+            .iter()
+            .find(|c| Some(c.prompt_line) == self.selected_command)
+        {
+            if let (Some(start), Some(end)) = (command.output_start, command.output_end) {
+                let highlight = row_rect(start).union(row_rect(end.max(start + 1) - 1));
+                painter.rect_filled(highlight, 0.0, ui.visuals().selection.bg_fill.gamma_multiply(0.4));
+            }
+        }
+        
+        // Only the visible rows are fetched, so a huge scrollback stays cheap
+        let first_row = ((clip.top() - rect.top()) / row_height).floor().max(0.0) as usize;
+        let end_row = (((clip.bottom() - rect.top()) / row_height).ceil().max(0.0) as usize).min(line_count);
+        
+        // Shift bypasses mouse reporting so text can still be selected
+        let reporting = self.emulator.mouse_tracking() != MouseTracking::Off
+            && !ui.input(|i| i.modifiers.shift);
+        if reporting || self.emulator.alternate_screen() {
+            self.report_mouse(ui, &response, rect, (char_width, row_height));
+        }
+        if !reporting {
+            self.handle_selection(ui, &response, rect, (char_width, row_height));
+        }
+        if let Some(selection) = &self.selection {
+            let fill = ui.visuals().selection.bg_fill;
+            for row in first_row..end_row {
+                let Some(columns) = selection.columns(&self.emulator, first_line + row) else {
+                    continue;
+                };
+                let top = rect.top() + row as f32 * row_height;
+                let highlight = egui::Rect::from_min_max(
+                    egui::pos2(text_left + columns.start.min(cols) as f32 * char_width, top),
+                    egui::pos2(text_left + columns.end.min(cols) as f32 * char_width, top + row_height),
+                );
+                painter.rect_filled(highlight, 0.0, fill);
+            }
+        }
+        
+        // Find bar matches, the current one stronger
+        for (index, found) in self.find.matches().iter().enumerate() {
+            let row = row_rect(found.line);
+            if !clip.intersects(row) {
+                continue;
+            }
+            let highlight = egui::Rect::from_min_max(
+                egui::pos2(text_left + found.start as f32 * char_width, row.top()),
+                egui::pos2(text_left + found.end as f32 * char_width, row.bottom()),
+            );
+            let color = if Some(index) == self.find.current() {
+                Color32::from_rgba_unmultiplied(255, 140, 0, 160)
+            } else {
+                Color32::from_rgba_unmultiplied(255, 210, 0, 60)
+            };
+            painter.rect_filled(highlight, 0.0, color);
+        }
+        
+        for row in first_row..end_row {
+            let Some(line) = self.emulator.line(first_line + row) else {
+                continue;
+            };
+            let top = rect.top() + row as f32 * row_height;
+            let job = style::line_job(&line, &font_id);
+            if job.text.is_empty() {
+                continue;
+            }
+            let galley = ui.fonts_mut(|f| f.layout_job(job));
+            painter.galley(egui::pos2(text_left, top), galley, style::DEFAULT_FG);
+        }
+        
+        // Command status markers from shell integration
+        let mut clicked_command = None;
+        for command in self.emulator.commands() {
+            if command.output_start.is_none() {
+                continue;
+            }
+            let marker_rect = egui::Rect::from_min_size(
+                row_rect(command.prompt_line).min,
+                Vec2::new(GUTTER_WIDTH, row_height),
+            );
+            if !clip.intersects(marker_rect) {
+                continue;
+            }
+            let (color, status) = match command.exit_status {
+                _ if command.is_running() => (Color32::YELLOW, "running".to_string()),
+                Some(0) => (Color32::GREEN, "exit 0".to_string()),
+                Some(code) => (Color32::LIGHT_RED, format!("exit {}", code)),
+                None => (Color32::GRAY, "finished".to_string()),
+            };
+            painter.circle_filled(marker_rect.center(), 3.0, color);
+            
+            let hover = match command.duration {
+                Some(duration) => format!("{} · {}\nClick to select and copy the output", status, format_duration(duration)),
+                None => status,
+            };
+            let marker = ui
+                .interact(marker_rect, response.id.with(("command", command.prompt_line)), Sense::click())
+                .on_hover_text(hover);
+            if marker.clicked() {
+                clicked_command = Some(command.prompt_line);
+            }
+        }
+        
+        if let Some((line, align)) = self.scroll_to_line.take() {
+            ui.scroll_to_rect(row_rect(line), Some(align));
+        }
+        
+        if self.emulator.cursor_visible() {
+            let (x, _) = self.emulator.cursor();
+            let x = x.min(cols.saturating_sub(1));
+            let cursor_rect = egui::Rect::from_min_size(
+                egui::pos2(
+                    text_left + x as f32 * char_width,
+                    rect.top() + (self.emulator.cursor_line() - first_line) as f32 * row_height,
+                ),
+                Vec2::new(char_width, row_height),
+            );
+            if response.has_focus() {
+                painter.rect_filled(cursor_rect, 0.0, Color32::from_white_alpha(140));
+            } else {
+                painter.rect_stroke(
+                    cursor_rect,
+                    0.0,
+                    egui::Stroke::new(1.0, Color32::from_white_alpha(140)),
+                    egui::StrokeKind::Inside,
+                );
+            }
+        }
+        
+        if !reporting {
+            self.handle_links(ui, &response, rect, (char_width, row_height));
+        }
+        
+        if let Some(prompt_line) = clicked_command {
+            self.select_command_output(ui.ctx(), prompt_line);
+        }
+        
+        let has_selection = self.selection.is_some_and(|s| !s.is_empty(&self.emulator));
+        response.context_menu(|ui| {
+            if ui
+                .add_enabled(has_selection, egui::Button::new("Copy").shortcut_text("Ctrl+Shift+C"))
+                .clicked()
+            {
+                self.copy_selection(ui.ctx());
+                ui.close();
+            }
+            if ui.button("Select all").clicked() {
+                let mut selection = Selection::new((self.emulator.first_line_number(), 0), SelectionMode::Line);
+                selection.extend_to((self.emulator.cursor_line(), 0));
+                self.selection = Some(selection);
+                ui.close();
+            }
+        });
+        
+        response
+    }
+    
+    /// Forward mouse events to an application that enabled mouse tracking.
+    /// On the alternate screen without tracking, the wheel sends arrow keys
+    /// instead, like xterm's alternateScroll.
+    fn report_mouse(&mut self, ui: &Ui, response: &Response, rect: egui::Rect, cell: (f32, f32)) {
+        let (char_width, row_height) = cell;
+        let (cols, rows) = self.emulator.size();
+        let first_line = self.emulator.first_line_number();
+        let screen_top = self.emulator.screen_top_line();
+        let cell_at = |pos: egui::Pos2| {
+            let line = first_line + ((pos.y - rect.top()) / row_height).max(0.0) as usize;
+            let column = ((pos.x - rect.left() - GUTTER_WIDTH) / char_width).max(0.0) as usize;
+            let row = line.checked_sub(screen_top)?;
+            (row < rows).then_some((column.min(cols.saturating_sub(1)), row))
+        };
+        
+        let tracking = self.emulator.mouse_tracking();
+        let sgr = self.emulator.sgr_mouse();
+        let hovered = response.hovered();
+        let (events, modifiers) = ui.input(|i| (i.events.clone(), i.modifiers));
+        let mut reports: Vec<u8> = Vec::new();
+        for event in events {
+            match event {
+                Event::PointerButton { pos, button, pressed, modifiers } if tracking != MouseTracking::Off => {
+                    let code = match button {
+                        egui::PointerButton::Primary => input::MOUSE_LEFT,
+                        egui::PointerButton::Middle => input::MOUSE_MIDDLE,
+                        egui::PointerButton::Secondary => input::MOUSE_RIGHT,
+                        _ => continue,
+                    };
+                    // Releases are reported for presses we reported, even
+                    // outside the grid
+                    if (pressed && !hovered) || (!pressed && self.mouse_button != Some(code)) {
+                        continue;
+                    }
+                    let Some((column, row)) = cell_at(pos).or(self.mouse_cell) else {
+                        continue;
+                    };
+                    self.mouse_button = pressed.then_some(code);
+                    self.mouse_cell = Some((column, row));
+                    reports.extend(input::encode_mouse(code, column, row, !pressed, modifiers, sgr).unwrap_or_default());
+                }
+                Event::PointerMoved(pos) => {
+                    let button = match (tracking, self.mouse_button) {
+                        (MouseTracking::Drag | MouseTracking::Motion, Some(button)) => button,
+                        (MouseTracking::Motion, None) if hovered => input::MOUSE_NONE,
+                        _ => continue,
+                    };
+                    let Some(cell) = cell_at(pos) else {
+                        continue;
+                    };
+                    if self.mouse_cell == Some(cell) {
+                        continue;
+                    }
+                    self.mouse_cell = Some(cell);
+                    reports.extend(input::encode_mouse(
+                        button + input::MOUSE_MOTION,
+                        cell.0,
+                        cell.1,
+                        false,
+                        modifiers,
+                        sgr,
+                    ).unwrap_or_default());
+                }
+                Event::MouseWheel { unit, delta, .. } if hovered && delta.y != 0.0 => {
+                    self.wheel_lines += match unit {
+                        egui::MouseWheelUnit::Point => delta.y / row_height,
+                        egui::MouseWheelUnit::Line => delta.y,
+                        egui::MouseWheelUnit::Page => delta.y * rows as f32,
+                    };
+                    // One step per line crossed, keeping the remainder
+                    let steps = self.wheel_lines.trunc();
+                    if steps == 0.0 {
+                        continue;
+                    }
+                    self.wheel_lines -= steps;
+                    let up = steps > 0.0;
+                    let bytes = if tracking == MouseTracking::Off {
+                        let key = if up { Key::ArrowUp } else { Key::ArrowDown };
+                        let application = self.emulator.application_cursor_keys();
+                        input::encode_key(key, Modifiers::NONE, application)
+                    } else {
+                        let pointer = ui.input(|i| i.pointer.hover_pos());
+                        let Some((column, row)) = pointer.and_then(cell_at) else {
+                            continue;
+                        };
+                        let code = if up { input::MOUSE_WHEEL_UP } else { input::MOUSE_WHEEL_DOWN };
+                        input::encode_mouse(code, column, row, false, modifiers, sgr)
+                    };
+                    if let Some(bytes) = bytes {
+                        for _ in 0..steps.abs() as usize {
+                            reports.extend_from_slice(&bytes);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        if !reports.is_empty() {
+            self.write_pty(&reports);
+        }
+    }
+    
+    /// Start, extend or clear the mouse selection: drag selects cells,
+    /// double-click a word and triple-click a line.
+    fn handle_selection(&mut self, ui: &Ui, response: &Response, rect: egui::Rect, cell: (f32, f32)) {
+        let (char_width, row_height) = cell;
+        let first_line = self.emulator.first_line_number();
+        let last_line = first_line + self.emulator.line_count().saturating_sub(1);
+        let (cols, _) = self.emulator.size();
+        // Drags select between cells, clicks pick the cell under the pointer
+        let point_at = |pos: egui::Pos2, between_cells: bool| {
+            let row = ((pos.y - rect.top()) / row_height).max(0.0) as usize;
+            let x = ((pos.x - rect.left() - GUTTER_WIDTH) / char_width).max(0.0);
+            let column = if between_cells { x.round() } else { x.floor() } as usize;
+            ((first_line + row).min(last_line), column.min(cols))
+        };
+        
+        if response.triple_clicked() {
+            if let Some(pos) = response.interact_pointer_pos() {
+                self.selection = Some(Selection::new(point_at(pos, false), SelectionMode::Line));
+            }
+        } else if response.double_clicked() {
+            if let Some(pos) = response.interact_pointer_pos() {
+                self.selection = Some(Selection::new(point_at(pos, false), SelectionMode::Word));
+            }
+        } else if response.drag_started_by(egui::PointerButton::Primary) {
+            if let Some(origin) = ui.input(|i| i.pointer.press_origin()) {
+                self.selection = Some(Selection::new(point_at(origin, true), SelectionMode::Cell));
+            }
+        } else if response.dragged_by(egui::PointerButton::Primary) {
+            if let Some(pos) = response.interact_pointer_pos() {
+                if let Some(selection) = &mut self.selection {
+                    selection.extend_to(point_at(pos, true));
+                }
+                // Scroll while dragging past the edge of the view
+                let clip = ui.clip_rect();
+                if pos.y < clip.top() {
+                    ui.scroll_with_delta(Vec2::new(0.0, row_height));
+                } else if pos.y > clip.bottom() {
+                    ui.scroll_with_delta(Vec2::new(0.0, -row_height));
+                }
+            }
+        } else if response.clicked() {
+            self.selection = None;
+        }
+    }
+    
+    /// Underline the link under the pointer and turn a click on it into a
+    /// link action: reveal in the explorer, or open with Ctrl held.
+    fn handle_links(&mut self, ui: &Ui, response: &Response, rect: egui::Rect, cell: (f32, f32)) {
+        let (char_width, row_height) = cell;
+        let Some(pos) = response.hover_pos() else {
+            return;
+        };
+        let line = self.emulator.first_line_number() + ((pos.y - rect.top()) / row_height).max(0.0) as usize;
+        let x = (pos.x - rect.left() - GUTTER_WIDTH) / char_width;
+        if x < 0.0 {
+            return;
+        }
+        let column = x as usize;
+        
+        let revision = self.emulator.revision();
+        let stale = !matches!(&self.hovered_links, Some((l, r, _)) if *l == line && *r == revision);
+        if stale {
+            let links = self
+                .emulator
+                .line(line)
+                .map(|text| links::find_links(&text, &self.current_dir))
+                .unwrap_or_default();
+            self.hovered_links = Some((line, revision, links));
+        }
+        let Some((_, _, links)) = &self.hovered_links else {
+            return;
+        };
+        let Some(link) = links.iter().find(|link| link.columns.contains(&column)) else {
+            return;
+        };
+        
+        let top = rect.top() + (line - self.emulator.first_line_number()) as f32 * row_height;
+        let left = rect.left() + GUTTER_WIDTH;
+        ui.painter().hline(
+            left + link.columns.start as f32 * char_width..=left + link.columns.end as f32 * char_width,
+            top + row_height - 1.0,
+            egui::Stroke::new(1.0, style::DEFAULT_FG),
+        );
+        ui.ctx().set_cursor_icon(egui::CursorIcon::PointingHand);
+        let hint = match link.target {
+            LinkTarget::Path { .. } => "Click to reveal in the explorer, Ctrl+click to open",
+            LinkTarget::Url(_) => "Click to open",
+        };
+        response
+            .clone()
+            .on_hover_text_at_pointer(format!("{}\n{}", link.target.describe(), hint));
+        
+        if response.clicked() {
+            let target = link.target.clone();
+            let open = ui.input(|i| i.modifiers.command) || matches!(target, LinkTarget::Url(_));
+            self.pending_link = Some(if open { LinkAction::Open(target) } else { LinkAction::Reveal(target) });
+        }
+    }
+    
+    /// A link clicked since the last call, for the application to handle.
+    pub fn take_link_action(&mut self) -> Option<LinkAction> {
+        self.pending_link.take()
+    }
+    
+    /// Copy the selected text to the clipboard, if anything is selected.
+    pub fn copy_selection(&mut self, ctx: &egui::Context) {
+        if let Some(selection) = &self.selection {
+            if !selection.is_empty(&self.emulator) {
+                ctx.copy_text(selection.text(&self.emulator));
+            }
+        }
+    }
+    
+    pub fn set_scrollback_limit(&mut self, limit: ScrollbackLimit) {
+        self.emulator.set_scrollback_limit(limit);
+    }
+    
+    /// Show the scrollback find bar, focusing its query field.
+    pub fn open_find(&mut self) {
+        self.find.open();
+    }
+    
+    fn reveal_match(&mut self, index: usize) {
+        if let Some(found) = self.find.matches().get(index) {
+            self.scroll_to_line = Some((found.line, egui::Align::Center));
+        }
+    }
+    
+    /// Search the buffer and draw the find bar, acting on its buttons.
+    fn render_find_bar(&mut self, ui: &mut Ui) {
+        let first_line = self.emulator.first_line_number();
+        if self.find.update(self.emulator.lines(), first_line, self.emulator.revision()) {
+            if let Some(current) = self.find.current() {
+                self.reveal_match(current);
+            }
+        }
+        // Pick up output that arrived while refreshes were held back
+        ui.ctx().request_repaint_after(find::REFRESH_INTERVAL);
+        
+        match self.find.render(ui) {
+            FindAction::None => {}
+            FindAction::Reveal(index) => self.reveal_match(index),
+            FindAction::Close => {
+                self.find.close();
+                self.focus_input = !self.raw_input;
+                self.focus_grid = self.raw_input;
+            }
+        }
+    }
+    
+    /// Select a finished command's output and copy it to the clipboard.
+    fn select_command_output(&mut self, ctx: &egui::Context, prompt_line: usize) {
+        self.selected_command = Some(prompt_line);
+        let range = self
+            .emulator
+            .commands()
+            .iter()
+            .find(|c| c.prompt_line == prompt_line)
+            .and_then(|c| Some((c.output_start?, c.output_end?)));
+        if let Some((start, end)) = range {
+            ctx.copy_text(self.emulator.text_range(start, end));
+        }
+    }
+    
+    /// The Ctrl+R popup, above the input line at `input_rect`.
+    fn render_reverse_search(&mut self, ui: &Ui, input_rect: egui::Rect) {
+        let commands = self.history.borrow().recall(&self.current_dir);
+        let action = self.reverse_search.render(
+            ui.ctx(),
+            ui.id().with("reverse_search"),
+            input_rect.left_top(),
+            input_rect.width(),
+            &commands,
+        );
+        match action {
+            SearchAction::None => {}
+            SearchAction::Accept(command) => {
+                self.input_buffer = command;
+                self.history_index = None;
+                self.reverse_search.close();
+                self.focus_input = true;
+            }
+            SearchAction::Close => {
+                self.reverse_search.close();
+                self.focus_input = true;
+            }
+        }
+    }
+    
+    /// Forward every keystroke straight to the PTY while the grid has focus
+    /// in raw input mode, so interactive programs (vim, fzf, REPLs, shell
+    /// completion) see the keys they expect.
+    fn handle_raw_input(&mut self, ui: &mut Ui, response: &Response) {
+        if response.clicked() || response.drag_started() || self.focus_grid {
+            response.request_focus();
+            self.focus_grid = false;
+        }
+        if !response.has_focus() {
+            return;
+        }
+        
+        // Keep Tab, arrows and Escape from moving egui focus away
+        ui.memory_mut(|m| {
+            m.set_focus_lock_filter(
+                response.id,
+                EventFilter {
+                    tab: true,
+                    horizontal_arrows: true,
+                    vertical_arrows: true,
+                    escape: true,
+                },
+            )
+        });
+        
+        let application_cursor = self.emulator.application_cursor_keys();
+        let bracketed_paste = self.emulator.bracketed_paste();
+        let (events, modifiers) = ui.input(|i| (i.events.clone(), i.modifiers));
+        for event in events {
+            let bytes = match event {
+                Event::Text(text) => Some(input::encode_text(&text, modifiers)),
+                Event::Key { key, pressed: true, modifiers, .. } => {
+                    input::encode_key(key, modifiers, application_cursor)
+                }
+                // Ctrl+C/X/V arrive as clipboard commands rather than key
+                // presses; with Shift they copy and paste
+                Event::Copy if modifiers.shift => None,
+                Event::Copy => Some(vec![0x03]),
+                Event::Cut => Some(vec![0x18]),
+                Event::Paste(text) if modifiers.shift => Some(input::encode_paste(&text, bracketed_paste)),
+                Event::Paste(_) => Some(vec![0x16]),
+                _ => None,
+            };
+            if let Some(bytes) = bytes {
+                // Enter, Ctrl+C and Ctrl+U leave an empty prompt behind
+                self.line_dirty = !matches!(bytes.last(), Some(b'\r' | 0x03 | 0x15));
+                self.write_pty(&bytes);
+            }
+        }
+    }
+    
+    /// Type `paths`, quoted for the shell, at the input cursor, or straight
+    /// into the program in raw input mode.
+    pub fn insert_paths(&mut self, paths: &[PathBuf]) {
+        if paths.is_empty() || self.exit_status.is_some() {
+            return;
+        }
+        let kind = self.shell_kind.unwrap_or(ShellKind::Sh);
+        let quoted: Vec<String> = paths.iter().map(|path| kind.quote(path)).collect();
+        let text = quoted.join(" ");
+        if self.raw_input {
+            let bytes = input::encode_paste(&format!("{} ", text), self.emulator.bracketed_paste());
+            self.line_dirty = true;
+            self.write_pty(&bytes);
+            self.focus_grid = true;
+        } else {
+            self.pending_insert = Some(text);
+            self.focus_input = true;
+        }
+    }
+    
+    /// Splice pending inserted text into the input line at its cursor,
+    /// keeping it separated from the words around it.
+    fn apply_pending_insert(&mut self, ctx: &egui::Context, input_id: egui::Id) {
+        let Some(text) = self.pending_insert.take() else {
+            return;
+        };
+        let mut state = TextEdit::load_state(ctx, input_id).unwrap_or_default();
+        let char_count = self.input_buffer.chars().count();
+        let cursor = state
+            .cursor
+            .char_range()
+            .map_or(char_count, |range| range.primary.index.min(char_count));
+        let byte = self
+            .input_buffer
+            .char_indices()
+            .nth(cursor)
+            .map_or(self.input_buffer.len(), |(i, _)| i);
+        
+        let (before, after) = self.input_buffer.split_at(byte);
+        let mut insert = String::new();
+        if before.chars().last().is_some_and(|c| !c.is_whitespace()) {
+            insert.push(' ');
+        }
+        insert.push_str(&text);
+        if !after.starts_with(char::is_whitespace) {
+            insert.push(' ');
+        }
+        self.input_buffer = format!("{}{}{}", before, insert, after);
+        
+        let end = egui::text::CCursor::new(cursor + insert.chars().count());
+        state.cursor.set_char_range(Some(egui::text::CCursorRange::one(end)));
+        TextEdit::store_state(ctx, input_id, state);
+    }
+    
+    /// Accept explorer entries dropped anywhere on the panel.
+    fn handle_drop(&mut self, ui: &Ui, response: &Response) {
+        if response.dnd_hover_payload::<DraggedPaths>().is_some() {
+            ui.painter().rect_stroke(
+                response.rect,
+                2.0,
+                egui::Stroke::new(2.0, ui.visuals().selection.stroke.color),
+                egui::StrokeKind::Inside,
+            );
+        }
+        if let Some(paths) = response.dnd_release_payload::<DraggedPaths>() {
+            self.insert_paths(&paths.0);
+        }
+    }
+    
+    /// Fade a light wash over the grid after the bell rang.
+    fn paint_bell_flash(&mut self, ui: &Ui, rect: egui::Rect) {
+        let Some(rang) = self.bell_flash else {
+            return;
+        };
+        let elapsed = rang.elapsed();
+        if elapsed >= BELL_FLASH {
+            self.bell_flash = None;
+            return;
+        }
+        let strength = 1.0 - elapsed.as_secs_f32() / BELL_FLASH.as_secs_f32();
+        let color = Color32::from_white_alpha((strength * 60.0) as u8);
+        ui.painter().rect_filled(rect, 0.0, color);
+        ui.ctx().request_repaint();
+    }
+    
     pub fn render(&mut self, ui: &mut Ui) {
-        ui.vertical(|ui| {
+        let response = ui.vertical(|ui| {
             // Terminal header
             ui.horizontal(|ui| {
                 ui.label(RichText::new("🖥️  Terminal").strong());
                 ui.separator();
                 ui.label(format!("{}", self.current_dir.display()));
+                if !self.emulator.title().is_empty() {
+                    ui.separator();
+                    ui.label(RichText::new(self.emulator.title()).weak());
+                }
+                if let Some(queued) = &self.queued_dir {
+                    ui.label(RichText::new("⏳").weak()).on_hover_text(format!(
+                        "Will change to {} when the shell is idle",
+                        queued.display()
+                    ));
+                }
+                if !self.queued_commands.is_empty() {
+                    ui.label(RichText::new("⏳").weak()).on_hover_text(format!(
+                        "Will run when the shell is idle:\n{}",
+                        self.queued_commands.join("\n")
+                    ));
+                }
+                if self.emulator.shell_state() == Some(ShellState::Running) {
+                    ui.spinner().on_hover_text("A command is running");
+                }
+                if let Some(status) = &self.exit_status {
+                    let color = if status.success() { Color32::GRAY } else { Color32::LIGHT_RED };
+                    ui.label(RichText::new(describe_exit(status)).color(color));
+                }
+                if let Some(log) = &self.session_log {
+                    let mode = match log.mode() {
+                        LogMode::Raw => "raw output",
+                        LogMode::Text => "text",
+                    };
+                    ui.label(RichText::new("⏺ REC").color(Color32::LIGHT_RED))
+                        .on_hover_text(format!("Logging {} to {}", mode, log.path().display()));
+                }
                 
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui
+                        .button("⟳ Restart")
+                        .on_hover_text("Start a new shell in the current directory")
+                        .clicked()
+                    {
+                        self.restart();
+                    }
                     if ui.button("Clear").clicked() {
-                        self.scrollback.clear();
+                        self.emulator.clear();
+                    }
+                    ui.menu_button("💾", |ui| self.render_log_menu(ui))
+                        .response
+                        .on_hover_text("Log or export output");
+                    if ui
+                        .selectable_label(self.find.is_open(), "🔍")
+                        .on_hover_text("Find in scrollback (Ctrl+Shift+F)")
+                        .clicked()
+                    {
+                        if self.find.is_open() {
+                            self.find.close();
+                        } else {
+                            self.find.open();
+                        }
+                    }
+                    let has_prompts = !self.emulator.commands().is_empty();
+                    if ui
+                        .add_enabled(has_prompts, egui::Button::new("⏷"))
+                        .on_hover_text("Next prompt")
+                        .clicked()
+                    {
+                        self.jump_to_prompt(true);
+                    }
+                    if ui
+                        .add_enabled(has_prompts, egui::Button::new("⏶"))
+                        .on_hover_text("Previous prompt")
+                        .clicked()
+                    {
+                        self.jump_to_prompt(false);
+                    }
+                    if ui
+                        .selectable_label(self.raw_input, "⌨ Raw input")
+                        .on_hover_text("Send every keystroke directly to the shell")
+                        .clicked()
+                    {
+                        self.raw_input = !self.raw_input;
+                        self.focus_grid = self.raw_input;
+                        self.focus_input = !self.raw_input;
                     }
                 });
             });
             
             ui.separator();
             
+            if self.find.is_open() {
+                self.render_find_bar(ui);
+            }
+            self.render_export_dialog(ui);
+            
             // Scrollback display
-            let available_height = ui.available_height() - 40.0; // Reserve space for input
+            let available_height = if self.raw_input {
+                ui.available_height()
+            } else {
+                ui.available_height() - 40.0 // Reserve space for input
+            };
             
-            ScrollArea::vertical()
+            // Fit the PTY to the visible grid area
+            let (char_width, row_height) = cell_size(ui, &egui::FontId::monospace(12.0));
+            let grid_width = ui.available_width() - ui.spacing().scroll.bar_width - GUTTER_WIDTH;
+            self.resize(
+                (grid_width / char_width).floor() as u16,
+                (available_height / row_height).floor() as u16,
+            );
+            
+            // The wheel belongs to the program while it owns the screen
+            let program_scrolls = self.emulator.alternate_screen()
+                || self.emulator.mouse_tracking() != MouseTracking::Off;
+            let grid_output = ScrollArea::vertical()
                 .auto_shrink([false; 2])
                 .stick_to_bottom(true)
+                .scroll_source(egui::containers::scroll_area::ScrollSource {
+                    scroll_bar: true,
+                    drag: !program_scrolls,
+                    mouse_wheel: !program_scrolls,
+                })
                 .max_height(available_height)
-                .show(ui, |ui| {
-                    ui.style_mut().override_font_id = Some(egui::FontId::monospace(12.0));
-                    
-                    for line in &self.scrollback {
-                        // Strip ANSI escape sequences for display
-                        let clean_line = strip_ansi_escapes(line);
-                        ui.label(RichText::new(clean_line).color(Color32::LIGHT_GRAY));
+                .show(ui, |ui| self.render_grid(ui));
+            self.paint_bell_flash(ui, grid_output.inner_rect);
+            let grid_response = grid_output.inner;
+            
+            if self.exit_status.is_some() {
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new("The shell has exited.").weak());
+                    if ui.button("⟳ Restart shell").clicked() {
+                        self.restart();
                     }
                 });
+                return;
+            }
+            
+            if self.raw_input {
+                self.handle_raw_input(ui, &grid_response);
+                return;
+            }
+            
+            // Selecting output should not leave the input line unfocused
+            if grid_response.clicked() || grid_response.drag_stopped() {
+                self.focus_input = true;
+            }
             
             // Input line
             ui.horizontal(|ui| {
                 ui.label(RichText::new("❯").color(Color32::GREEN).monospace());
                 
+                let input_id = ui.id().with("input");
+                self.apply_pending_insert(ui.ctx(), input_id);
                 let response = ui.add(
                     TextEdit::singleline(&mut self.input_buffer)
+                        .id(input_id)
                         .font(egui::FontId::monospace(12.0))
                         .desired_width(f32::INFINITY)
                         .hint_text("Type command...")
                 );
                 
-                if self.focus_input {
+                if self.focus_input && !self.reverse_search.is_open() {
                     response.request_focus();
                     self.focus_input = false;
                 }
@@ -294,47 +1481,73 @@ impl TerminalPanel {
                     }
                     if ui.input(|i| i.key_pressed(Key::C) && i.modifiers.contains(Modifiers::CTRL)) {
                         // Ctrl+C - send interrupt
-                        if let Some(writer) = &mut self.pty_writer {
-                            let _ = writer.write_all(&[0x03]); // ETX (Ctrl+C)
-                            let _ = writer.flush();
-                        }
+                        self.write_pty(&[0x03]); // ETX (Ctrl+C)
                     }
+                    if ui.input_mut(|i| i.consume_key(Modifiers::CTRL, Key::R)) {
+                        self.reverse_search.open(&self.input_buffer);
+                    }
+                }
+                
+                if self.reverse_search.is_open() {
+                    self.render_reverse_search(ui, response.rect);
                 }
             });
-        });
+        }).response;
+        self.handle_drop(ui, &response);
     }
 }
 
-fn strip_ansi_escapes(s: &str) -> String {
-    // Simple ANSI escape sequence stripper
-    let mut result = String::with_capacity(s.len());
-    let mut chars = s.chars().peekable();
-    
-    while let Some(c) = chars.next() {
-        if c == '\x1b' {
-            // Start of escape sequence
-            if chars.peek() == Some(&'[') {
-                chars.next(); // consume '['
-                // Consume until we hit a letter
-                while let Some(&next) = chars.peek() {
-                    chars.next();
-                    if next.is_ascii_alphabetic() {
-                        break;
-                    }
-                }
-            }
-        } else {
-            result.push(c);
-        }
+/// Whether the shell is the terminal's foreground process group. Where this
+/// cannot be determined the shell is assumed to be in the foreground.
+#[cfg(unix)]
+fn foreground_is_shell(pair: Option<&PtyPair>, shell_pid: Option<u32>) -> bool {
+    match (pair.and_then(|p| p.master.process_group_leader()), shell_pid) {
+        (Some(leader), Some(pid)) => leader as u32 == pid,
+        _ => true,
     }
-    
-    result
+}
+
+#[cfg(not(unix))]
+fn foreground_is_shell(_pair: Option<&PtyPair>, _shell_pid: Option<u32>) -> bool {
+    true
+}
+
+#[cfg(target_os = "linux")]
+fn process_cwd(pid: u32) -> Option<PathBuf> {
+    std::fs::read_link(format!("/proc/{}/cwd", pid)).ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn process_cwd(_pid: u32) -> Option<PathBuf> {
+    None
+}
+
+fn describe_exit(status: &ExitStatus) -> String {
+    match status.signal() {
+        Some(signal) => format!("Shell killed by {}", signal),
+        None => format!("Shell exited with code {}", status.exit_code()),
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 60 {
+        format!("{}m {}s", secs / 60, secs % 60)
+    } else if secs >= 1 {
+        format!("{:.1}s", duration.as_secs_f32())
+    } else {
+        format!("{} ms", duration.as_millis())
+    }
+}
+
+/// Width and height of one monospace cell.
+fn cell_size(ui: &Ui, font_id: &egui::FontId) -> (f32, f32) {
+    ui.fonts_mut(|f| (f.glyph_width(font_id, 'M'), f.row_height(font_id)))
 }
 
 impl Drop for TerminalPanel {
     fn drop(&mut self) {
-        // Clean up PTY
-        self.pty_writer = None;
-        self.pty_pair = None;
+        // Clean up PTY and reap the shell
+        self.stop_shell();
     }
 }
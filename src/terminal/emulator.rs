@@ -0,0 +1,1044 @@
+use termwiz::cell::{unicode_column_width, Cell, CellAttributes};
+use termwiz::escape::csi::{
+    Cursor, DecPrivateMode, DecPrivateModeCode, Device, Edit, EraseInDisplay, EraseInLine, Mode,
+    Sgr,
+};
+use termwiz::escape::parser::Parser;
+use termwiz::escape::osc::FinalTermSemanticPrompt;
+use termwiz::escape::{Action, ControlCode, Esc, EscCode, OperatingSystemCommand, CSI};
+use super::scrollback::{Scrollback, ScrollbackLimit};
+use super::style;
+use std::borrow::Cow;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use termwiz::surface::{Change, CursorVisibility, Line, Position, Surface};
+
+const TAB_WIDTH: usize = 8;
+const MAX_COMMANDS: usize = 1000;
+
+/// Cursor state saved by DECSC / CSI s and restored by DECRC / CSI u.
+#[derive(Clone)]
+struct SavedCursor {
+    x: usize,
+    y: usize,
+    pen: CellAttributes,
+    line_drawing: bool,
+}
+
+/// Where the shell is in its prompt/command cycle, according to the OSC 133
+/// marks it emits.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ShellState {
+    /// The prompt is being drawn (after `A`)
+    Prompt,
+    /// The user is editing the command line (after `B`)
+    Input,
+    /// A command is running (after `C`)
+    Running,
+}
+
+/// Which mouse events the application asked to receive (DECSET 1000, 1002
+/// and 1003).
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MouseTracking {
+    Off,
+    /// Button presses and releases
+    Click,
+    /// Presses, releases and motion while a button is held
+    Drag,
+    /// All of the above plus motion with no button held
+    Motion,
+}
+
+/// One prompt/command/output cycle delimited by OSC 133 marks. Line numbers
+/// are absolute: they keep counting as lines are dropped from the scrollback.
+#[derive(Clone, Debug)]
+pub struct CommandRecord {
+    pub prompt_line: usize,
+    input_start: Option<(usize, usize)>,
+    pub output_start: Option<usize>,
+    pub output_end: Option<usize>,
+    started: Option<Instant>,
+    pub duration: Option<Duration>,
+    pub exit_status: Option<i32>,
+    /// The command line as typed, read when the output starts.
+    pub command_line: Option<String>,
+}
+
+impl CommandRecord {
+    pub fn is_running(&self) -> bool {
+        self.output_start.is_some() && self.output_end.is_none()
+    }
+}
+
+/// A VT100/xterm screen model.
+///
+/// PTY output is run through termwiz's escape parser and the resulting
+/// actions are applied to a `Surface`. Lines that scroll off the top of
+/// the screen are kept in a scrollback buffer.
+pub struct Emulator {
+    parser: Parser,
+    surface: Surface,
+    /// The main screen, set aside while a full-screen program uses the
+    /// alternate screen.
+    primary: Option<Surface>,
+    scrollback: Scrollback,
+    pen: CellAttributes,
+    scroll_top: usize,
+    scroll_bottom: usize,
+    saved_cursor: Option<SavedCursor>,
+    auto_wrap: bool,
+    application_cursor_keys: bool,
+    bracketed_paste: bool,
+    mouse_tracking: MouseTracking,
+    sgr_mouse: bool,
+    line_drawing: bool,
+    title: String,
+    working_dir: Option<PathBuf>,
+    shell_state: Option<ShellState>,
+    commands: Vec<CommandRecord>,
+    /// Commands that finished since the last `take_finished_commands`.
+    finished: Vec<CommandRecord>,
+    bell: bool,
+    responses: Vec<u8>,
+}
+
+impl Emulator {
+    pub fn new(cols: usize, rows: usize) -> Self {
+        Self {
+            parser: Parser::new(),
+            surface: Surface::new(cols, rows),
+            primary: None,
+            scrollback: Scrollback::new(ScrollbackLimit::default()),
+            pen: CellAttributes::default(),
+            scroll_top: 0,
+            scroll_bottom: rows.saturating_sub(1),
+            saved_cursor: None,
+            auto_wrap: true,
+            application_cursor_keys: false,
+            bracketed_paste: false,
+            mouse_tracking: MouseTracking::Off,
+            sgr_mouse: false,
+            line_drawing: false,
+            title: String::new(),
+            working_dir: None,
+            shell_state: None,
+            commands: Vec::new(),
+            finished: Vec::new(),
+            bell: false,
+            responses: Vec::new(),
+        }
+    }
+
+    /// Feed raw PTY output into the parser and apply it to the screen.
+    pub fn advance(&mut self, bytes: &[u8]) {
+        for action in self.parser.parse_as_vec(bytes) {
+            self.perform(action);
+        }
+        // The surface records every change for diffing; we only ever read
+        // its current state, so drop the history to keep memory bounded.
+        let seq = self.surface.current_seqno();
+        self.surface.flush_changes_older_than(seq);
+    }
+
+    /// Bytes the emulator wants written back to the PTY, such as replies to
+    /// cursor position and device attribute queries.
+    pub fn take_responses(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.responses)
+    }
+
+    /// Print a message generated by the application itself (not the shell).
+    pub fn print_notice(&mut self, message: &str) {
+        if self.cursor().0 > 0 {
+            self.carriage_return();
+            self.linefeed();
+        }
+        for c in message.chars() {
+            self.print(c);
+        }
+        self.carriage_return();
+        self.linefeed();
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        self.surface.dimensions()
+    }
+
+    /// Resize the screen. When it gets shorter, lines above the cursor are
+    /// pushed into the scrollback so the cursor line stays visible.
+    pub fn resize(&mut self, cols: usize, rows: usize) {
+        let (x, y) = self.cursor();
+        if let Some(primary) = &mut self.primary {
+            primary.resize(cols, rows);
+        } else if y >= rows {
+            let excess = y + 1 - rows;
+            self.scroll_top = 0;
+            self.scroll_bottom = self.rows() - 1;
+            self.scroll_up(excess);
+            self.goto(x, y - excess);
+        }
+        self.surface.resize(cols, rows);
+        self.scroll_top = 0;
+        self.scroll_bottom = rows - 1;
+        if let Some(saved) = &mut self.saved_cursor {
+            saved.x = saved.x.min(cols - 1);
+            saved.y = saved.y.min(rows - 1);
+        }
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// The most recent directory reported by the shell through OSC 7, if it
+    /// has changed since the last call.
+    pub fn take_working_dir(&mut self) -> Option<PathBuf> {
+        self.working_dir.take()
+    }
+
+    pub fn cursor(&self) -> (usize, usize) {
+        self.surface.cursor_position()
+    }
+
+    pub fn cursor_visible(&self) -> bool {
+        self.surface.cursor_visibility() == CursorVisibility::Visible
+    }
+
+    /// Whether DECCKM is set, in which case cursor keys are sent as SS3
+    /// sequences rather than CSI.
+    pub fn application_cursor_keys(&self) -> bool {
+        self.application_cursor_keys
+    }
+
+    /// Whether a full-screen program has switched to the alternate screen.
+    pub fn alternate_screen(&self) -> bool {
+        self.primary.is_some()
+    }
+
+    pub fn mouse_tracking(&self) -> MouseTracking {
+        self.mouse_tracking
+    }
+
+    /// Whether mouse reports use the SGR encoding (mode 1006).
+    pub fn sgr_mouse(&self) -> bool {
+        self.sgr_mouse
+    }
+
+    /// Absolute line number of the top screen row.
+    pub fn screen_top_line(&self) -> usize {
+        self.absolute_line(0)
+    }
+
+    /// Whether the application asked for pastes to be bracketed (mode 2004).
+    pub fn bracketed_paste(&self) -> bool {
+        self.bracketed_paste
+    }
+
+    pub fn set_scrollback_limit(&mut self, limit: ScrollbackLimit) {
+        self.scrollback.set_limit(limit);
+        self.forget_dropped_commands();
+    }
+
+    /// A number that changes whenever the buffer contents change.
+    pub fn revision(&self) -> usize {
+        self.surface.current_seqno() + self.scrollback.first_line()
+    }
+
+    /// Absolute number of the first scrollback line.
+    pub fn first_line_number(&self) -> usize {
+        self.scrollback.first_line()
+    }
+
+    /// Number of lines in the scrollback and on the screen together.
+    pub fn line_count(&self) -> usize {
+        self.scrollback.len() + self.rows()
+    }
+
+    /// Absolute line number of screen row `y`.
+    fn absolute_line(&self, y: usize) -> usize {
+        self.scrollback.first_line() + self.scrollback.len() + y
+    }
+
+    /// Absolute line number of the cursor row.
+    pub fn cursor_line(&self) -> usize {
+        self.absolute_line(self.cursor().1)
+    }
+
+    /// The absolute line `number`, from the scrollback or the screen.
+    pub fn line(&self, number: usize) -> Option<Cow<'_, Line>> {
+        let index = number.checked_sub(self.scrollback.first_line())?;
+        match index.checked_sub(self.scrollback.len()) {
+            None => self.scrollback.get(index),
+            Some(row) => self.surface.screen_lines().into_iter().nth(row),
+        }
+    }
+
+    /// Every available line, oldest first, ending with the screen.
+    pub fn lines(&self) -> impl Iterator<Item = Cow<'_, Line>> {
+        self.scrollback.iter().chain(self.surface.screen_lines())
+    }
+
+    /// Plain text of the absolute lines `start..end`, one line per row with
+    /// trailing blanks removed. Rows soft-wrapped by the terminal are joined.
+    pub fn text_range(&self, start: usize, end: usize) -> String {
+        let start = start.max(self.scrollback.first_line());
+        let mut text = String::new();
+        for line in (start..end).map_while(|number| self.line(number)) {
+            let row = line.as_str();
+            if line.last_cell_was_wrapped() {
+                text.push_str(&row);
+            } else {
+                text.push_str(row.trim_end());
+                text.push('\n');
+            }
+        }
+        text
+    }
+
+    /// The shell's state from OSC 133 marks, or `None` if the shell has not
+    /// sent any.
+    pub fn shell_state(&self) -> Option<ShellState> {
+        self.shell_state
+    }
+
+    /// Forget the shell integration state after the shell has exited. A
+    /// command still running is closed with an unknown status.
+    pub fn shell_exited(&mut self) {
+        self.finish_command(0, false);
+        self.shell_state = None;
+    }
+
+    pub fn commands(&self) -> &[CommandRecord] {
+        &self.commands
+    }
+
+    /// Commands that finished since the last call, oldest first.
+    pub fn take_finished_commands(&mut self) -> Vec<CommandRecord> {
+        std::mem::take(&mut self.finished)
+    }
+
+    /// Whether BEL was received since the last call.
+    pub fn take_bell(&mut self) -> bool {
+        std::mem::take(&mut self.bell)
+    }
+
+    /// Whether the shell is waiting at a prompt with nothing typed, if shell
+    /// integration tells us.
+    pub fn at_empty_prompt(&self) -> Option<bool> {
+        let state = self.shell_state?;
+        if state != ShellState::Input {
+            return Some(false);
+        }
+        let (x, y) = self.cursor();
+        let input_start = self.commands.last().and_then(|c| c.input_start);
+        Some(input_start == Some((self.absolute_line(y), x)))
+    }
+
+    /// Drop the scrollback and move the cursor line to the top of the
+    /// screen, like Ctrl+L in most shells.
+    pub fn clear(&mut self) {
+        let (_, rows) = self.size();
+        let (x, y) = self.cursor();
+        self.scrollback.clear();
+        self.scrollback.skip(y);
+        if y > 0 {
+            self.surface.add_change(Change::ScrollRegionUp {
+                first_row: 0,
+                region_size: rows,
+                scroll_count: y,
+            });
+        }
+        self.goto(x, 0);
+    }
+
+    fn perform(&mut self, action: Action) {
+        match action {
+            Action::Print(c) => self.print(c),
+            Action::PrintString(s) => {
+                for c in s.chars() {
+                    self.print(c);
+                }
+            }
+            Action::Control(code) => self.control(code),
+            Action::CSI(csi) => self.csi(csi),
+            Action::Esc(esc) => self.esc(esc),
+            Action::OperatingSystemCommand(osc) => self.osc(*osc),
+            _ => {}
+        }
+    }
+
+    fn cols(&self) -> usize {
+        self.surface.dimensions().0
+    }
+
+    fn rows(&self) -> usize {
+        self.surface.dimensions().1
+    }
+
+    fn goto(&mut self, x: usize, y: usize) {
+        self.surface.add_change(Change::CursorPosition {
+            x: Position::Absolute(x),
+            y: Position::Absolute(y),
+        });
+    }
+
+    /// The column the cursor is logically in. After printing into the last
+    /// column the surface reports `x == cols` until the next character wraps.
+    fn column(&self) -> usize {
+        self.cursor().0.min(self.cols().saturating_sub(1))
+    }
+
+    fn print(&mut self, c: char) {
+        let c = if self.line_drawing { dec_line_drawing(c) } else { c };
+        let mut buf = [0u8; 4];
+        let text: &str = c.encode_utf8(&mut buf);
+        let width = unicode_column_width(text, None);
+        let cols = self.cols();
+        let (x, y) = self.cursor();
+
+        if width == 0 {
+            // Combining mark: attach it to the previously printed cell.
+            if x > 0 {
+                let mut cells = self.surface.screen_cells();
+                let prev = &mut cells[y][x.min(cols) - 1];
+                let combined = format!("{}{}", prev.str(), c);
+                *prev = Cell::new_grapheme(&combined, prev.attrs().clone(), None);
+            }
+            return;
+        }
+
+        if x + width > cols {
+            if self.auto_wrap {
+                self.mark_wrapped(y);
+                self.carriage_return();
+                self.linefeed();
+            } else {
+                self.goto(cols.saturating_sub(width), y);
+            }
+        }
+
+        self.surface.add_change(Change::Text(text.to_string()));
+    }
+
+    fn mark_wrapped(&mut self, y: usize) {
+        let mut cells = self.surface.screen_cells();
+        if let Some(last) = cells[y].last_mut() {
+            last.attrs_mut().set_wrapped(true);
+        }
+    }
+
+    fn carriage_return(&mut self) {
+        let (_, y) = self.cursor();
+        self.goto(0, y);
+    }
+
+    fn linefeed(&mut self) {
+        let (x, y) = self.cursor();
+        if y == self.scroll_bottom {
+            self.scroll_up(1);
+            self.goto(x, y);
+        } else if y + 1 < self.rows() {
+            self.goto(x, y + 1);
+        }
+    }
+
+    fn reverse_index(&mut self) {
+        let (x, y) = self.cursor();
+        if y == self.scroll_top {
+            self.scroll_down(1);
+            self.goto(x, y);
+        } else if y > 0 {
+            self.goto(x, y - 1);
+        }
+    }
+
+    fn scroll_up(&mut self, count: usize) {
+        let region_size = self.scroll_bottom + 1 - self.scroll_top;
+        let count = count.min(region_size);
+        // The alternate screen has no scrollback
+        if self.scroll_top == 0 && self.primary.is_none() {
+            let lines = self.surface.screen_lines();
+            for line in lines.into_iter().take(count) {
+                self.scrollback.push(line.into_owned());
+            }
+            self.forget_dropped_commands();
+        }
+        self.surface.add_change(Change::ScrollRegionUp {
+            first_row: self.scroll_top,
+            region_size,
+            scroll_count: count,
+        });
+    }
+
+    fn scroll_down(&mut self, count: usize) {
+        let region_size = self.scroll_bottom + 1 - self.scroll_top;
+        self.surface.add_change(Change::ScrollRegionDown {
+            first_row: self.scroll_top,
+            region_size,
+            scroll_count: count.min(region_size),
+        });
+    }
+
+    /// A blank cell carrying the current background color, as used by the
+    /// erase operations.
+    fn blank(&self) -> Cell {
+        let mut attrs = CellAttributes::default();
+        attrs.set_background(self.pen.background());
+        Cell::new(' ', attrs)
+    }
+
+    fn erase(&mut self, y: usize, cols: std::ops::Range<usize>) {
+        let blank = self.blank();
+        let mut cells = self.surface.screen_cells();
+        let row = &mut cells[y];
+        let end = cols.end.min(row.len());
+        for cell in &mut row[cols.start.min(end)..end] {
+            *cell = blank.clone();
+        }
+    }
+
+    fn control(&mut self, code: ControlCode) {
+        match code {
+            ControlCode::LineFeed | ControlCode::VerticalTab | ControlCode::FormFeed => {
+                self.linefeed()
+            }
+            ControlCode::CarriageReturn => self.carriage_return(),
+            ControlCode::Backspace => {
+                let (_, y) = self.cursor();
+                let x = self.column();
+                self.goto(x.saturating_sub(1), y);
+            }
+            ControlCode::HorizontalTab => {
+                let (_, y) = self.cursor();
+                let next = (self.column() / TAB_WIDTH + 1) * TAB_WIDTH;
+                self.goto(next.min(self.cols().saturating_sub(1)), y);
+            }
+            ControlCode::ShiftIn => self.line_drawing = false,
+            ControlCode::IND => self.linefeed(),
+            ControlCode::NEL => {
+                self.carriage_return();
+                self.linefeed();
+            }
+            ControlCode::RI => self.reverse_index(),
+            ControlCode::Bell => self.bell = true,
+            _ => {}
+        }
+    }
+
+    fn esc(&mut self, esc: Esc) {
+        let Esc::Code(code) = esc else {
+            return;
+        };
+        match code {
+            EscCode::Index => self.linefeed(),
+            EscCode::NextLine => {
+                self.carriage_return();
+                self.linefeed();
+            }
+            EscCode::ReverseIndex => self.reverse_index(),
+            EscCode::DecSaveCursorPosition => self.save_cursor(),
+            EscCode::DecRestoreCursorPosition => self.restore_cursor(),
+            EscCode::DecLineDrawingG0 => self.line_drawing = true,
+            EscCode::AsciiCharacterSetG0 | EscCode::UkCharacterSetG0 => {
+                self.line_drawing = false
+            }
+            EscCode::FullReset => self.reset(),
+            _ => {}
+        }
+    }
+
+    fn osc(&mut self, osc: OperatingSystemCommand) {
+        match osc {
+            OperatingSystemCommand::SetIconNameAndWindowTitle(title)
+            | OperatingSystemCommand::SetWindowTitle(title)
+            | OperatingSystemCommand::SetWindowTitleSun(title) => self.title = title,
+            OperatingSystemCommand::FinalTermSemanticPrompt(mark) => self.semantic_prompt(mark),
+            OperatingSystemCommand::CurrentWorkingDirectory(url) => {
+                if let Some(path) = parse_file_url(&url) {
+                    self.working_dir = Some(path);
+                }
+            }
+            OperatingSystemCommand::SetHyperlink(link) => {
+                self.pen.set_hyperlink(link.map(Arc::new));
+                self.surface.add_change(Change::AllAttributes(self.pen.clone()));
+            }
+            _ => {}
+        }
+    }
+
+    fn fresh_line(&mut self) {
+        if self.cursor().0 > 0 {
+            self.carriage_return();
+            self.linefeed();
+        }
+    }
+
+    /// Track OSC 133 prompt (`A`), input (`B`), output (`C`) and command
+    /// status (`D`) marks.
+    fn semantic_prompt(&mut self, mark: FinalTermSemanticPrompt) {
+        match mark {
+            FinalTermSemanticPrompt::FreshLine => self.fresh_line(),
+            FinalTermSemanticPrompt::FreshLineAndStartPrompt { .. }
+            | FinalTermSemanticPrompt::MarkEndOfCommandWithFreshLine { .. } => {
+                self.finish_command(0, false);
+                self.fresh_line();
+                let prompt_line = self.absolute_line(self.cursor().1);
+                self.commands.push(CommandRecord {
+                    prompt_line,
+                    input_start: None,
+                    output_start: None,
+                    output_end: None,
+                    started: None,
+                    duration: None,
+                    exit_status: None,
+                    command_line: None,
+                });
+                if self.commands.len() > MAX_COMMANDS {
+                    self.commands.remove(0);
+                }
+                self.shell_state = Some(ShellState::Prompt);
+            }
+            FinalTermSemanticPrompt::MarkEndOfPromptAndStartOfInputUntilNextMarker
+            | FinalTermSemanticPrompt::MarkEndOfPromptAndStartOfInputUntilEndOfLine => {
+                let (x, y) = self.cursor();
+                let position = (self.absolute_line(y), x);
+                if let Some(command) = self.commands.last_mut() {
+                    command.input_start = Some(position);
+                }
+                self.shell_state = Some(ShellState::Input);
+            }
+            FinalTermSemanticPrompt::MarkEndOfInputAndStartOfOutput { .. } => {
+                let line = self.absolute_line(self.cursor().1);
+                let command_line = self.command_line(line);
+                if let Some(command) = self.commands.last_mut() {
+                    command.output_start = Some(line);
+                    command.started = Some(Instant::now());
+                    command.command_line = command_line;
+                }
+                self.shell_state = Some(ShellState::Running);
+            }
+            FinalTermSemanticPrompt::CommandStatus { status, .. } => {
+                self.finish_command(status, true);
+                self.shell_state = Some(ShellState::Prompt);
+            }
+            FinalTermSemanticPrompt::StartPrompt(_) => {}
+        }
+    }
+
+    /// Text typed after the last prompt, up to the line where output starts.
+    fn command_line(&self, output_start: usize) -> Option<String> {
+        let (line, column) = self.commands.last()?.input_start?;
+        let text = self.text_range(line, output_start.max(line + 1));
+        let text: String = text.chars().skip(column).collect();
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        (!text.is_empty()).then_some(text)
+    }
+
+    fn forget_dropped_commands(&mut self) {
+        let first = self.scrollback.first_line();
+        if self.commands.first().is_some_and(|c| c.prompt_line < first) {
+            self.commands.retain(|c| c.prompt_line >= first);
+        }
+    }
+
+    /// Close the running command, if any. A new prompt without a `D` mark
+    /// still ends the command, but with an unknown status.
+    fn finish_command(&mut self, status: i32, status_known: bool) {
+        let (x, y) = self.cursor();
+        let end = self.absolute_line(y) + usize::from(x > 0);
+        if let Some(command) = self.commands.last_mut() {
+            if command.is_running() {
+                command.output_end = Some(end);
+                command.duration = command.started.map(|started| started.elapsed());
+                command.exit_status = status_known.then_some(status);
+                self.finished.push(command.clone());
+            }
+        }
+    }
+
+    fn csi(&mut self, csi: CSI) {
+        match csi {
+            CSI::Cursor(cursor) => self.csi_cursor(cursor),
+            CSI::Edit(edit) => self.csi_edit(edit),
+            CSI::Mode(mode) => self.csi_mode(mode),
+            CSI::Device(device) => self.csi_device(*device),
+            CSI::Sgr(sgr) => self.sgr(sgr),
+            _ => {}
+        }
+    }
+
+    fn sgr(&mut self, sgr: Sgr) {
+        style::apply_sgr(&mut self.pen, sgr);
+        self.surface.add_change(Change::AllAttributes(self.pen.clone()));
+    }
+
+    fn csi_cursor(&mut self, cursor: Cursor) {
+        let (_, y) = self.cursor();
+        let x = self.column();
+        let rows = self.rows();
+        // Vertical movement stops at the scroll margins when starting inside them.
+        let top = if y >= self.scroll_top { self.scroll_top } else { 0 };
+        let bottom = if y <= self.scroll_bottom { self.scroll_bottom } else { rows - 1 };
+
+        match cursor {
+            Cursor::Up(n) | Cursor::LinePositionBackward(n) => {
+                self.goto(x, y.saturating_sub(n as usize).max(top))
+            }
+            Cursor::Down(n) | Cursor::LinePositionForward(n) => {
+                self.goto(x, (y + n as usize).min(bottom))
+            }
+            Cursor::Left(n) | Cursor::CharacterPositionBackward(n) => {
+                self.goto(x.saturating_sub(n as usize), y)
+            }
+            Cursor::Right(n) | Cursor::CharacterPositionForward(n) => {
+                self.goto(x + n as usize, y)
+            }
+            Cursor::NextLine(n) => self.goto(0, (y + n as usize).min(bottom)),
+            Cursor::PrecedingLine(n) => self.goto(0, y.saturating_sub(n as usize).max(top)),
+            Cursor::CharacterAbsolute(col) | Cursor::CharacterPositionAbsolute(col) => {
+                self.goto(col.as_zero_based() as usize, y)
+            }
+            Cursor::LinePositionAbsolute(line) => {
+                self.goto(x, (line as usize).saturating_sub(1))
+            }
+            Cursor::Position { line, col } | Cursor::CharacterAndLinePosition { line, col } => {
+                self.goto(col.as_zero_based() as usize, line.as_zero_based() as usize)
+            }
+            Cursor::ForwardTabulation(n) => {
+                let next = (x / TAB_WIDTH + n as usize) * TAB_WIDTH;
+                self.goto(next.min(self.cols() - 1), y)
+            }
+            Cursor::BackwardTabulation(n) => {
+                let prev = (x.div_ceil(TAB_WIDTH)).saturating_sub(n as usize) * TAB_WIDTH;
+                self.goto(prev, y)
+            }
+            Cursor::SaveCursor => self.save_cursor(),
+            Cursor::RestoreCursor => self.restore_cursor(),
+            Cursor::SetTopAndBottomMargins { top, bottom } => {
+                let top = top.as_zero_based() as usize;
+                let bottom = (bottom.as_zero_based() as usize).min(rows - 1);
+                if top < bottom {
+                    self.scroll_top = top;
+                    self.scroll_bottom = bottom;
+                    self.goto(0, 0);
+                }
+            }
+            Cursor::RequestActivePositionReport => {
+                let report = format!("\x1b[{};{}R", y + 1, x + 1);
+                self.responses.extend_from_slice(report.as_bytes());
+            }
+            _ => {}
+        }
+    }
+
+    fn csi_edit(&mut self, edit: Edit) {
+        let (_, y) = self.cursor();
+        let x = self.column();
+        let cols = self.cols();
+        let rows = self.rows();
+
+        match edit {
+            Edit::EraseInLine(EraseInLine::EraseToEndOfLine) => self.erase(y, x..cols),
+            Edit::EraseInLine(EraseInLine::EraseToStartOfLine) => self.erase(y, 0..x + 1),
+            Edit::EraseInLine(EraseInLine::EraseLine) => self.erase(y, 0..cols),
+            Edit::EraseInDisplay(EraseInDisplay::EraseToEndOfDisplay) => {
+                self.erase(y, x..cols);
+                for row in y + 1..rows {
+                    self.erase(row, 0..cols);
+                }
+            }
+            Edit::EraseInDisplay(EraseInDisplay::EraseToStartOfDisplay) => {
+                for row in 0..y {
+                    self.erase(row, 0..cols);
+                }
+                self.erase(y, 0..x + 1);
+            }
+            Edit::EraseInDisplay(EraseInDisplay::EraseDisplay) => {
+                for row in 0..rows {
+                    self.erase(row, 0..cols);
+                }
+            }
+            Edit::EraseInDisplay(EraseInDisplay::EraseScrollback) => {
+                self.scrollback.clear();
+            }
+            Edit::EraseCharacter(n) => self.erase(y, x..x + n as usize),
+            Edit::DeleteCharacter(n) => {
+                let blank = self.blank();
+                let mut cells = self.surface.screen_cells();
+                let row = &mut cells[y];
+                let n = (n as usize).min(cols - x);
+                row[x..].rotate_left(n);
+                for cell in &mut row[cols - n..] {
+                    *cell = blank.clone();
+                }
+            }
+            Edit::InsertCharacter(n) => {
+                let blank = self.blank();
+                let mut cells = self.surface.screen_cells();
+                let row = &mut cells[y];
+                let n = (n as usize).min(cols - x);
+                row[x..].rotate_right(n);
+                for cell in &mut row[x..x + n] {
+                    *cell = blank.clone();
+                }
+            }
+            Edit::InsertLine(n) => {
+                if (self.scroll_top..=self.scroll_bottom).contains(&y) {
+                    let region_size = self.scroll_bottom + 1 - y;
+                    self.surface.add_change(Change::ScrollRegionDown {
+                        first_row: y,
+                        region_size,
+                        scroll_count: (n as usize).min(region_size),
+                    });
+                    self.goto(0, y);
+                }
+            }
+            Edit::DeleteLine(n) => {
+                if (self.scroll_top..=self.scroll_bottom).contains(&y) {
+                    let region_size = self.scroll_bottom + 1 - y;
+                    self.surface.add_change(Change::ScrollRegionUp {
+                        first_row: y,
+                        region_size,
+                        scroll_count: (n as usize).min(region_size),
+                    });
+                    self.goto(0, y);
+                }
+            }
+            Edit::ScrollUp(n) => {
+                let (x, y) = self.cursor();
+                self.scroll_up(n as usize);
+                self.goto(x, y);
+            }
+            Edit::ScrollDown(n) => {
+                let (x, y) = self.cursor();
+                self.scroll_down(n as usize);
+                self.goto(x, y);
+            }
+            Edit::Repeat(n) => {
+                if x > 0 {
+                    let cells = self.surface.screen_lines();
+                    let last = cells[y].get_cell(x - 1).map(|c| c.str().to_string());
+                    if let Some(last) = last {
+                        // The count comes from the program; never more than fills the row
+                        let n = (n as usize).min(self.cols().saturating_sub(x));
+                        for _ in 0..n {
+                            for c in last.chars() {
+                                self.print(c);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn csi_mode(&mut self, mode: Mode) {
+        let (code, enable) = match mode {
+            Mode::SetDecPrivateMode(DecPrivateMode::Code(code)) => (code, true),
+            Mode::ResetDecPrivateMode(DecPrivateMode::Code(code)) => (code, false),
+            _ => return,
+        };
+        match code {
+            DecPrivateModeCode::AutoWrap => self.auto_wrap = enable,
+            DecPrivateModeCode::ApplicationCursorKeys => self.application_cursor_keys = enable,
+            DecPrivateModeCode::BracketedPaste => self.bracketed_paste = enable,
+            DecPrivateModeCode::MouseTracking => self.set_mouse_tracking(MouseTracking::Click, enable),
+            DecPrivateModeCode::ButtonEventMouse => self.set_mouse_tracking(MouseTracking::Drag, enable),
+            DecPrivateModeCode::AnyEventMouse => self.set_mouse_tracking(MouseTracking::Motion, enable),
+            DecPrivateModeCode::SGRMouse => self.sgr_mouse = enable,
+            DecPrivateModeCode::ClearAndEnableAlternateScreen => {
+                if enable {
+                    self.save_cursor();
+                    self.enter_alternate_screen();
+                } else {
+                    self.leave_alternate_screen();
+                    self.restore_cursor();
+                }
+            }
+            DecPrivateModeCode::EnableAlternateScreen
+            | DecPrivateModeCode::OptEnableAlternateScreen => {
+                if enable {
+                    self.enter_alternate_screen();
+                } else {
+                    self.leave_alternate_screen();
+                }
+            }
+            DecPrivateModeCode::ShowCursor => {
+                self.surface.add_change(Change::CursorVisibility(if enable {
+                    CursorVisibility::Visible
+                } else {
+                    CursorVisibility::Hidden
+                }));
+            }
+            _ => {}
+        }
+    }
+
+    fn set_mouse_tracking(&mut self, mode: MouseTracking, enable: bool) {
+        if enable {
+            self.mouse_tracking = mode;
+        } else if self.mouse_tracking == mode {
+            self.mouse_tracking = MouseTracking::Off;
+        }
+    }
+
+    /// Switch to a blank alternate screen, keeping the cursor position.
+    fn enter_alternate_screen(&mut self) {
+        if self.primary.is_some() {
+            return;
+        }
+        let (cols, rows) = self.size();
+        let (x, y) = self.cursor();
+        let visibility = self.surface.cursor_visibility();
+        let primary = std::mem::replace(&mut self.surface, Surface::new(cols, rows));
+        self.primary = Some(primary);
+        self.surface.add_change(Change::CursorVisibility(visibility));
+        self.surface.add_change(Change::AllAttributes(self.pen.clone()));
+        self.goto(x, y);
+    }
+
+    fn leave_alternate_screen(&mut self) {
+        let Some(primary) = self.primary.take() else {
+            return;
+        };
+        let visibility = self.surface.cursor_visibility();
+        self.surface = primary;
+        self.surface.add_change(Change::CursorVisibility(visibility));
+        self.surface.add_change(Change::AllAttributes(self.pen.clone()));
+        self.scroll_top = 0;
+        self.scroll_bottom = self.rows() - 1;
+    }
+
+    fn csi_device(&mut self, device: Device) {
+        match device {
+            // VT220 with ANSI color
+            Device::RequestPrimaryDeviceAttributes => {
+                self.responses.extend_from_slice(b"\x1b[?62;22c")
+            }
+            Device::RequestSecondaryDeviceAttributes => {
+                self.responses.extend_from_slice(b"\x1b[>1;10;0c")
+            }
+            Device::StatusReport => self.responses.extend_from_slice(b"\x1b[0n"),
+            Device::SoftReset => {
+                self.pen = CellAttributes::default();
+                self.surface.add_change(Change::AllAttributes(self.pen.clone()));
+                self.scroll_top = 0;
+                self.scroll_bottom = self.rows() - 1;
+                self.auto_wrap = true;
+            }
+            _ => {}
+        }
+    }
+
+    fn save_cursor(&mut self) {
+        let (x, y) = self.cursor();
+        self.saved_cursor = Some(SavedCursor {
+            x,
+            y,
+            pen: self.pen.clone(),
+            line_drawing: self.line_drawing,
+        });
+    }
+
+    fn restore_cursor(&mut self) {
+        if let Some(saved) = self.saved_cursor.clone() {
+            self.pen = saved.pen;
+            self.line_drawing = saved.line_drawing;
+            self.surface.add_change(Change::AllAttributes(self.pen.clone()));
+            self.goto(saved.x, saved.y);
+        } else {
+            self.goto(0, 0);
+        }
+    }
+
+    fn reset(&mut self) {
+        let (cols, rows) = self.size();
+        let scrollback = std::mem::replace(&mut self.scrollback, Scrollback::new(ScrollbackLimit::default()));
+        *self = Self::new(cols, rows);
+        self.scrollback = scrollback;
+    }
+}
+
+/// Parse the `file://host/path` URL sent with OSC 7. The host is ignored
+/// and the path is percent-decoded.
+pub fn parse_file_url(url: &str) -> Option<PathBuf> {
+    let rest = url.strip_prefix("file://")?;
+    let path = &rest[rest.find('/')?..];
+
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut iter = path.bytes();
+    while let Some(b) = iter.next() {
+        if b == b'%' {
+            let hex = [iter.next()?, iter.next()?];
+            let hex = std::str::from_utf8(&hex).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+        } else {
+            bytes.push(b);
+        }
+    }
+    Some(PathBuf::from(String::from_utf8_lossy(&bytes).into_owned()))
+}
+
+/// Map a character through the DEC Special Graphics set selected by `ESC ( 0`.
+fn dec_line_drawing(c: char) -> char {
+    match c {
+        '`' => '◆',
+        'a' => '▒',
+        'f' => '°',
+        'g' => '±',
+        'j' => '┘',
+        'k' => '┐',
+        'l' => '┌',
+        'm' => '└',
+        'n' => '┼',
+        'o' => '⎺',
+        'p' => '⎻',
+        'q' => '─',
+        'r' => '⎼',
+        's' => '⎽',
+        't' => '├',
+        'u' => '┤',
+        'v' => '┴',
+        'w' => '┬',
+        'x' => '│',
+        'y' => '≤',
+        'z' => '≥',
+        '{' => 'π',
+        '|' => '≠',
+        '}' => '£',
+        '~' => '·',
+        _ => c,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_file_url_ignores_the_host() {
+        assert_eq!(parse_file_url("file://laptop/home/me"), Some(PathBuf::from("/home/me")));
+        assert_eq!(parse_file_url("file:///tmp"), Some(PathBuf::from("/tmp")));
+    }
+
+    #[test]
+    fn parse_file_url_decodes_escapes() {
+        assert_eq!(
+            parse_file_url("file://host/home/me/My%20Files/caf%C3%A9"),
+            Some(PathBuf::from("/home/me/My Files/café"))
+        );
+        assert_eq!(
+            parse_file_url("file://host/tmp/100%25%20done"),
+            Some(PathBuf::from("/tmp/100% done"))
+        );
+    }
+
+    #[test]
+    fn parse_file_url_rejects_malformed_urls() {
+        assert_eq!(parse_file_url("/home/me"), None);
+        assert_eq!(parse_file_url("file://host"), None);
+        assert_eq!(parse_file_url("file://host/tmp/100%"), None);
+        assert_eq!(parse_file_url("file://host/tmp/%zz"), None);
+    }
+}
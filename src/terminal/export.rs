@@ -0,0 +1,147 @@
+use super::style;
+use egui::{RichText, TextEdit};
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+use termwiz::surface::Line;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ExportFormat {
+    /// Text only
+    Plain,
+    /// Text with the colors as escape sequences, for `less -R` or `cat`
+    Ansi,
+    /// A standalone page with the colors
+    Html,
+}
+
+impl ExportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Plain => "txt",
+            ExportFormat::Ansi => "ansi",
+            ExportFormat::Html => "html",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            ExportFormat::Plain => "Plain text",
+            ExportFormat::Ansi => "Text with ANSI colors",
+            ExportFormat::Html => "HTML",
+        }
+    }
+}
+
+/// Write `lines` out in `format`. Rows soft-wrapped by the terminal are
+/// joined back into one line.
+pub fn render<'a>(lines: impl Iterator<Item = Cow<'a, Line>>, format: ExportFormat) -> String {
+    let mut out = String::new();
+    if format == ExportFormat::Html {
+        let (background, foreground) = style::html_page_colors();
+        out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Terminal output</title>\n</head>\n");
+        out.push_str(&format!(
+            "<body style=\"background:{};color:{}\">\n<pre style=\"font-family:monospace\">",
+            background, foreground
+        ));
+    }
+    for line in lines {
+        match format {
+            ExportFormat::Plain => out.push_str(line.as_str().trim_end()),
+            ExportFormat::Ansi => out.push_str(&style::line_to_ansi(&line)),
+            ExportFormat::Html => out.push_str(&style::line_to_html(&line)),
+        }
+        if !line.last_cell_was_wrapped() {
+            out.push('\n');
+        }
+    }
+    if format == ExportFormat::Html {
+        out.push_str("</pre>\n</body>\n</html>\n");
+    }
+    out
+}
+
+/// What the export window wants the terminal to do after a frame.
+pub enum ExportAction {
+    None,
+    Export(ExportFormat, PathBuf),
+    Close,
+}
+
+/// The "Export scrollback" window.
+pub struct ExportDialog {
+    format: ExportFormat,
+    path: String,
+    /// Outcome of the last export: a message and whether it failed.
+    result: Option<(String, bool)>,
+}
+
+impl ExportDialog {
+    /// A dialog proposing a timestamped file in `dir`.
+    pub fn new(dir: &Path) -> Self {
+        let format = ExportFormat::Plain;
+        let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+        let path = dir.join(format!("terminal-{}.{}", stamp, format.extension()));
+        Self {
+            format,
+            path: path.display().to_string(),
+            result: None,
+        }
+    }
+
+    pub fn set_result(&mut self, result: Result<String, String>) {
+        self.result = Some(match result {
+            Ok(message) => (message, false),
+            Err(message) => (message, true),
+        });
+    }
+
+    pub fn render(&mut self, ctx: &egui::Context, id: egui::Id) -> ExportAction {
+        let mut action = ExportAction::None;
+        let mut open = true;
+        egui::Window::new("Export Scrollback")
+            .id(id)
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                for format in [ExportFormat::Plain, ExportFormat::Ansi, ExportFormat::Html] {
+                    if ui.radio_value(&mut self.format, format, format.label()).changed() {
+                        // Follow the format with the extension unless the
+                        // user picked their own
+                        let path = PathBuf::from(&self.path);
+                        let known = ["txt", "ansi", "html"]
+                            .iter()
+                            .any(|ext| path.extension().is_some_and(|e| e == *ext));
+                        if known {
+                            self.path = path.with_extension(format.extension()).display().to_string();
+                        }
+                    }
+                }
+                ui.horizontal(|ui| {
+                    ui.label("Save to:");
+                    ui.add(TextEdit::singleline(&mut self.path).desired_width(360.0));
+                });
+                if let Some((message, failed)) = &self.result {
+                    let color = if *failed {
+                        egui::Color32::LIGHT_RED
+                    } else {
+                        egui::Color32::GREEN
+                    };
+                    ui.label(RichText::new(message).color(color));
+                }
+                ui.horizontal(|ui| {
+                    let has_path = !self.path.trim().is_empty();
+                    if ui.add_enabled(has_path, egui::Button::new("Export")).clicked() {
+                        action = ExportAction::Export(self.format, PathBuf::from(self.path.trim()));
+                    }
+                    if ui.button("Close").clicked() {
+                        action = ExportAction::Close;
+                    }
+                });
+            });
+        if !open {
+            action = ExportAction::Close;
+        }
+        action
+    }
+}
@@ -0,0 +1,244 @@
+use egui::{Key, RichText, TextEdit, Ui};
+use regex::{Regex, RegexBuilder};
+use std::borrow::Cow;
+use std::time::{Duration, Instant};
+use termwiz::surface::Line;
+
+/// How often matches are refreshed while new output keeps arriving.
+pub const REFRESH_INTERVAL: Duration = Duration::from_millis(300);
+
+/// A match in the terminal buffer, in cell columns of an absolute line.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Match {
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// What the find bar wants the terminal to do after a frame.
+pub enum FindAction {
+    None,
+    /// Scroll the match at this index into view.
+    Reveal(usize),
+    Close,
+}
+
+/// The Ctrl+Shift+F find bar of a terminal panel.
+#[derive(Default)]
+pub struct FindBar {
+    open: bool,
+    query: String,
+    use_regex: bool,
+    case_sensitive: bool,
+    focus: bool,
+    matches: Vec<Match>,
+    current: Option<usize>,
+    error: Option<String>,
+    /// Query, options and buffer revision the matches were computed for.
+    searched: Option<(String, bool, bool, usize)>,
+    searched_at: Option<Instant>,
+    /// How long the last search took.
+    search_cost: Duration,
+}
+
+impl FindBar {
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn open(&mut self) {
+        self.open = true;
+        self.focus = true;
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+        self.matches.clear();
+        self.current = None;
+        self.searched = None;
+    }
+
+    pub fn matches(&self) -> &[Match] {
+        &self.matches
+    }
+
+    pub fn current(&self) -> Option<usize> {
+        self.current
+    }
+
+    /// Re-run the search if the query, options or buffer changed.
+    /// `lines` are all lines of the buffer, the first being absolute line
+    /// `first_line`. Returns whether the current match moved.
+    pub fn update<'a>(
+        &mut self,
+        lines: impl Iterator<Item = Cow<'a, Line>>,
+        first_line: usize,
+        revision: usize,
+    ) -> bool {
+        let key = (self.query.clone(), self.use_regex, self.case_sensitive, revision);
+        if !self.open || self.searched.as_ref() == Some(&key) {
+            return false;
+        }
+        let query_changed = self
+            .searched
+            .as_ref()
+            .is_none_or(|(query, regex, case, _)| {
+                (query, *regex, *case) != (&self.query, self.use_regex, self.case_sensitive)
+            });
+        // A command flooding output would otherwise trigger a full search
+        // every frame; back off further when searching is slow
+        let interval = REFRESH_INTERVAL.max(self.search_cost * 5);
+        let recent = self.searched_at.is_some_and(|at| at.elapsed() < interval);
+        if !query_changed && recent {
+            return false;
+        }
+        self.searched = Some(key);
+        self.searched_at = Some(Instant::now());
+
+        let previous = self.current.map(|i| self.matches[i]);
+        self.matches.clear();
+        self.error = None;
+        if self.query.is_empty() {
+            self.current = None;
+            return query_changed;
+        }
+
+        let pattern = if self.use_regex {
+            self.query.clone()
+        } else {
+            regex::escape(&self.query)
+        };
+        let regex = match RegexBuilder::new(&pattern)
+            .case_insensitive(!self.case_sensitive)
+            .build()
+        {
+            Ok(regex) => regex,
+            Err(e) => {
+                self.error = Some(e.to_string());
+                self.current = None;
+                return false;
+            }
+        };
+        let started = Instant::now();
+        for (offset, line) in lines.enumerate() {
+            find_in_line(&regex, &line, first_line + offset, &mut self.matches);
+        }
+        self.search_cost = started.elapsed();
+
+        if query_changed {
+            // Start from the most recent output, where errors usually are
+            self.current = self.matches.len().checked_sub(1);
+            true
+        } else {
+            // New output arrived: stay on the same match if it still exists
+            self.current = previous
+                .and_then(|m| self.matches.iter().position(|other| *other == m))
+                .or_else(|| self.matches.len().checked_sub(1));
+            false
+        }
+    }
+
+    /// Step to the next (`forward`) or previous match, wrapping around.
+    fn step(&mut self, forward: bool) -> FindAction {
+        let count = self.matches.len();
+        if count == 0 {
+            return FindAction::None;
+        }
+        let next = match (self.current, forward) {
+            (None, true) => 0,
+            (None, false) => count - 1,
+            (Some(i), true) => (i + 1) % count,
+            (Some(i), false) => (i + count - 1) % count,
+        };
+        self.current = Some(next);
+        FindAction::Reveal(next)
+    }
+
+    pub fn render(&mut self, ui: &mut Ui) -> FindAction {
+        let mut action = FindAction::None;
+        ui.horizontal(|ui| {
+            ui.label("🔍");
+            let response = ui.add(
+                TextEdit::singleline(&mut self.query)
+                    .font(egui::FontId::monospace(12.0))
+                    .desired_width(200.0)
+                    .hint_text("Find in scrollback..."),
+            );
+            if self.focus {
+                response.request_focus();
+                self.focus = false;
+            }
+            if response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter)) {
+                let forward = !ui.input(|i| i.modifiers.shift);
+                action = self.step(forward);
+                response.request_focus();
+            }
+            if response.lost_focus() && ui.input(|i| i.key_pressed(Key::Escape)) {
+                action = FindAction::Close;
+            }
+
+            ui.checkbox(&mut self.use_regex, ".* Regex");
+            ui.checkbox(&mut self.case_sensitive, "Aa Case");
+
+            if let Some(error) = &self.error {
+                ui.label(RichText::new("Invalid regex").color(egui::Color32::LIGHT_RED))
+                    .on_hover_text(error);
+            } else if !self.query.is_empty() {
+                let position = self.current.map_or(0, |i| i + 1);
+                ui.label(format!("{}/{}", position, self.matches.len()));
+            }
+
+            let has_matches = !self.matches.is_empty();
+            if ui
+                .add_enabled(has_matches, egui::Button::new("⏶"))
+                .on_hover_text("Previous match (Shift+Enter)")
+                .clicked()
+            {
+                action = self.step(false);
+            }
+            if ui
+                .add_enabled(has_matches, egui::Button::new("⏷"))
+                .on_hover_text("Next match (Enter)")
+                .clicked()
+            {
+                action = self.step(true);
+            }
+            if ui.button("✕").on_hover_text("Close (Esc)").clicked() {
+                action = FindAction::Close;
+            }
+        });
+        action
+    }
+}
+
+/// Append the matches of `regex` in `line` to `out`.
+fn find_in_line(regex: &Regex, line: &Line, number: usize, out: &mut Vec<Match>) {
+    let (text, columns) = line_text(line);
+    for found in regex.find_iter(&text) {
+        if found.is_empty() {
+            continue;
+        }
+        out.push(Match {
+            line: number,
+            start: columns[found.start()],
+            end: columns[found.end()],
+        });
+    }
+}
+
+/// The text of `line` together with the cell column of every byte offset
+/// in it, plus one past the end, to map text matches back to the grid.
+pub fn line_text(line: &Line) -> (String, Vec<usize>) {
+    let mut text = String::new();
+    let mut columns = Vec::new();
+    let mut end_column = 0;
+    for cell in line.visible_cells() {
+        for _ in 0..cell.str().len() {
+            columns.push(cell.cell_index());
+        }
+        text.push_str(cell.str());
+        end_column = cell.cell_index() + cell.width().max(1);
+    }
+    columns.push(end_column);
+    (text, columns)
+}
@@ -0,0 +1,202 @@
+use egui::{Key, Modifiers, RichText, TextEdit};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// Most commands kept; the oldest are forgotten first.
+const MAX_ENTRIES: usize = 5_000;
+
+/// History shared by every terminal, so a command run in one tab can be
+/// recalled in another.
+pub type SharedHistory = Rc<RefCell<CommandHistory>>;
+
+/// A command run from the input line, with the directory it was run in.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub command: String,
+    pub dir: PathBuf,
+}
+
+/// Commands entered in the input line, oldest first. A command appears at
+/// most once per directory, at the position it was last run.
+#[derive(Default)]
+pub struct CommandHistory {
+    entries: Vec<HistoryEntry>,
+}
+
+impl CommandHistory {
+    pub fn from_entries(mut entries: Vec<HistoryEntry>) -> Self {
+        let excess = entries.len().saturating_sub(MAX_ENTRIES);
+        entries.drain(..excess);
+        Self { entries }
+    }
+
+    pub fn entries(&self) -> &[HistoryEntry] {
+        &self.entries
+    }
+
+    pub fn record(&mut self, command: &str, dir: &Path) {
+        let command = command.trim();
+        if command.is_empty() {
+            return;
+        }
+        self.entries
+            .retain(|entry| entry.command != command || entry.dir != dir);
+        self.entries.push(HistoryEntry {
+            command: command.to_string(),
+            dir: dir.to_path_buf(),
+        });
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.remove(0);
+        }
+    }
+
+    /// Distinct commands in recall order for a terminal in `dir`: the last
+    /// one is offered first. Commands run in `dir` come before those run
+    /// elsewhere, each group most recent first.
+    pub fn recall(&self, dir: &Path) -> Vec<String> {
+        let (here, elsewhere): (Vec<_>, Vec<_>) =
+            self.entries.iter().partition(|entry| entry.dir == dir);
+        let mut seen = HashSet::new();
+        let mut commands: Vec<String> = here
+            .iter()
+            .rev()
+            .chain(elsewhere.iter().rev())
+            .filter(|entry| seen.insert(entry.command.as_str()))
+            .map(|entry| entry.command.clone())
+            .collect();
+        commands.reverse();
+        commands
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// Matches shown at once in the reverse search popup.
+const SEARCH_RESULTS: usize = 8;
+
+/// What the reverse search wants the input line to do after a frame.
+pub enum SearchAction {
+    None,
+    /// Put this command in the input line.
+    Accept(String),
+    Close,
+}
+
+/// The Ctrl+R reverse search popup of the input line.
+#[derive(Default)]
+pub struct ReverseSearch {
+    open: bool,
+    query: String,
+    /// Index into the matches, 0 being the most relevant.
+    selected: usize,
+    focus: bool,
+}
+
+impl ReverseSearch {
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn open(&mut self, query: &str) {
+        self.open = true;
+        self.query = query.to_string();
+        self.selected = 0;
+        self.focus = true;
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+    }
+
+    /// Show the popup above `anchor`. `commands` are in recall order, as
+    /// returned by `CommandHistory::recall`.
+    pub fn render(
+        &mut self,
+        ctx: &egui::Context,
+        id: egui::Id,
+        anchor: egui::Pos2,
+        width: f32,
+        commands: &[String],
+    ) -> SearchAction {
+        let query = self.query.to_lowercase();
+        let matches: Vec<&String> = commands
+            .iter()
+            .rev()
+            .filter(|command| command.to_lowercase().contains(&query))
+            .collect();
+        self.selected = self.selected.min(matches.len().saturating_sub(1));
+
+        let mut action = SearchAction::None;
+        egui::Area::new(id)
+            .order(egui::Order::Foreground)
+            .fixed_pos(anchor)
+            .pivot(egui::Align2::LEFT_BOTTOM)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.set_width(width);
+                    let first = self.selected.saturating_sub(SEARCH_RESULTS - 1);
+                    // Oldest at the top, so the best match sits next to the
+                    // input line like in the shell
+                    for (index, command) in matches.iter().enumerate().skip(first).take(SEARCH_RESULTS).rev() {
+                        let label = RichText::new(command.as_str()).monospace();
+                        if ui.selectable_label(index == self.selected, label).clicked() {
+                            action = SearchAction::Accept(command.to_string());
+                        }
+                    }
+                    if matches.is_empty() {
+                        ui.label(RichText::new("No matching commands").weak());
+                    }
+                    ui.separator();
+
+                    ui.horizontal(|ui| {
+                        ui.label(RichText::new("(reverse-i-search)").weak().monospace());
+                        let id = ui.id().with("query");
+                        if ui.memory(|m| m.has_focus(id)) {
+                            let (older, newer) = ui.input_mut(|i| {
+                                (
+                                    i.consume_key(Modifiers::CTRL, Key::R) || i.consume_key(Modifiers::NONE, Key::ArrowUp),
+                                    i.consume_key(Modifiers::NONE, Key::ArrowDown),
+                                )
+                            });
+                            if older && self.selected + 1 < matches.len() {
+                                self.selected += 1;
+                            }
+                            if newer {
+                                self.selected = self.selected.saturating_sub(1);
+                            }
+                        }
+                        let response = ui.add(
+                            TextEdit::singleline(&mut self.query)
+                                .id(id)
+                                .font(egui::FontId::monospace(12.0))
+                                .desired_width(f32::INFINITY),
+                        );
+                        if response.changed() {
+                            self.selected = 0;
+                        }
+                        if self.focus {
+                            response.request_focus();
+                            self.focus = false;
+                        }
+                        if response.lost_focus() {
+                            if ui.input(|i| i.key_pressed(Key::Enter)) {
+                                if let Some(command) = matches.get(self.selected) {
+                                    action = SearchAction::Accept(command.to_string());
+                                } else {
+                                    action = SearchAction::Close;
+                                }
+                            } else if ui.input(|i| i.key_pressed(Key::Escape)) {
+                                action = SearchAction::Close;
+                            }
+                        }
+                    });
+                });
+            });
+        action
+    }
+}
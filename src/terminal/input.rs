@@ -0,0 +1,171 @@
+use egui::{Key, Modifiers};
+
+/// xterm modifier parameter: 1 + shift + 2*alt + 4*ctrl.
+fn modifier_param(modifiers: Modifiers) -> u8 {
+    1 + modifiers.shift as u8 + 2 * modifiers.alt as u8 + 4 * modifiers.ctrl as u8
+}
+
+/// `ESC [ 1 ; m <final>` when modified, otherwise `ESC [ <final>` or, in
+/// application cursor mode, `ESC O <final>`.
+fn cursor_key(final_byte: char, modifiers: Modifiers, application: bool) -> Vec<u8> {
+    let m = modifier_param(modifiers);
+    if m > 1 {
+        format!("\x1b[1;{}{}", m, final_byte).into_bytes()
+    } else if application {
+        format!("\x1bO{}", final_byte).into_bytes()
+    } else {
+        format!("\x1b[{}", final_byte).into_bytes()
+    }
+}
+
+/// `ESC [ n ~`, or `ESC [ n ; m ~` when modified.
+fn tilde_key(code: u8, modifiers: Modifiers) -> Vec<u8> {
+    let m = modifier_param(modifiers);
+    if m > 1 {
+        format!("\x1b[{};{}~", code, m).into_bytes()
+    } else {
+        format!("\x1b[{}~", code).into_bytes()
+    }
+}
+
+/// F1-F4 use SS3 when unmodified.
+fn ss3_function_key(final_byte: char, modifiers: Modifiers) -> Vec<u8> {
+    let m = modifier_param(modifiers);
+    if m > 1 {
+        format!("\x1b[1;{}{}", m, final_byte).into_bytes()
+    } else {
+        format!("\x1bO{}", final_byte).into_bytes()
+    }
+}
+
+/// The control byte sent for Ctrl+<key>, if the key has one.
+fn control_byte(key: Key) -> Option<u8> {
+    let name = key.name();
+    if name.len() == 1 {
+        let c = name.as_bytes()[0].to_ascii_uppercase();
+        if c.is_ascii_uppercase() {
+            return Some(c - b'@');
+        }
+    }
+    match key {
+        Key::Space | Key::Num2 => Some(0x00),
+        Key::OpenBracket | Key::Num3 => Some(0x1b),
+        Key::Backslash | Key::Num4 => Some(0x1c),
+        Key::CloseBracket | Key::Num5 => Some(0x1d),
+        Key::Num6 => Some(0x1e),
+        Key::Minus | Key::Num7 => Some(0x1f),
+        Key::Slash => Some(0x1f),
+        Key::Num8 => Some(0x7f),
+        _ => None,
+    }
+}
+
+/// Encode a key press the way xterm does.
+///
+/// Printable characters arrive separately as text events, so this only
+/// returns bytes for keys that do not produce text on their own: editing
+/// and navigation keys, function keys, and Ctrl/Alt combinations.
+pub fn encode_key(key: Key, modifiers: Modifiers, application_cursor: bool) -> Option<Vec<u8>> {
+    let bytes = match key {
+        Key::ArrowUp => cursor_key('A', modifiers, application_cursor),
+        Key::ArrowDown => cursor_key('B', modifiers, application_cursor),
+        Key::ArrowRight => cursor_key('C', modifiers, application_cursor),
+        Key::ArrowLeft => cursor_key('D', modifiers, application_cursor),
+        Key::Home => cursor_key('H', modifiers, application_cursor),
+        Key::End => cursor_key('F', modifiers, application_cursor),
+        Key::Insert => tilde_key(2, modifiers),
+        Key::Delete => tilde_key(3, modifiers),
+        Key::PageUp => tilde_key(5, modifiers),
+        Key::PageDown => tilde_key(6, modifiers),
+        Key::F1 => ss3_function_key('P', modifiers),
+        Key::F2 => ss3_function_key('Q', modifiers),
+        Key::F3 => ss3_function_key('R', modifiers),
+        Key::F4 => ss3_function_key('S', modifiers),
+        Key::F5 => tilde_key(15, modifiers),
+        Key::F6 => tilde_key(17, modifiers),
+        Key::F7 => tilde_key(18, modifiers),
+        Key::F8 => tilde_key(19, modifiers),
+        Key::F9 => tilde_key(20, modifiers),
+        Key::F10 => tilde_key(21, modifiers),
+        Key::F11 => tilde_key(23, modifiers),
+        Key::F12 => tilde_key(24, modifiers),
+        Key::Enter => with_alt(b'\r', modifiers),
+        Key::Escape => vec![0x1b],
+        Key::Tab if modifiers.shift => b"\x1b[Z".to_vec(),
+        Key::Tab => with_alt(b'\t', modifiers),
+        Key::Backspace if modifiers.ctrl => with_alt(0x08, modifiers),
+        Key::Backspace => with_alt(0x7f, modifiers),
+        _ if modifiers.ctrl => with_alt(control_byte(key)?, modifiers),
+        _ => return None,
+    };
+    Some(bytes)
+}
+
+/// Alt sends an ESC prefix (xterm's `metaSendsEscape`).
+fn with_alt(byte: u8, modifiers: Modifiers) -> Vec<u8> {
+    if modifiers.alt {
+        vec![0x1b, byte]
+    } else {
+        vec![byte]
+    }
+}
+
+/// Encode typed text, prefixing ESC when Alt is held.
+pub fn encode_text(text: &str, modifiers: Modifiers) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(text.len() + 1);
+    if modifiers.alt && !modifiers.ctrl {
+        bytes.push(0x1b);
+    }
+    bytes.extend_from_slice(text.as_bytes());
+    bytes
+}
+
+/// Encode pasted text. Line breaks are sent as carriage returns, like a
+/// typed Enter. With bracketed paste (mode 2004) the text is wrapped in
+/// `ESC [ 200 ~` / `ESC [ 201 ~` so the application can tell it was pasted;
+/// ESC characters are stripped so the text cannot end the bracket early.
+pub fn encode_paste(text: &str, bracketed: bool) -> Vec<u8> {
+    let text = text.replace("\r\n", "\r").replace('\n', "\r");
+    if bracketed {
+        format!("\x1b[200~{}\x1b[201~", text.replace('\x1b', "")).into_bytes()
+    } else {
+        text.into_bytes()
+    }
+}
+
+/// Button codes of xterm mouse reports.
+pub const MOUSE_LEFT: u8 = 0;
+pub const MOUSE_MIDDLE: u8 = 1;
+pub const MOUSE_RIGHT: u8 = 2;
+/// Motion with no button held, reported as a release in legacy encoding.
+pub const MOUSE_NONE: u8 = 3;
+pub const MOUSE_WHEEL_UP: u8 = 64;
+pub const MOUSE_WHEEL_DOWN: u8 = 65;
+/// Added to the button code for motion events.
+pub const MOUSE_MOTION: u8 = 32;
+
+/// Encode a mouse report for the zero-based cell `column`, `row`. With SGR
+/// encoding (mode 1006) this is `ESC [ < b ; x ; y M`, or `m` for a
+/// release; otherwise the legacy `ESC [ M` form, which cannot express
+/// positions past column or row 223.
+pub fn encode_mouse(
+    button: u8,
+    column: usize,
+    row: usize,
+    release: bool,
+    modifiers: Modifiers,
+    sgr: bool,
+) -> Option<Vec<u8>> {
+    let code = button
+        + 4 * modifiers.shift as u8
+        + 8 * modifiers.alt as u8
+        + 16 * modifiers.ctrl as u8;
+    if sgr {
+        let action = if release { 'm' } else { 'M' };
+        return Some(format!("\x1b[<{};{};{}{}", code, column + 1, row + 1, action).into_bytes());
+    }
+    // Legacy releases do not say which button was let go
+    let code = if release { (code & !3) | MOUSE_NONE } else { code };
+    let encode = |value: usize| u8::try_from(value + 33).ok();
+    Some(vec![0x1b, b'[', b'M', 32 + code, encode(column)?, encode(row)?])
+}
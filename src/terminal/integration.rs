@@ -0,0 +1,68 @@
+use super::shell::ShellKind;
+use portable_pty::CommandBuilder;
+use std::path::PathBuf;
+
+const BASH_RC: &str = include_str!("integration/bash.sh");
+const ZSH_ENV: &str = include_str!("integration/zshenv.zsh");
+const ZSH_HOOKS: &str = include_str!("integration/zsh.zsh");
+const FISH_INIT: &str = include_str!("integration/fish.fish");
+
+/// Directory the integration scripts are written to before each spawn.
+fn script_dir() -> Option<PathBuf> {
+    Some(dirs::cache_dir()?.join("rustfm").join("shell-integration"))
+}
+
+fn write_script(name: &str, contents: &str) -> Option<PathBuf> {
+    let dir = script_dir()?;
+    std::fs::create_dir_all(&dir).ok()?;
+    let path = dir.join(name);
+    std::fs::write(&path, contents).ok()?;
+    Some(path)
+}
+
+/// Arrange for the shell to load our OSC 133/OSC 7 hooks on top of the
+/// user's own startup files. Returns false if the shell is not supported or
+/// the scripts could not be written, in which case `cmd` is left untouched.
+///
+/// Bash does not read an rcfile as a login shell, so for `login` our rcfile
+/// reads the login files itself instead of the caller passing `-l`.
+pub fn inject(kind: ShellKind, login: bool, cmd: &mut CommandBuilder) -> bool {
+    match kind {
+        ShellKind::Bash => {
+            let Some(rc) = write_script("bashrc", BASH_RC) else {
+                return false;
+            };
+            cmd.arg("--rcfile");
+            cmd.arg(rc);
+            if login {
+                cmd.env("RUSTFM_BASH_LOGIN", "1");
+            }
+            true
+        }
+        ShellKind::Zsh => {
+            let (Some(_), Some(hooks)) = (
+                write_script(".zshenv", ZSH_ENV),
+                write_script("rustfm.zsh", ZSH_HOOKS),
+            ) else {
+                return false;
+            };
+            if let Ok(user_zdotdir) = std::env::var("ZDOTDIR") {
+                cmd.env("RUSTFM_USER_ZDOTDIR", user_zdotdir);
+            }
+            if let Some(dir) = script_dir() {
+                cmd.env("ZDOTDIR", dir);
+            }
+            cmd.env("RUSTFM_SHELL_INTEGRATION", hooks);
+            true
+        }
+        ShellKind::Fish => {
+            let Some(init) = write_script("rustfm.fish", FISH_INIT) else {
+                return false;
+            };
+            cmd.arg("--init-command");
+            cmd.arg(format!("source {}", ShellKind::Fish.quote(&init)));
+            true
+        }
+        _ => false,
+    }
+}
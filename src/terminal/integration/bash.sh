@@ -0,0 +1,38 @@
+# Shell integration for the embedded terminal: OSC 133 prompt/command marks
+# and OSC 7 working directory reports. Loaded via `bash --rcfile`.
+
+if [ -n "$RUSTFM_BASH_LOGIN" ]; then
+    # Started for a login profile: read what bash --login would
+    unset RUSTFM_BASH_LOGIN
+    if [ -f /etc/profile ]; then . /etc/profile; fi
+    for __rustfm_file in "$HOME/.bash_profile" "$HOME/.bash_login" "$HOME/.profile"; do
+        if [ -f "$__rustfm_file" ]; then . "$__rustfm_file"; break; fi
+    done
+    unset __rustfm_file
+else
+    if [ -f /etc/bash.bashrc ]; then . /etc/bash.bashrc; fi
+    if [ -f "$HOME/.bashrc" ]; then . "$HOME/.bashrc"; fi
+fi
+
+if [ -z "$__rustfm_integration" ]; then
+    __rustfm_integration=1
+
+    __rustfm_precmd() {
+        local status=$?
+        # The terminal ignores D when no command was started with C
+        printf '\033]133;D;%s\007' "$status"
+        # OSC 7 carries a URL: escape what the terminal would decode
+        local dir=${PWD//%/%25}
+        printf '\033]133;A\007\033]7;file://%s%s\007' "$HOSTNAME" "$dir"
+        case "$PS1" in
+            *'133;B'*) ;;
+            *) PS1="$PS1"'\[\033]133;B\007\]' ;;
+        esac
+        # Hooks after this one, and the prompt, see the command's status
+        return "$status"
+    }
+
+    PROMPT_COMMAND="__rustfm_precmd${PROMPT_COMMAND:+; $PROMPT_COMMAND}"
+    # PS0 is printed after a command line is read, before it runs (bash 4.4+)
+    PS0="${PS0}"$'\033]133;C\007'
+fi
@@ -0,0 +1,23 @@
+# Shell integration for the embedded terminal: OSC 133 prompt/command marks
+# and OSC 7 working directory reports. Loaded via `fish --init-command`.
+
+function __rustfm_prompt --on-event fish_prompt
+    # OSC 7 carries a URL: escape what the terminal would decode
+    printf '\e]133;A\a\e]7;file://%s%s\a' $hostname (string replace -a % %25 -- $PWD)
+end
+
+function __rustfm_preexec --on-event fish_preexec
+    printf '\e]133;C\a'
+end
+
+function __rustfm_postexec --on-event fish_postexec
+    printf '\e]133;D;%s\a' $status
+end
+
+if functions -q fish_prompt; and not functions -q __rustfm_original_prompt
+    functions -c fish_prompt __rustfm_original_prompt
+    function fish_prompt
+        __rustfm_original_prompt
+        printf '\e]133;B\a'
+    end
+end
@@ -0,0 +1,151 @@
+use super::emulator::parse_file_url;
+use super::find::line_text;
+use regex::Regex;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use termwiz::surface::Line;
+
+/// What a link in terminal output points at.
+#[derive(Clone, PartialEq, Debug)]
+pub enum LinkTarget {
+    /// An existing file or directory, with the position from a
+    /// `path:line:col` reference.
+    Path {
+        path: PathBuf,
+        line: Option<u32>,
+        column: Option<u32>,
+    },
+    Url(String),
+}
+
+impl LinkTarget {
+    /// How the link reads in a status message, e.g. `src/main.rs:10:5`.
+    pub fn describe(&self) -> String {
+        match self {
+            LinkTarget::Path { path, line, column } => {
+                let mut text = path.display().to_string();
+                for number in [line, column].into_iter().flatten() {
+                    text.push_str(&format!(":{}", number));
+                }
+                text
+            }
+            LinkTarget::Url(url) => url.clone(),
+        }
+    }
+}
+
+/// What clicking a link asks the application to do.
+#[derive(Clone, PartialEq, Debug)]
+pub enum LinkAction {
+    /// Show the path in the explorer
+    Reveal(LinkTarget),
+    /// Open the path or URL with the default application
+    Open(LinkTarget),
+}
+
+#[derive(Clone, Debug)]
+pub struct Link {
+    pub columns: Range<usize>,
+    pub target: LinkTarget,
+}
+
+/// Links in one line of output: OSC 8 hyperlinks, URLs, and paths that
+/// exist relative to `cwd`, optionally followed by `:line` and `:line:col`
+/// as printed by compilers and grep.
+pub fn find_links(line: &Line, cwd: &Path) -> Vec<Link> {
+    let mut links = hyperlinks(line);
+    let (text, columns) = line_text(line);
+    for token in token_regex().find_iter(&text) {
+        let start = columns[token.start()];
+        if links.iter().any(|link| link.columns.contains(&start)) {
+            continue;
+        }
+        let word = token.as_str();
+        if word.starts_with("http://") || word.starts_with("https://") {
+            let url = word.trim_end_matches(['.', ',', ':', ';', '!', '?']);
+            links.push(Link {
+                columns: start..columns[token.start() + url.len()],
+                target: LinkTarget::Url(url.to_string()),
+            });
+            continue;
+        }
+        let Some(found) = path_regex().captures(word) else {
+            continue;
+        };
+        let path_text = found["path"].trim_end_matches(['.', ',']);
+        if path_text.is_empty() {
+            continue;
+        }
+        let Some(path) = resolve(path_text, cwd) else {
+            continue;
+        };
+        // Trailing punctuation means the path ended a sentence, not that a
+        // position follows
+        let (line, column, matched) = if path_text.len() == found["path"].len() {
+            let number = |name| found.name(name).and_then(|m| m.as_str().parse().ok());
+            (number("line"), number("column"), found[0].len())
+        } else {
+            (None, None, path_text.len())
+        };
+        links.push(Link {
+            columns: start..columns[token.start() + matched],
+            target: LinkTarget::Path { path, line, column },
+        });
+    }
+    links
+}
+
+/// Runs of cells carrying the same OSC 8 hyperlink.
+fn hyperlinks(line: &Line) -> Vec<Link> {
+    let mut links: Vec<Link> = Vec::new();
+    let mut previous_uri: Option<String> = None;
+    for cell in line.visible_cells() {
+        let uri = cell.attrs().hyperlink().map(|link| link.uri().to_string());
+        let end = cell.cell_index() + cell.width().max(1);
+        match (&uri, links.last_mut()) {
+            (Some(uri), Some(link)) if previous_uri.as_ref() == Some(uri) => {
+                link.columns.end = end;
+            }
+            (Some(uri), _) => {
+                let target = match parse_file_url(uri) {
+                    Some(path) => LinkTarget::Path { path, line: None, column: None },
+                    None => LinkTarget::Url(uri.clone()),
+                };
+                links.push(Link { columns: cell.cell_index()..end, target });
+            }
+            (None, _) => {}
+        }
+        previous_uri = uri;
+    }
+    links
+}
+
+/// Resolve a path as written in output against the shell's directory,
+/// keeping it only if it exists.
+fn resolve(text: &str, cwd: &Path) -> Option<PathBuf> {
+    // Plain words are far more often not paths than paths
+    if !text.contains(['/', '\\', '.']) {
+        return None;
+    }
+    let path = match text.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir()?.join(rest),
+        None => cwd.join(text),
+    };
+    path.exists().then(|| dunce::simplified(&path).to_path_buf())
+}
+
+/// Whitespace-separated words, not counting quotes and brackets around them.
+fn token_regex() -> &'static Regex {
+    static TOKEN: OnceLock<Regex> = OnceLock::new();
+    TOKEN.get_or_init(|| Regex::new(r#"[^\s'"`()\[\]{}<>,;|]+"#).expect("valid regex"))
+}
+
+/// A path followed by an optional `:line` or `:line:col`.
+fn path_regex() -> &'static Regex {
+    static PATH: OnceLock<Regex> = OnceLock::new();
+    PATH.get_or_init(|| {
+        Regex::new(r"^(?P<path>(?:[A-Za-z]:)?[^:]+)(?::(?P<line>\d+))?(?::(?P<column>\d+))?")
+            .expect("valid regex")
+    })
+}
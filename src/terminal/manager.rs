@@ -0,0 +1,724 @@
+use super::history::{CommandHistory, HistoryEntry, SharedHistory};
+use super::notify::{self, NotifySettings};
+use super::profile::ProfileEditor;
+use super::{format_duration, LinkAction, ScrollbackLimit, ShellKind, TerminalPanel, TerminalProfile};
+use egui::{Color32, Event, Id, Key, Modifiers, Rect, RichText, Sense, Stroke, StrokeKind, Ui, UiBuilder};
+use std::path::PathBuf;
+
+const SPLITTER_WIDTH: f32 = 6.0;
+const MIN_PANE_RATIO: f32 = 0.1;
+
+#[derive(Clone, Copy, PartialEq)]
+enum SplitDirection {
+    /// Panes side by side
+    Horizontal,
+    /// Panes stacked top to bottom
+    Vertical,
+}
+
+/// A tab's layout: either a single terminal or a split holding two panes.
+enum Pane {
+    Terminal { id: u64, panel: Box<TerminalPanel> },
+    Split { direction: SplitDirection, ratio: f32, children: Vec<Pane> },
+}
+
+impl Pane {
+    fn find_mut(&mut self, target: u64) -> Option<&mut TerminalPanel> {
+        match self {
+            Pane::Terminal { id, panel } => (*id == target).then_some(panel.as_mut()),
+            Pane::Split { children, .. } => {
+                children.iter_mut().find_map(|child| child.find_mut(target))
+            }
+        }
+    }
+
+    fn first_id(&self) -> u64 {
+        match self {
+            Pane::Terminal { id, .. } => *id,
+            Pane::Split { children, .. } => children[0].first_id(),
+        }
+    }
+
+    fn count(&self) -> usize {
+        match self {
+            Pane::Terminal { .. } => 1,
+            Pane::Split { children, .. } => children.iter().map(Pane::count).sum(),
+        }
+    }
+
+    fn for_each_mut(&mut self, f: &mut impl FnMut(&mut TerminalPanel)) {
+        match self {
+            Pane::Terminal { panel, .. } => f(panel),
+            Pane::Split { children, .. } => {
+                for child in children {
+                    child.for_each_mut(f);
+                }
+            }
+        }
+    }
+
+    /// Replace the terminal `target` with a split of it and `new`.
+    fn split(&mut self, target: u64, direction: SplitDirection, new: Pane) -> Result<(), Pane> {
+        match self {
+            Pane::Terminal { id, .. } if *id == target => {
+                let old = std::mem::replace(
+                    self,
+                    Pane::Split { direction, ratio: 0.5, children: Vec::with_capacity(2) },
+                );
+                if let Pane::Split { children, .. } = self {
+                    children.push(old);
+                    children.push(new);
+                }
+                Ok(())
+            }
+            Pane::Terminal { .. } => Err(new),
+            Pane::Split { children, .. } => {
+                let mut new = new;
+                for child in children {
+                    match child.split(target, direction, new) {
+                        Ok(()) => return Ok(()),
+                        Err(returned) => new = returned,
+                    }
+                }
+                Err(new)
+            }
+        }
+    }
+
+    /// Remove the terminal `target` from a split, collapsing the split into
+    /// its remaining child. The root pane itself is never removed here.
+    fn close(&mut self, target: u64) -> bool {
+        let Pane::Split { children, .. } = self else {
+            return false;
+        };
+        if let Some(index) = children
+            .iter()
+            .position(|child| matches!(child, Pane::Terminal { id, .. } if *id == target))
+        {
+            children.remove(index);
+            let remaining = children.remove(0);
+            *self = remaining;
+            return true;
+        }
+        children.iter_mut().any(|child| child.close(target))
+    }
+
+    fn render(&mut self, ui: &mut Ui, rect: Rect, focused: &mut u64, show_focus: bool) {
+        match self {
+            Pane::Terminal { id, panel } => {
+                let mut child = ui.new_child(
+                    UiBuilder::new()
+                        .id_salt(("terminal_pane", *id))
+                        .max_rect(rect.shrink(2.0)),
+                );
+                panel.render(&mut child);
+
+                if ui.rect_contains_pointer(rect) && ui.input(|i| i.pointer.any_pressed()) {
+                    *focused = *id;
+                }
+                if show_focus && *focused == *id {
+                    ui.painter().rect_stroke(
+                        rect.shrink(1.0),
+                        2.0,
+                        Stroke::new(1.0, ui.visuals().selection.stroke.color),
+                        StrokeKind::Inside,
+                    );
+                }
+            }
+            Pane::Split { direction, ratio, children } => {
+                let (first, splitter, second) = split_rect(rect, *direction, *ratio);
+                let splitter_id = Id::new(("terminal_splitter", children[0].first_id()));
+                let response = ui.interact(splitter, splitter_id, Sense::drag());
+                if response.dragged() {
+                    if let Some(pointer) = response.interact_pointer_pos() {
+                        *ratio = match direction {
+                            SplitDirection::Horizontal => (pointer.x - rect.left()) / rect.width(),
+                            SplitDirection::Vertical => (pointer.y - rect.top()) / rect.height(),
+                        }
+                        .clamp(MIN_PANE_RATIO, 1.0 - MIN_PANE_RATIO);
+                    }
+                }
+                if response.hovered() || response.dragged() {
+                    ui.ctx().set_cursor_icon(match direction {
+                        SplitDirection::Horizontal => egui::CursorIcon::ResizeHorizontal,
+                        SplitDirection::Vertical => egui::CursorIcon::ResizeVertical,
+                    });
+                }
+                ui.painter().rect_filled(
+                    splitter.shrink(2.0),
+                    0.0,
+                    ui.visuals().widgets.noninteractive.bg_stroke.color,
+                );
+
+                children[0].render(ui, first, focused, show_focus);
+                children[1].render(ui, second, focused, show_focus);
+            }
+        }
+    }
+}
+
+fn split_rect(rect: Rect, direction: SplitDirection, ratio: f32) -> (Rect, Rect, Rect) {
+    match direction {
+        SplitDirection::Horizontal => {
+            let x = rect.left() + rect.width() * ratio;
+            (
+                Rect::from_min_max(rect.min, egui::pos2(x - SPLITTER_WIDTH / 2.0, rect.bottom())),
+                Rect::from_min_max(
+                    egui::pos2(x - SPLITTER_WIDTH / 2.0, rect.top()),
+                    egui::pos2(x + SPLITTER_WIDTH / 2.0, rect.bottom()),
+                ),
+                Rect::from_min_max(egui::pos2(x + SPLITTER_WIDTH / 2.0, rect.top()), rect.max),
+            )
+        }
+        SplitDirection::Vertical => {
+            let y = rect.top() + rect.height() * ratio;
+            (
+                Rect::from_min_max(rect.min, egui::pos2(rect.right(), y - SPLITTER_WIDTH / 2.0)),
+                Rect::from_min_max(
+                    egui::pos2(rect.left(), y - SPLITTER_WIDTH / 2.0),
+                    egui::pos2(rect.right(), y + SPLITTER_WIDTH / 2.0),
+                ),
+                Rect::from_min_max(egui::pos2(rect.left(), y + SPLITTER_WIDTH / 2.0), rect.max),
+            )
+        }
+    }
+}
+
+struct TerminalTab {
+    name: Option<String>,
+    number: usize,
+    root: Pane,
+    focused: u64,
+}
+
+impl TerminalTab {
+    fn focused_panel(&mut self) -> Option<&mut TerminalPanel> {
+        self.root.find_mut(self.focused)
+    }
+
+    fn label(&mut self) -> String {
+        let number = self.number;
+        let name = self.name.clone();
+        let Some(panel) = self.focused_panel() else {
+            return name.unwrap_or_else(|| format!("Terminal {}", number));
+        };
+        let label = match name {
+            Some(name) => name,
+            None if !panel.title().is_empty() => panel.title().to_string(),
+            None if !panel.profile().program.is_empty() => panel.profile().name.clone(),
+            None => format!("Terminal {}", number),
+        };
+        if panel.is_alive() {
+            label
+        } else {
+            format!("{} (exited)", label)
+        }
+    }
+
+    /// Whether any pane has new output, and whether any rang the bell,
+    /// since the tab was last shown.
+    fn unseen(&mut self) -> (bool, bool) {
+        let (mut output, mut bell) = (false, false);
+        self.root.for_each_mut(&mut |panel| {
+            output |= panel.has_unseen_output();
+            bell |= panel.has_unseen_bell();
+        });
+        (output, bell)
+    }
+}
+
+enum TabAction {
+    Select(usize),
+    Close(usize),
+    MoveLeft(usize),
+    MoveRight(usize),
+    StartRename(usize),
+}
+
+/// Owns every open shell, organised as tabs that can each be split into
+/// several panes.
+pub struct TerminalManager {
+    tabs: Vec<TerminalTab>,
+    active: usize,
+    next_id: u64,
+    next_number: usize,
+    current_dir: PathBuf,
+    renaming: Option<(usize, String)>,
+    scrollback_limit: ScrollbackLimit,
+    history: SharedHistory,
+    /// The first profile is used for new tabs.
+    profiles: Vec<TerminalProfile>,
+    profile_editor: Option<ProfileEditor>,
+    notify: NotifySettings,
+}
+
+impl TerminalManager {
+    pub fn new(initial_dir: PathBuf, mut profiles: Vec<TerminalProfile>) -> Self {
+        if profiles.is_empty() {
+            profiles = TerminalProfile::defaults();
+        }
+        let mut manager = Self {
+            tabs: Vec::new(),
+            active: 0,
+            next_id: 0,
+            next_number: 1,
+            current_dir: initial_dir,
+            renaming: None,
+            scrollback_limit: ScrollbackLimit::default(),
+            history: SharedHistory::default(),
+            profiles,
+            profile_editor: None,
+            notify: NotifySettings::default(),
+        };
+        manager.new_tab();
+        manager
+    }
+
+    /// A terminal running `profile` in `dir`, or else where the focused
+    /// terminal is.
+    fn new_pane(&mut self, profile: TerminalProfile, dir: Option<PathBuf>) -> (u64, Pane) {
+        let id = self.next_id;
+        self.next_id += 1;
+        let dir = dir.unwrap_or_else(|| {
+            self.active_panel()
+                .map(|panel| panel.current_dir().to_path_buf())
+                .unwrap_or_else(|| self.current_dir.clone())
+        });
+        let mut panel = Box::new(TerminalPanel::new(dir, profile));
+        panel.set_scrollback_limit(self.scrollback_limit);
+        panel.set_history(self.history.clone());
+        (id, Pane::Terminal { id, panel })
+    }
+
+    pub fn scrollback_limit(&self) -> ScrollbackLimit {
+        self.scrollback_limit
+    }
+
+    /// Apply a scrollback limit to every open terminal and to new ones.
+    pub fn set_scrollback_limit(&mut self, limit: ScrollbackLimit) {
+        self.scrollback_limit = limit;
+        for tab in &mut self.tabs {
+            tab.root.for_each_mut(&mut |panel| panel.set_scrollback_limit(limit));
+        }
+    }
+
+    /// Input line history of every terminal, for saving between sessions.
+    pub fn history(&self) -> Vec<HistoryEntry> {
+        self.history.borrow().entries().to_vec()
+    }
+
+    pub fn load_history(&mut self, entries: Vec<HistoryEntry>) {
+        *self.history.borrow_mut() = CommandHistory::from_entries(entries);
+    }
+
+    pub fn notify_settings(&self) -> NotifySettings {
+        self.notify
+    }
+
+    pub fn set_notify_settings(&mut self, settings: NotifySettings) {
+        self.notify = settings;
+    }
+
+    pub fn profiles(&self) -> &[TerminalProfile] {
+        &self.profiles
+    }
+
+    pub fn new_tab(&mut self) {
+        self.open_profile(0, None);
+    }
+
+    /// Open a new tab with the default profile in `dir`.
+    pub fn new_tab_in(&mut self, dir: PathBuf) {
+        self.open_profile(0, Some(dir));
+    }
+
+    /// Open a new tab running the profile at `index`.
+    fn open_profile(&mut self, index: usize, dir: Option<PathBuf>) {
+        let profile = self.profiles.get(index).cloned().unwrap_or_default();
+        let (id, root) = self.new_pane(profile, dir);
+        self.tabs.push(TerminalTab {
+            name: None,
+            number: self.next_number,
+            root,
+            focused: id,
+        });
+        self.next_number += 1;
+        self.active = self.tabs.len() - 1;
+    }
+
+    fn split_active(&mut self, direction: SplitDirection) {
+        if self.tabs.is_empty() {
+            return;
+        }
+        // The new pane runs the same profile as the one being split
+        let profile = self
+            .active_panel()
+            .map(|panel| panel.profile().clone())
+            .unwrap_or_default();
+        let (id, pane) = self.new_pane(profile, None);
+        let tab = &mut self.tabs[self.active];
+        if tab.root.split(tab.focused, direction, pane).is_ok() {
+            tab.focused = id;
+        }
+    }
+
+    fn close_active_pane(&mut self) {
+        let Some(tab) = self.tabs.get_mut(self.active) else {
+            return;
+        };
+        if tab.root.count() > 1 {
+            tab.root.close(tab.focused);
+            tab.focused = tab.root.first_id();
+        } else {
+            self.close_tab(self.active);
+        }
+    }
+
+    fn close_tab(&mut self, index: usize) {
+        self.tabs.remove(index);
+        self.renaming = None;
+        if self.active >= self.tabs.len() {
+            self.active = self.tabs.len().saturating_sub(1);
+        } else if self.active > index {
+            self.active -= 1;
+        }
+    }
+
+    fn move_tab(&mut self, from: usize, to: usize) {
+        self.tabs.swap(from, to);
+        if self.active == from {
+            self.active = to;
+        } else if self.active == to {
+            self.active = from;
+        }
+    }
+
+    fn active_panel(&mut self) -> Option<&mut TerminalPanel> {
+        self.tabs.get_mut(self.active)?.focused_panel()
+    }
+
+    /// Follow explorer navigation in the focused terminal of the active tab.
+    pub fn set_directory(&mut self, path: PathBuf) {
+        self.current_dir = path.clone();
+        if let Some(panel) = self.active_panel() {
+            panel.set_directory(path);
+        }
+    }
+
+    /// The shell in the focused terminal, `None` if there is no terminal or
+    /// it runs something other than a shell.
+    pub fn shell_kind(&mut self) -> Option<ShellKind> {
+        self.active_panel().and_then(|panel| panel.shell_kind())
+    }
+
+    /// Run a command line in the focused terminal.
+    pub fn run_command(&mut self, command: &str) {
+        if let Some(panel) = self.active_panel() {
+            panel.run_command(command);
+        }
+    }
+
+    /// Type `paths` into the focused terminal.
+    pub fn insert_paths(&mut self, paths: &[PathBuf]) {
+        if let Some(panel) = self.active_panel() {
+            panel.insert_paths(paths);
+        }
+    }
+
+    /// A directory the focused terminal's shell has moved to. Changes in
+    /// other terminals are discarded so the explorer only follows one shell.
+    pub fn check_navigation(&mut self) -> Option<PathBuf> {
+        let navigation = self.active_panel().and_then(|panel| panel.check_navigation());
+        for tab in &mut self.tabs {
+            tab.root.for_each_mut(&mut |panel| {
+                panel.check_navigation();
+            });
+        }
+        navigation
+    }
+
+    /// A link clicked in any terminal since the last call.
+    pub fn check_links(&mut self) -> Option<LinkAction> {
+        let mut action = None;
+        for tab in &mut self.tabs {
+            tab.root.for_each_mut(&mut |panel| {
+                if let Some(link) = panel.take_link_action() {
+                    action = Some(link);
+                }
+            });
+        }
+        action
+    }
+
+    pub fn update(&mut self, ctx: &egui::Context) {
+        for tab in &mut self.tabs {
+            tab.root.for_each_mut(&mut |panel| panel.update(ctx));
+        }
+        self.notify_finished_commands(ctx);
+    }
+
+    /// Tell the user about long commands that finished while the window was
+    /// in the background.
+    fn notify_finished_commands(&mut self, ctx: &egui::Context) {
+        let unfocused = ctx.input(|i| i.viewport().focused) == Some(false);
+        let settings = self.notify;
+        let mut notified = false;
+        for tab in &mut self.tabs {
+            let mut finished = Vec::new();
+            tab.root.for_each_mut(&mut |panel| finished.extend(panel.take_finished_commands()));
+            if !unfocused {
+                continue;
+            }
+            for command in finished {
+                let Some(duration) = command.duration.filter(|d| settings.applies_to(*d)) else {
+                    continue;
+                };
+                let title = match command.exit_status {
+                    Some(0) | None => "Command finished".to_string(),
+                    Some(code) => format!("Command failed with exit {}", code),
+                };
+                let body = format!(
+                    "{}\n{} after {}",
+                    command.command_line.as_deref().unwrap_or("(unknown command)"),
+                    tab.label(),
+                    format_duration(duration)
+                );
+                notify::send(&title, &body);
+                notified = true;
+            }
+        }
+        if notified {
+            ctx.send_viewport_cmd(egui::ViewportCommand::RequestUserAttention(
+                egui::UserAttentionType::Informational,
+            ));
+        }
+    }
+
+    pub fn render(&mut self, ui: &mut Ui) {
+        self.render_tab_bar(ui);
+        ui.separator();
+
+        if let Some(editor) = &mut self.profile_editor {
+            if !editor.render(ui.ctx(), &mut self.profiles) {
+                self.profile_editor = None;
+            }
+        }
+
+        if self.tabs.is_empty() {
+            ui.centered_and_justified(|ui| {
+                if ui.button("🖥️  Open a new terminal").clicked() {
+                    self.new_tab();
+                }
+            });
+            return;
+        }
+
+        // Consumed here so the key never reaches a pane in raw input mode
+        if ui.input_mut(|i| i.consume_key(Modifiers::CTRL | Modifiers::SHIFT, Key::F)) {
+            if let Some(panel) = self.active_panel() {
+                panel.open_find();
+            }
+        }
+        // Ctrl+Shift+C copies the focused terminal's selection
+        let copy = ui.input(|i| i.modifiers.shift && i.events.contains(&Event::Copy));
+        if copy {
+            if let Some(panel) = self.active_panel() {
+                panel.copy_selection(ui.ctx());
+            }
+        }
+
+        let rect = ui.available_rect_before_wrap();
+        let tab = &mut self.tabs[self.active];
+        tab.root.for_each_mut(&mut |panel| panel.mark_seen());
+        let show_focus = tab.root.count() > 1;
+        tab.root.render(ui, rect, &mut tab.focused, show_focus);
+        ui.allocate_rect(rect, Sense::hover());
+    }
+
+    fn render_tab_bar(&mut self, ui: &mut Ui) {
+        let mut action = None;
+        let tab_count = self.tabs.len();
+
+        ui.horizontal(|ui| {
+            for index in 0..tab_count {
+                if let Some((renaming, name)) = &mut self.renaming {
+                    if *renaming == index {
+                        let response = ui.add(
+                            egui::TextEdit::singleline(name).desired_width(100.0),
+                        );
+                        response.request_focus();
+                        if response.lost_focus() {
+                            let name = name.trim().to_string();
+                            self.tabs[index].name = (!name.is_empty()).then_some(name);
+                            self.renaming = None;
+                        }
+                        continue;
+                    }
+                }
+
+                let mut label = self.tabs[index].label();
+                let mut hover = None;
+                if index != self.active {
+                    match self.tabs[index].unseen() {
+                        (_, true) => {
+                            label = format!("🔔 {}", label);
+                            hover = Some("The bell rang");
+                        }
+                        (true, false) => {
+                            label = format!("● {}", label);
+                            hover = Some("New output");
+                        }
+                        (false, false) => {}
+                    }
+                }
+                let response = ui.selectable_label(self.active == index, label);
+                let response = match hover {
+                    Some(hover) => response.on_hover_text(hover),
+                    None => response,
+                };
+                if response.clicked() {
+                    action = Some(TabAction::Select(index));
+                }
+                if response.double_clicked() {
+                    action = Some(TabAction::StartRename(index));
+                }
+                response.context_menu(|ui| {
+                    if ui.button("Rename").clicked() {
+                        action = Some(TabAction::StartRename(index));
+                        ui.close();
+                    }
+                    if ui.add_enabled(index > 0, egui::Button::new("Move Left")).clicked() {
+                        action = Some(TabAction::MoveLeft(index));
+                        ui.close();
+                    }
+                    if ui
+                        .add_enabled(index + 1 < tab_count, egui::Button::new("Move Right"))
+                        .clicked()
+                    {
+                        action = Some(TabAction::MoveRight(index));
+                        ui.close();
+                    }
+                    ui.separator();
+                    if ui.button("Close").clicked() {
+                        action = Some(TabAction::Close(index));
+                        ui.close();
+                    }
+                });
+            }
+
+            if ui.button("➕").on_hover_text("New terminal tab").clicked() {
+                self.new_tab();
+            }
+            ui.menu_button("⏷", |ui| {
+                let mut open = None;
+                for (index, profile) in self.profiles.iter().enumerate() {
+                    if ui.button(&profile.name).clicked() {
+                        open = Some(index);
+                        ui.close();
+                    }
+                }
+                ui.separator();
+                if ui.button("Edit profiles...").clicked() {
+                    self.profile_editor = Some(ProfileEditor::default());
+                    ui.close();
+                }
+                if let Some(index) = open {
+                    self.open_profile(index, None);
+                }
+            })
+            .response
+            .on_hover_text("New tab with a profile");
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                ui.menu_button("⚙", |ui| self.render_settings(ui))
+                    .response
+                    .on_hover_text("Terminal settings");
+                let has_tabs = !self.tabs.is_empty();
+                if ui
+                    .add_enabled(has_tabs, egui::Button::new(RichText::new("✕").color(Color32::LIGHT_RED)))
+                    .on_hover_text("Close pane")
+                    .clicked()
+                {
+                    self.close_active_pane();
+                }
+                if ui
+                    .add_enabled(has_tabs, egui::Button::new("⬍"))
+                    .on_hover_text("Split vertically")
+                    .clicked()
+                {
+                    self.split_active(SplitDirection::Vertical);
+                }
+                if ui
+                    .add_enabled(has_tabs, egui::Button::new("⬌"))
+                    .on_hover_text("Split horizontally")
+                    .clicked()
+                {
+                    self.split_active(SplitDirection::Horizontal);
+                }
+            });
+        });
+
+        match action {
+            Some(TabAction::Select(index)) => self.active = index,
+            Some(TabAction::Close(index)) => self.close_tab(index),
+            Some(TabAction::MoveLeft(index)) => self.move_tab(index, index - 1),
+            Some(TabAction::MoveRight(index)) => self.move_tab(index, index + 1),
+            Some(TabAction::StartRename(index)) => {
+                let name = self.tabs[index].label();
+                self.renaming = Some((index, name));
+            }
+            None => {}
+        }
+    }
+
+    fn render_settings(&mut self, ui: &mut Ui) {
+        ui.label(RichText::new("Scrollback").strong());
+        let mut limit = self.scrollback_limit;
+        let lines = match limit {
+            ScrollbackLimit::Lines(lines) | ScrollbackLimit::Spill(lines) => lines,
+            ScrollbackLimit::Unlimited => ScrollbackLimit::default_lines(),
+        };
+        ui.radio_value(&mut limit, ScrollbackLimit::Lines(lines), "Keep the most recent lines");
+        ui.radio_value(
+            &mut limit,
+            ScrollbackLimit::Spill(lines),
+            "Keep recent lines in memory, older ones on disk",
+        );
+        ui.radio_value(&mut limit, ScrollbackLimit::Unlimited, "Unlimited");
+        if let ScrollbackLimit::Lines(lines) | ScrollbackLimit::Spill(lines) = &mut limit {
+            ui.horizontal(|ui| {
+                ui.label("Lines in memory:");
+                ui.add(egui::DragValue::new(lines).range(100..=1_000_000).speed(100));
+            });
+        }
+        if limit != self.scrollback_limit {
+            self.set_scrollback_limit(limit);
+        }
+
+        ui.separator();
+        ui.label(RichText::new("Notifications").strong());
+        ui.checkbox(&mut self.notify.enabled, "Notify when a long command finishes")
+            .on_hover_text("Sent while the window is in the background; needs shell integration");
+        ui.add_enabled_ui(self.notify.enabled, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Longer than:");
+                ui.add(
+                    egui::DragValue::new(&mut self.notify.min_seconds)
+                        .range(1..=86_400)
+                        .suffix(" s"),
+                );
+            });
+        });
+
+        ui.separator();
+        ui.label(RichText::new("History").strong());
+        let count = self.history.borrow().entries().len();
+        ui.label(format!("{} commands remembered", count));
+        if ui
+            .add_enabled(count > 0, egui::Button::new("Clear command history"))
+            .clicked()
+        {
+            self.history.borrow_mut().clear();
+        }
+    }
+}
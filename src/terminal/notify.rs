@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// When to tell the user that a command has finished.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct NotifySettings {
+    /// Send a desktop notification when a long command finishes while the
+    /// window is in the background.
+    pub enabled: bool,
+    /// Commands shorter than this finish without a notification.
+    pub min_seconds: u64,
+}
+
+impl Default for NotifySettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_seconds: 10,
+        }
+    }
+}
+
+impl NotifySettings {
+    pub fn applies_to(&self, duration: Duration) -> bool {
+        self.enabled && duration >= Duration::from_secs(self.min_seconds)
+    }
+}
+
+/// Show a desktop notification through the platform's own tool. Failures are
+/// ignored: the taskbar attention request still tells the user.
+pub fn send(title: &str, body: &str) {
+    #[cfg(target_os = "macos")]
+    {
+        let quote = |text: &str| text.replace('\\', "\\\\").replace('"', "\\\"");
+        let script = format!(
+            "display notification \"{}\" with title \"{}\"",
+            quote(body),
+            quote(title)
+        );
+        let mut command = std::process::Command::new("osascript");
+        command.arg("-e").arg(script);
+        spawn(command);
+    }
+    #[cfg(target_os = "linux")]
+    {
+        let mut command = std::process::Command::new("notify-send");
+        command.args(["--app-name", "File Explorer", "--", title, body]);
+        spawn(command);
+    }
+    #[cfg(target_os = "windows")]
+    {
+        let quote = |text: &str| text.replace('\'', "''");
+        let script = format!(
+            "Add-Type -AssemblyName System.Windows.Forms; \
+             $n = New-Object System.Windows.Forms.NotifyIcon; \
+             $n.Icon = [System.Drawing.SystemIcons]::Information; \
+             $n.Visible = $true; \
+             $n.ShowBalloonTip(5000, '{}', '{}', 'Info'); \
+             Start-Sleep -Seconds 6; $n.Dispose()",
+            quote(title),
+            quote(body)
+        );
+        let mut command = std::process::Command::new("powershell");
+        command.args(["-NoProfile", "-WindowStyle", "Hidden", "-Command", &script]);
+        spawn(command);
+    }
+}
+
+/// Run a notification tool, reaping it in the background.
+fn spawn(mut command: std::process::Command) {
+    if let Ok(mut child) = command.spawn() {
+        std::thread::spawn(move || {
+            let _ = child.wait();
+        });
+    }
+}
@@ -0,0 +1,360 @@
+use super::integration;
+use super::shell::ShellKind;
+use egui::{RichText, TextEdit, Ui};
+use portable_pty::CommandBuilder;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// A named way of starting a terminal: which program to run, with what
+/// arguments and environment, and where.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct TerminalProfile {
+    pub name: String,
+    /// Program to run; empty means the user's shell.
+    pub program: String,
+    pub args: Vec<String>,
+    /// Variables set on top of the inherited environment.
+    pub env: Vec<(String, String)>,
+    /// Where to start instead of the explorer's current directory.
+    pub directory: Option<PathBuf>,
+    /// Start the shell as a login shell, reading the profile files.
+    pub login: bool,
+    /// Force an interactive shell even when arguments would make it
+    /// non-interactive.
+    pub interactive: bool,
+}
+
+impl Default for TerminalProfile {
+    fn default() -> Self {
+        Self {
+            name: "Shell".to_string(),
+            program: String::new(),
+            args: Vec::new(),
+            env: Vec::new(),
+            directory: None,
+            login: false,
+            interactive: false,
+        }
+    }
+}
+
+impl TerminalProfile {
+    /// Profiles offered before the user configures any. The first one is
+    /// used for new tabs.
+    pub fn defaults() -> Vec<Self> {
+        vec![
+            Self::default(),
+            Self {
+                name: "Login shell".to_string(),
+                login: true,
+                ..Self::default()
+            },
+            Self {
+                name: "Python 3".to_string(),
+                program: "python3".to_string(),
+                ..Self::default()
+            },
+        ]
+    }
+
+    /// The program to run, falling back to `$SHELL`.
+    pub fn program(&self) -> String {
+        if !self.program.trim().is_empty() {
+            return self.program.trim().to_string();
+        }
+        std::env::var("SHELL").unwrap_or_else(|_| {
+            if cfg!(target_os = "windows") {
+                "cmd.exe".to_string()
+            } else {
+                "/bin/sh".to_string()
+            }
+        })
+    }
+
+    /// The directory to start in: the profile's own, with `~` expanded,
+    /// or `fallback`.
+    pub fn start_dir(&self, fallback: &Path) -> PathBuf {
+        let Some(dir) = &self.directory else {
+            return fallback.to_path_buf();
+        };
+        match (dir.strip_prefix("~"), dirs::home_dir()) {
+            (Ok(rest), Some(home)) => home.join(rest),
+            _ => dir.clone(),
+        }
+    }
+
+    /// Build the command to spawn in `dir`, along with the kind of shell it
+    /// runs. Programs that are not a known shell get no shell flags, no
+    /// shell integration and no `cd` commands typed into them.
+    pub fn command(&self, dir: &Path) -> (CommandBuilder, Option<ShellKind>) {
+        let program = self.program();
+        let kind = ShellKind::recognize(&program);
+        let mut cmd = CommandBuilder::new(&program);
+        if let Some(kind) = kind {
+            // Integrated bash reads the login files from our rcfile
+            if self.login && kind != ShellKind::Bash {
+                cmd.args(kind.login_flag());
+            }
+            let integrated = integration::inject(kind, self.login, &mut cmd);
+            if self.login && kind == ShellKind::Bash && !integrated {
+                cmd.args(kind.login_flag());
+            }
+            if self.interactive {
+                cmd.args(kind.interactive_flag());
+            }
+        }
+        cmd.args(&self.args);
+
+        cmd.env("TERM", "xterm-256color");
+        cmd.env("COLORTERM", "truecolor");
+        for (key, value) in &self.env {
+            if !key.is_empty() {
+                cmd.env(key, value);
+            }
+        }
+        cmd.cwd(dir);
+        (cmd, kind)
+    }
+}
+
+/// Split a command line typed in the profile editor into arguments.
+/// Whitespace separates arguments; single and double quotes group them.
+pub fn split_args(text: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut quote = None;
+    for c in text.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => current.push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                in_arg = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            (None, c) => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    if in_arg {
+        args.push(current);
+    }
+    args
+}
+
+/// The inverse of `split_args`, for showing arguments in the editor.
+pub fn join_args(args: &[String]) -> String {
+    args.iter()
+        .map(|arg| {
+            if arg.is_empty() || arg.contains(char::is_whitespace) || arg.contains(['\'', '"']) {
+                if !arg.contains('\'') {
+                    format!("'{}'", arg)
+                } else if !arg.contains('"') {
+                    format!("\"{}\"", arg)
+                } else {
+                    // Write each single quote double-quoted between single-quoted runs
+                    format!("'{}'", arg.replace('\'', r#"'"'"'"#))
+                }
+            } else {
+                arg.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The window for adding, removing and changing profiles.
+#[derive(Default)]
+pub struct ProfileEditor {
+    selected: usize,
+    /// Arguments of the selected profile as typed, kept so quoting and
+    /// spacing survive while editing.
+    args_text: Option<String>,
+}
+
+impl ProfileEditor {
+    /// Returns false once the window is closed.
+    pub fn render(&mut self, ctx: &egui::Context, profiles: &mut Vec<TerminalProfile>) -> bool {
+        let mut open = true;
+        egui::Window::new("Terminal Profiles")
+            .open(&mut open)
+            .default_width(480.0)
+            .show(ctx, |ui| {
+                ui.horizontal_top(|ui| {
+                    ui.vertical(|ui| {
+                        ui.set_width(140.0);
+                        self.render_list(ui, profiles);
+                    });
+                    ui.separator();
+                    ui.vertical(|ui| {
+                        if let Some(profile) = profiles.get_mut(self.selected) {
+                            self.render_profile(ui, profile);
+                        }
+                    });
+                });
+            });
+        open
+    }
+
+    fn select(&mut self, index: usize) {
+        self.selected = index;
+        self.args_text = None;
+    }
+
+    fn render_list(&mut self, ui: &mut Ui, profiles: &mut Vec<TerminalProfile>) {
+        for (index, profile) in profiles.iter().enumerate() {
+            let mut label = RichText::new(&profile.name);
+            if index == 0 {
+                label = label.strong();
+            }
+            let response = ui.selectable_label(index == self.selected, label);
+            let response = if index == 0 {
+                response.on_hover_text("Used for new tabs")
+            } else {
+                response
+            };
+            if response.clicked() {
+                self.select(index);
+            }
+        }
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("➕").on_hover_text("New profile").clicked() {
+                profiles.push(TerminalProfile {
+                    name: format!("Profile {}", profiles.len() + 1),
+                    ..TerminalProfile::default()
+                });
+                self.select(profiles.len() - 1);
+            }
+            let selected = self.selected;
+            if ui
+                .add_enabled(profiles.len() > 1, egui::Button::new("🗑"))
+                .on_hover_text("Remove profile")
+                .clicked()
+            {
+                profiles.remove(selected);
+                self.select(selected.min(profiles.len() - 1));
+            }
+            if ui
+                .add_enabled(selected > 0, egui::Button::new("⏶"))
+                .on_hover_text("Move up; the first profile is used for new tabs")
+                .clicked()
+            {
+                profiles.swap(selected, selected - 1);
+                self.select(selected - 1);
+            }
+        });
+    }
+
+    fn render_profile(&mut self, ui: &mut Ui, profile: &mut TerminalProfile) {
+        egui::Grid::new("profile_fields").num_columns(2).show(ui, |ui| {
+            ui.label("Name:");
+            ui.text_edit_singleline(&mut profile.name);
+            ui.end_row();
+
+            ui.label("Program:");
+            ui.add(TextEdit::singleline(&mut profile.program).hint_text("Default shell ($SHELL)"));
+            ui.end_row();
+
+            ui.label("Arguments:");
+            let args = self.args_text.get_or_insert_with(|| join_args(&profile.args));
+            if ui
+                .add(TextEdit::singleline(args).hint_text("e.g. develop"))
+                .changed()
+            {
+                profile.args = split_args(args);
+            }
+            ui.end_row();
+
+            ui.label("Directory:");
+            let mut dir = profile
+                .directory
+                .as_ref()
+                .map(|dir| dir.display().to_string())
+                .unwrap_or_default();
+            if ui
+                .add(TextEdit::singleline(&mut dir).hint_text("Explorer's current directory"))
+                .changed()
+            {
+                profile.directory = (!dir.trim().is_empty()).then(|| PathBuf::from(dir.trim()));
+            }
+            ui.end_row();
+        });
+
+        ui.checkbox(&mut profile.login, "Login shell")
+            .on_hover_text("Read the login profile files, like a new console session");
+        ui.checkbox(&mut profile.interactive, "Force interactive")
+            .on_hover_text("Pass the shell's interactive flag");
+
+        ui.separator();
+        ui.label(RichText::new("Environment").strong());
+        let mut remove = None;
+        egui::Grid::new("profile_env").num_columns(3).show(ui, |ui| {
+            for (index, (key, value)) in profile.env.iter_mut().enumerate() {
+                ui.add(TextEdit::singleline(key).desired_width(120.0).hint_text("NAME"));
+                ui.add(TextEdit::singleline(value).desired_width(200.0).hint_text("value"));
+                if ui.small_button("✕").clicked() {
+                    remove = Some(index);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(index) = remove {
+            profile.env.remove(index);
+        }
+        if ui.button("➕ Add variable").clicked() {
+            profile.env.push((String::new(), String::new()));
+        }
+        ui.label(
+            RichText::new("TERM=xterm-256color and COLORTERM=truecolor are set unless overridden here.")
+                .weak()
+                .small(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn split_args_groups_quoted_text() {
+        assert_eq!(split_args("  -l   -c 'echo hi' "), args(&["-l", "-c", "echo hi"]));
+        assert_eq!(split_args(r#"--dir "/tmp/it's here""#), args(&["--dir", "/tmp/it's here"]));
+        assert_eq!(split_args(r#"a'b c'"d""#), args(&["ab cd"]));
+        assert_eq!(split_args("'' x"), args(&["", "x"]));
+        assert!(split_args("   ").is_empty());
+    }
+
+    #[test]
+    fn join_args_quotes_only_when_needed() {
+        assert_eq!(join_args(&args(&["-l", "-i"])), "-l -i");
+        assert_eq!(join_args(&args(&["/tmp/a b", ""])), "'/tmp/a b' ''");
+        assert_eq!(join_args(&args(&["it's"])), r#""it's""#);
+    }
+
+    #[test]
+    fn join_args_round_trips_through_split_args() {
+        let cases = [
+            args(&["-c", "echo hi"]),
+            args(&["/tmp/it's here", r#"say "hi""#]),
+            args(&[r#"both ' and ""#, "", "plain"]),
+            args(&["tab\there", "ünïcode path"]),
+        ];
+        for case in cases {
+            assert_eq!(split_args(&join_args(&case)), case);
+        }
+    }
+}
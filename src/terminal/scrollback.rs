@@ -0,0 +1,375 @@
+use super::style;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use termwiz::cell::{unicode_column_width, CellAttributes};
+use termwiz::escape::parser::Parser;
+use termwiz::escape::{Action, OperatingSystemCommand, CSI};
+use termwiz::surface::Line;
+
+/// How many lines scrolled off the screen are kept.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum ScrollbackLimit {
+    /// Keep the most recent lines, dropping older ones.
+    Lines(usize),
+    /// Keep everything in memory.
+    Unlimited,
+    /// Keep the most recent lines in memory and move older ones to a
+    /// temporary file.
+    Spill(usize),
+}
+
+impl ScrollbackLimit {
+    pub fn default_lines() -> usize {
+        10_000
+    }
+}
+
+impl Default for ScrollbackLimit {
+    fn default() -> Self {
+        ScrollbackLimit::Lines(Self::default_lines())
+    }
+}
+
+/// Lines that have scrolled off the top of the screen, oldest first.
+///
+/// Lines are numbered from the first one still available; `first_line`
+/// says how many have been dropped before it, so callers can keep absolute
+/// line numbers that stay valid as the buffer is trimmed.
+pub struct Scrollback {
+    limit: ScrollbackLimit,
+    lines: VecDeque<Line>,
+    spill: Option<SpillFile>,
+    dropped: usize,
+}
+
+impl Scrollback {
+    pub fn new(limit: ScrollbackLimit) -> Self {
+        Self {
+            limit,
+            lines: VecDeque::new(),
+            spill: None,
+            dropped: 0,
+        }
+    }
+
+    /// Change the limit, trimming right away. Lines already spilled to disk
+    /// are dropped when leaving spill mode.
+    pub fn set_limit(&mut self, limit: ScrollbackLimit) {
+        if !matches!(limit, ScrollbackLimit::Spill(_)) {
+            self.drop_spill();
+        }
+        self.limit = limit;
+        self.trim();
+    }
+
+    /// Absolute number of the first available line.
+    pub fn first_line(&self) -> usize {
+        self.dropped
+    }
+
+    pub fn len(&self) -> usize {
+        self.spilled() + self.lines.len()
+    }
+
+    fn spilled(&self) -> usize {
+        self.spill.as_ref().map_or(0, SpillFile::len)
+    }
+
+    /// The line `index` lines after the first available one.
+    pub fn get(&self, index: usize) -> Option<Cow<'_, Line>> {
+        let spilled = self.spilled();
+        if index < spilled {
+            self.spill.as_ref()?.read(index).map(Cow::Owned)
+        } else {
+            self.lines.get(index - spilled).map(Cow::Borrowed)
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Cow<'_, Line>> {
+        let spilled = self.spill.iter().flat_map(SpillFile::iter).map(Cow::Owned);
+        spilled.chain(self.lines.iter().map(Cow::Borrowed))
+    }
+
+    pub fn push(&mut self, mut line: Line) {
+        line.compress_for_scrollback();
+        self.lines.push_back(line);
+        self.trim();
+    }
+
+    /// Drop every line.
+    pub fn clear(&mut self) {
+        self.drop_spill();
+        self.dropped += self.lines.len();
+        self.lines.clear();
+    }
+
+    /// Account for lines that left the screen without being kept, so
+    /// absolute line numbers stay in step with the screen.
+    pub fn skip(&mut self, count: usize) {
+        self.dropped += count;
+    }
+
+    fn drop_spill(&mut self) {
+        if let Some(spill) = self.spill.take() {
+            self.dropped += spill.len();
+        }
+    }
+
+    fn trim(&mut self) {
+        let keep = match self.limit {
+            ScrollbackLimit::Lines(keep) | ScrollbackLimit::Spill(keep) => keep,
+            ScrollbackLimit::Unlimited => return,
+        };
+        let spill = matches!(self.limit, ScrollbackLimit::Spill(_));
+        while self.lines.len() > keep {
+            let Some(line) = self.lines.pop_front() else {
+                break;
+            };
+            if spill && self.spill_line(&line) {
+                continue;
+            }
+            self.dropped += 1;
+        }
+    }
+
+    /// Move a line to the spill file, creating it on first use. If the file
+    /// cannot be written, everything spilled so far is dropped.
+    fn spill_line(&mut self, line: &Line) -> bool {
+        if self.spill.is_none() {
+            self.spill = SpillFile::create().ok();
+        }
+        let Some(spill) = &mut self.spill else {
+            return false;
+        };
+        if spill.append(line).is_ok() {
+            return true;
+        }
+        self.drop_spill();
+        false
+    }
+}
+
+/// Scrollback lines stored on disk as SGR-encoded text, one per record.
+struct SpillFile {
+    path: PathBuf,
+    file: RefCell<BufWriter<File>>,
+    /// Set when a read moved the file position away from the end.
+    moved: Cell<bool>,
+    /// Start offset of every record, plus the end of the last one.
+    offsets: Vec<u64>,
+}
+
+impl SpillFile {
+    fn create() -> std::io::Result<Self> {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "rustfm-scrollback-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)?;
+        Ok(Self {
+            path,
+            file: RefCell::new(BufWriter::new(file)),
+            moved: Cell::new(false),
+            offsets: vec![0],
+        })
+    }
+
+    fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    fn append(&mut self, line: &Line) -> std::io::Result<()> {
+        let wrapped = if line.last_cell_was_wrapped() { "w" } else { "-" };
+        let record = format!("{}{}\n", wrapped, style::line_to_ansi(line));
+        let file = self.file.get_mut();
+        let end = *self.offsets.last().unwrap_or(&0);
+        if self.moved.replace(false) {
+            file.seek(SeekFrom::Start(end))?;
+        }
+        file.write_all(record.as_bytes())?;
+        self.offsets.push(end + record.len() as u64);
+        Ok(())
+    }
+
+    fn read(&self, index: usize) -> Option<Line> {
+        let start = *self.offsets.get(index)?;
+        let end = *self.offsets.get(index + 1)?;
+        let mut file = self.file.borrow_mut();
+        file.flush().ok()?;
+        let file = file.get_mut();
+        self.moved.set(true);
+        file.seek(SeekFrom::Start(start)).ok()?;
+        let mut record = vec![0; (end - start) as usize];
+        file.read_exact(&mut record).ok()?;
+        Some(decode_record(&record))
+    }
+
+    /// Read every line in order, sequentially rather than line by line.
+    fn iter(&self) -> impl Iterator<Item = Line> {
+        let reader = {
+            let mut file = self.file.borrow_mut();
+            file.flush()
+                .and_then(|_| file.get_ref().try_clone())
+                .and_then(|mut reader| {
+                    reader.seek(SeekFrom::Start(0))?;
+                    Ok(reader)
+                })
+        };
+        self.moved.set(true);
+        let reader = reader.ok().map(|reader| BufReader::new(reader).take(self.offsets[self.len()]));
+        reader.into_iter().flat_map(|reader| {
+            reader
+                .split(b'\n')
+                .map_while(Result::ok)
+                .map(|record| decode_record(&record))
+        })
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+fn decode_record(record: &[u8]) -> Line {
+    let record = String::from_utf8_lossy(record);
+    let record = record.trim_end_matches('\n');
+    let (wrapped, text) = record.split_at(record.len().min(1));
+    line_from_ansi(text, wrapped == "w")
+}
+
+/// Rebuild a line written by `style::line_to_ansi`.
+fn line_from_ansi(text: &str, wrapped: bool) -> Line {
+    // Group combining marks with the character before them
+    let mut cells: Vec<(String, usize, CellAttributes)> = Vec::new();
+    let mut attrs = CellAttributes::default();
+    let mut print = |c: char, attrs: &CellAttributes| {
+        let mut buf = [0; 4];
+        let grapheme = c.encode_utf8(&mut buf);
+        let width = unicode_column_width(grapheme, None);
+        match cells.last_mut() {
+            Some((text, _, _)) if width == 0 => text.push(c),
+            _ => cells.push((grapheme.to_string(), width.max(1), attrs.clone())),
+        }
+    };
+    for action in Parser::new().parse_as_vec(text.as_bytes()) {
+        match action {
+            Action::Print(c) => print(c, &attrs),
+            Action::PrintString(s) => s.chars().for_each(|c| print(c, &attrs)),
+            Action::CSI(CSI::Sgr(sgr)) => style::apply_sgr(&mut attrs, sgr),
+            Action::OperatingSystemCommand(osc) => {
+                if let OperatingSystemCommand::SetHyperlink(link) = *osc {
+                    attrs.set_hyperlink(link.map(Arc::new));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut line = Line::new(0);
+    let mut column = 0;
+    for (text, width, attrs) in cells {
+        line.set_cell_grapheme(column, &text, width, attrs, 0);
+        column += width;
+    }
+    if wrapped {
+        line.set_last_cell_was_wrapped(true, 0);
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(text: &str) -> Line {
+        Line::from_text(text, &CellAttributes::default(), 0, None)
+    }
+
+    fn push_numbered(scrollback: &mut Scrollback, range: std::ops::Range<usize>) {
+        for n in range {
+            scrollback.push(line(&n.to_string()));
+        }
+    }
+
+    fn texts(scrollback: &Scrollback) -> Vec<String> {
+        scrollback.iter().map(|line| line.as_str().trim_end().to_string()).collect()
+    }
+
+    #[test]
+    fn ring_keeps_the_most_recent_lines_after_wrapping() {
+        let mut scrollback = Scrollback::new(ScrollbackLimit::Lines(3));
+        push_numbered(&mut scrollback, 0..3);
+        assert_eq!(texts(&scrollback), ["0", "1", "2"]);
+        assert_eq!(scrollback.first_line(), 0);
+
+        // Go all the way around the ring and then some
+        push_numbered(&mut scrollback, 3..10);
+        assert_eq!(scrollback.len(), 3);
+        assert_eq!(scrollback.first_line(), 7);
+        assert_eq!(texts(&scrollback), ["7", "8", "9"]);
+        assert_eq!(scrollback.get(0).unwrap().as_str().trim_end(), "7");
+        assert!(scrollback.get(3).is_none());
+    }
+
+    #[test]
+    fn unlimited_keeps_every_line() {
+        let mut scrollback = Scrollback::new(ScrollbackLimit::Unlimited);
+        push_numbered(&mut scrollback, 0..100);
+        assert_eq!(scrollback.len(), 100);
+        assert_eq!(scrollback.first_line(), 0);
+    }
+
+    #[test]
+    fn lowering_the_limit_trims_and_clearing_keeps_numbering() {
+        let mut scrollback = Scrollback::new(ScrollbackLimit::Lines(10));
+        push_numbered(&mut scrollback, 0..10);
+        scrollback.set_limit(ScrollbackLimit::Lines(4));
+        assert_eq!(texts(&scrollback), ["6", "7", "8", "9"]);
+        assert_eq!(scrollback.first_line(), 6);
+
+        scrollback.clear();
+        assert_eq!(scrollback.len(), 0);
+        assert_eq!(scrollback.first_line(), 10);
+        scrollback.skip(5);
+        assert_eq!(scrollback.first_line(), 15);
+    }
+
+    #[test]
+    fn spill_moves_old_lines_to_disk() {
+        let mut scrollback = Scrollback::new(ScrollbackLimit::Spill(2));
+        push_numbered(&mut scrollback, 0..6);
+        let mut wrapped = line("wrapped");
+        wrapped.set_last_cell_was_wrapped(true, 0);
+        scrollback.push(wrapped);
+
+        assert_eq!(scrollback.len(), 7);
+        assert_eq!(scrollback.first_line(), 0);
+        assert_eq!(texts(&scrollback), ["0", "1", "2", "3", "4", "5", "wrapped"]);
+        // Reading from the middle of the file does not disturb later appends
+        assert_eq!(scrollback.get(1).unwrap().as_str().trim_end(), "1");
+        push_numbered(&mut scrollback, 6..8);
+        assert_eq!(scrollback.get(6).unwrap().as_str().trim_end(), "wrapped");
+        assert!(scrollback.get(6).unwrap().last_cell_was_wrapped());
+        assert_eq!(scrollback.get(8).unwrap().as_str().trim_end(), "7");
+
+        scrollback.set_limit(ScrollbackLimit::Lines(2));
+        assert_eq!(texts(&scrollback), ["6", "7"]);
+        assert_eq!(scrollback.first_line(), 7);
+    }
+}
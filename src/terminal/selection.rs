@@ -0,0 +1,127 @@
+use super::emulator::Emulator;
+use std::ops::Range;
+
+/// A position between cells: absolute line number and column.
+pub type Point = (usize, usize);
+
+/// Characters that end a word for double-click selection, besides
+/// whitespace. Path separators and dots are kept so paths select whole.
+const WORD_DELIMITERS: &str = "()[]{}<>'\"`,;|";
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SelectionMode {
+    /// Click and drag
+    Cell,
+    /// Double-click
+    Word,
+    /// Triple-click
+    Line,
+}
+
+/// A text selection on the terminal grid. Points are absolute lines, so a
+/// selection stays on the same text as output scrolls.
+#[derive(Clone, Copy, Debug)]
+pub struct Selection {
+    anchor: Point,
+    head: Point,
+    mode: SelectionMode,
+}
+
+impl Selection {
+    pub fn new(point: Point, mode: SelectionMode) -> Self {
+        Self { anchor: point, head: point, mode }
+    }
+
+    /// Move the end being dragged.
+    pub fn extend_to(&mut self, point: Point) {
+        self.head = point;
+    }
+
+    pub fn is_empty(&self, emulator: &Emulator) -> bool {
+        let (start, end) = self.span(emulator);
+        start == end
+    }
+
+    /// Start and end of the selection in reading order, expanded to whole
+    /// words or lines for those modes. The end is exclusive.
+    pub fn span(&self, emulator: &Emulator) -> (Point, Point) {
+        let (start, end) = if self.anchor <= self.head {
+            (self.anchor, self.head)
+        } else {
+            (self.head, self.anchor)
+        };
+        match self.mode {
+            SelectionMode::Cell => (start, end),
+            SelectionMode::Word => {
+                let start = word_at(emulator, start).map_or(start, |word| (start.0, word.start));
+                let end = word_at(emulator, end).map_or(end, |word| (end.0, word.end));
+                (start, end)
+            }
+            SelectionMode::Line => ((start.0, 0), (end.0, usize::MAX)),
+        }
+    }
+
+    /// Selected columns of `line`, if any.
+    pub fn columns(&self, emulator: &Emulator, line: usize) -> Option<Range<usize>> {
+        let (start, end) = self.span(emulator);
+        if line < start.0 || line > end.0 {
+            return None;
+        }
+        let from = if line == start.0 { start.1 } else { 0 };
+        let to = if line == end.0 { end.1 } else { usize::MAX };
+        (from < to).then_some(from..to)
+    }
+
+    /// The selected text, with soft-wrapped rows joined and trailing blanks
+    /// removed from each line.
+    pub fn text(&self, emulator: &Emulator) -> String {
+        let (start, end) = self.span(emulator);
+        let mut text = String::new();
+        for number in start.0..=end.0 {
+            let Some(line) = emulator.line(number) else {
+                continue;
+            };
+            let Some(columns) = self.columns(emulator, number) else {
+                continue;
+            };
+            let columns = columns.start.min(line.len())..columns.end.min(line.len());
+            let row = line.columns_as_str(columns);
+            if number == end.0 {
+                text.push_str(row.trim_end());
+            } else if line.last_cell_was_wrapped() {
+                text.push_str(&row);
+            } else {
+                text.push_str(row.trim_end());
+                text.push('\n');
+            }
+        }
+        text
+    }
+}
+
+/// Columns of the word under `point`, if it is on one.
+fn word_at(emulator: &Emulator, (line, column): Point) -> Option<Range<usize>> {
+    let line = emulator.line(line)?;
+    let is_word = |cell: &str| {
+        !cell
+            .chars()
+            .all(|c| c.is_whitespace() || WORD_DELIMITERS.contains(c))
+    };
+    let cells: Vec<_> = line.visible_cells().collect();
+    let index = cells.iter().position(|cell| {
+        column >= cell.cell_index() && column < cell.cell_index() + cell.width().max(1)
+    })?;
+    if !is_word(cells[index].str()) {
+        return None;
+    }
+    let first = cells[..index]
+        .iter()
+        .rposition(|cell| !is_word(cell.str()))
+        .map_or(0, |i| i + 1);
+    let last = cells[index..]
+        .iter()
+        .position(|cell| !is_word(cell.str()))
+        .map_or(cells.len(), |i| index + i)
+        - 1;
+    Some(cells[first].cell_index()..cells[last].cell_index() + cells[last].width().max(1))
+}
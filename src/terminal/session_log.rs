@@ -0,0 +1,132 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use termwiz::escape::csi::{Edit, EraseInLine};
+use termwiz::escape::parser::Parser;
+use termwiz::escape::{Action, ControlCode, CSI};
+
+/// What a session log records.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LogMode {
+    /// The bytes the program wrote, escape sequences included, so the log
+    /// can be replayed with `cat`.
+    Raw,
+    /// Printable text only, with lines redrawn through carriage returns
+    /// (progress bars) collapsed to what was finally shown.
+    Text,
+}
+
+/// Where session logs are written.
+pub fn log_dir() -> Option<PathBuf> {
+    Some(dirs::data_local_dir()?.join("rustfm").join("logs"))
+}
+
+/// A log file receiving everything a terminal's program prints.
+pub struct SessionLog {
+    mode: LogMode,
+    path: PathBuf,
+    writer: BufWriter<File>,
+    parser: Parser,
+    /// The line being printed in text mode, and the cursor column in it.
+    line: Vec<char>,
+    column: usize,
+}
+
+impl SessionLog {
+    /// Create a new timestamped log file in `log_dir()`.
+    pub fn start(mode: LogMode) -> std::io::Result<Self> {
+        let dir = log_dir().ok_or_else(|| std::io::Error::other("no data directory"))?;
+        std::fs::create_dir_all(&dir)?;
+        let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+        let suffix = match mode {
+            LogMode::Raw => "-raw",
+            LogMode::Text => "",
+        };
+        let mut path = dir.join(format!("terminal-{}{}.log", stamp, suffix));
+        let mut n = 1;
+        while path.exists() {
+            n += 1;
+            path = dir.join(format!("terminal-{}{}-{}.log", stamp, suffix, n));
+        }
+        let file = File::create(&path)?;
+        Ok(Self {
+            mode,
+            path,
+            writer: BufWriter::new(file),
+            parser: Parser::new(),
+            line: Vec::new(),
+            column: 0,
+        })
+    }
+
+    pub fn mode(&self) -> LogMode {
+        self.mode
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Record a chunk of program output. The file is flushed every time so
+    /// the log is complete even if the application is killed.
+    pub fn write(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+        match self.mode {
+            LogMode::Raw => self.writer.write_all(bytes)?,
+            LogMode::Text => {
+                let mut finished = Vec::new();
+                let (line, column) = (&mut self.line, &mut self.column);
+                self.parser.parse(bytes, |action| {
+                    let mut print = |c: char| {
+                        if *column < line.len() {
+                            line[*column] = c;
+                        } else {
+                            line.resize(*column, ' ');
+                            line.push(c);
+                        }
+                        *column += 1;
+                    };
+                    match action {
+                        Action::Print(c) => print(c),
+                        Action::PrintString(s) => s.chars().for_each(print),
+                        Action::Control(ControlCode::HorizontalTab) => {
+                            *column = (*column / 8 + 1) * 8;
+                        }
+                        Action::Control(ControlCode::Backspace) => {
+                            *column = column.saturating_sub(1);
+                        }
+                        Action::Control(ControlCode::CarriageReturn) => *column = 0,
+                        Action::Control(ControlCode::LineFeed) => {
+                            finished.push(std::mem::take(line));
+                            *column = 0;
+                        }
+                        Action::CSI(CSI::Edit(Edit::EraseInLine(erase))) => match erase {
+                            EraseInLine::EraseToEndOfLine => line.truncate(*column),
+                            EraseInLine::EraseToStartOfLine => {
+                                let end = (*column + 1).min(line.len());
+                                line[..end].fill(' ');
+                            }
+                            EraseInLine::EraseLine => line.clear(),
+                        },
+                        _ => {}
+                    }
+                });
+                for line in finished {
+                    let text: String = line.into_iter().collect();
+                    writeln!(self.writer, "{}", text.trim_end())?;
+                }
+            }
+        }
+        self.writer.flush()
+    }
+}
+
+impl Drop for SessionLog {
+    fn drop(&mut self) {
+        // Keep a last line that never got its newline, e.g. the prompt
+        if !self.line.is_empty() {
+            let text: String = self.line.drain(..).collect();
+            let _ = writeln!(self.writer, "{}", text.trim_end());
+        }
+        let _ = self.writer.flush();
+    }
+}
@@ -0,0 +1,162 @@
+use std::path::Path;
+
+/// The family of shell running in a terminal, used to quote paths in
+/// commands we type on the user's behalf.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ShellKind {
+    Sh,
+    Bash,
+    Zsh,
+    Fish,
+    PowerShell,
+    Cmd,
+}
+
+impl ShellKind {
+    /// Recognise the shell from the program path, e.g. `/usr/bin/zsh`.
+    /// Returns `None` for programs that are not a known shell.
+    pub fn recognize(program: &str) -> Option<Self> {
+        let name = Path::new(program)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match name.as_str() {
+            "sh" | "dash" | "ash" | "ksh" | "mksh" => Some(ShellKind::Sh),
+            "bash" => Some(ShellKind::Bash),
+            "zsh" => Some(ShellKind::Zsh),
+            "fish" => Some(ShellKind::Fish),
+            "pwsh" | "powershell" => Some(ShellKind::PowerShell),
+            "cmd" => Some(ShellKind::Cmd),
+            _ => None,
+        }
+    }
+
+    /// Flag that starts the shell as a login shell.
+    pub fn login_flag(self) -> Option<&'static str> {
+        match self {
+            ShellKind::Sh | ShellKind::Bash | ShellKind::Zsh | ShellKind::Fish => Some("-l"),
+            ShellKind::PowerShell => Some("-Login"),
+            ShellKind::Cmd => None,
+        }
+    }
+
+    /// Flag that forces an interactive shell.
+    pub fn interactive_flag(self) -> Option<&'static str> {
+        match self {
+            ShellKind::Sh | ShellKind::Bash | ShellKind::Zsh | ShellKind::Fish => Some("-i"),
+            ShellKind::PowerShell => Some("-Interactive"),
+            ShellKind::Cmd => None,
+        }
+    }
+
+    /// Quote `path` so the shell reads it back as a single literal word.
+    pub fn quote(self, path: &Path) -> String {
+        let path = path.to_string_lossy();
+        match self {
+            // Everything inside single quotes is literal, including
+            // newlines; a quote is written as '\''
+            ShellKind::Sh | ShellKind::Bash | ShellKind::Zsh => {
+                format!("'{}'", path.replace('\'', r"'\''"))
+            }
+            // fish processes \\ and \' inside single quotes
+            ShellKind::Fish => {
+                format!("'{}'", path.replace('\\', r"\\").replace('\'', r"\'"))
+            }
+            ShellKind::PowerShell => format!("'{}'", path.replace('\'', "''")),
+            // Windows paths cannot contain double quotes
+            ShellKind::Cmd => format!("\"{}\"", path),
+        }
+    }
+
+    /// A complete command line that changes to `path`. POSIX-style shells
+    /// get a leading space, which keeps the command out of the history in
+    /// fish by default, in bash with `HISTCONTROL=ignorespace` or
+    /// `ignoreboth`, and in zsh with `setopt hist_ignore_space`.
+    pub fn cd_command(self, path: &Path) -> String {
+        let quoted = self.quote(path);
+        match self {
+            ShellKind::Sh | ShellKind::Bash | ShellKind::Zsh => format!(" cd -- {}\n", quoted),
+            ShellKind::Fish => format!(" cd {}\n", quoted),
+            ShellKind::PowerShell => format!("Set-Location -LiteralPath {}\r\n", quoted),
+            ShellKind::Cmd => format!("cd /d {}\r\n", quoted),
+        }
+    }
+
+    /// A complete command line running `commands` one after the other.
+    pub fn sequence(self, commands: &[String]) -> String {
+        match self {
+            ShellKind::Cmd => format!("{}\r\n", commands.join(" & ")),
+            ShellKind::PowerShell => format!("{}\r\n", commands.join("; ")),
+            _ => format!("{}\n", commands.join("; ")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AWKWARD: &str = r"/tmp/it's a \path with $HOME and `ls`";
+
+    #[test]
+    fn recognize_uses_the_program_name() {
+        assert_eq!(ShellKind::recognize("/usr/bin/zsh"), Some(ShellKind::Zsh));
+        assert_eq!(ShellKind::recognize("dash"), Some(ShellKind::Sh));
+        assert_eq!(ShellKind::recognize("cmd.exe"), Some(ShellKind::Cmd));
+        assert_eq!(ShellKind::recognize("pwsh.exe"), Some(ShellKind::PowerShell));
+        assert_eq!(ShellKind::recognize("/usr/bin/python3"), None);
+    }
+
+    #[test]
+    fn quote_escapes_quotes_per_shell() {
+        let path = Path::new(AWKWARD);
+        assert_eq!(
+            ShellKind::Bash.quote(path),
+            r"'/tmp/it'\''s a \path with $HOME and `ls`'"
+        );
+        assert_eq!(
+            ShellKind::Fish.quote(path),
+            r"'/tmp/it\'s a \\path with $HOME and `ls`'"
+        );
+        assert_eq!(
+            ShellKind::PowerShell.quote(path),
+            r"'/tmp/it''s a \path with $HOME and `ls`'"
+        );
+        assert_eq!(
+            ShellKind::Cmd.quote(Path::new(r"C:\Program Files\it's")),
+            r#""C:\Program Files\it's""#
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn quote_round_trips_through_sh() {
+        let quoted = ShellKind::Sh.quote(Path::new(AWKWARD));
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(format!("printf %s {}", quoted))
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), AWKWARD);
+    }
+
+    #[test]
+    fn cd_command_ends_the_line_for_the_shell() {
+        let path = Path::new("/tmp/a b");
+        assert_eq!(ShellKind::Zsh.cd_command(path), " cd -- '/tmp/a b'\n");
+        assert_eq!(ShellKind::Fish.cd_command(path), " cd '/tmp/a b'\n");
+        assert_eq!(
+            ShellKind::PowerShell.cd_command(path),
+            "Set-Location -LiteralPath '/tmp/a b'\r\n"
+        );
+        assert_eq!(ShellKind::Cmd.cd_command(Path::new(r"D:\x")), "cd /d \"D:\\x\"\r\n");
+    }
+
+    #[test]
+    fn sequence_joins_commands_on_one_line() {
+        let commands = ["make".to_string(), "make test".to_string()];
+        assert_eq!(ShellKind::Bash.sequence(&commands), "make; make test\n");
+        assert_eq!(ShellKind::PowerShell.sequence(&commands), "make; make test\r\n");
+        assert_eq!(ShellKind::Cmd.sequence(&commands), "make & make test\r\n");
+    }
+}
@@ -0,0 +1,329 @@
+use egui::text::{LayoutJob, TextFormat};
+use egui::{Color32, FontId, Stroke};
+use termwiz::cell::{Blink, CellAttributes, Intensity, Underline};
+use termwiz::color::{ColorAttribute, ColorSpec};
+use termwiz::escape::csi::Sgr;
+use termwiz::escape::{OperatingSystemCommand, CSI};
+use termwiz::surface::line::CellRef;
+use termwiz::surface::Line;
+
+pub const DEFAULT_FG: Color32 = Color32::LIGHT_GRAY;
+pub const DEFAULT_BG: Color32 = Color32::TRANSPARENT;
+
+/// The 16 ANSI colors, roughly matching the xterm defaults.
+const ANSI_COLORS: [Color32; 16] = [
+    Color32::from_rgb(0, 0, 0),
+    Color32::from_rgb(205, 49, 49),
+    Color32::from_rgb(13, 188, 121),
+    Color32::from_rgb(229, 229, 16),
+    Color32::from_rgb(36, 114, 200),
+    Color32::from_rgb(188, 63, 188),
+    Color32::from_rgb(17, 168, 205),
+    Color32::from_rgb(229, 229, 229),
+    Color32::from_rgb(102, 102, 102),
+    Color32::from_rgb(241, 76, 76),
+    Color32::from_rgb(35, 209, 139),
+    Color32::from_rgb(245, 245, 67),
+    Color32::from_rgb(59, 142, 234),
+    Color32::from_rgb(214, 112, 214),
+    Color32::from_rgb(41, 184, 219),
+    Color32::from_rgb(255, 255, 255),
+];
+
+/// Resolve an xterm 256-color palette index.
+pub fn palette_color(index: u8) -> Color32 {
+    match index {
+        0..=15 => ANSI_COLORS[index as usize],
+        16..=231 => {
+            // 6x6x6 color cube
+            let index = index - 16;
+            let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+            Color32::from_rgb(level(index / 36), level((index / 6) % 6), level(index % 6))
+        }
+        232..=255 => {
+            // Grayscale ramp
+            let gray = 8 + (index - 232) * 10;
+            Color32::from_gray(gray)
+        }
+    }
+}
+
+fn resolve(color: ColorAttribute, default: Color32) -> Color32 {
+    match color {
+        ColorAttribute::Default => default,
+        ColorAttribute::PaletteIndex(index) => palette_color(index),
+        ColorAttribute::TrueColorWithPaletteFallback(rgba, _)
+        | ColorAttribute::TrueColorWithDefaultFallback(rgba) => {
+            let (r, g, b, _) = rgba.to_srgb_u8();
+            Color32::from_rgb(r, g, b)
+        }
+    }
+}
+
+/// Foreground and background colors for a cell after applying bold, dim,
+/// inverse and invisible.
+pub fn cell_colors(attrs: &CellAttributes) -> (Color32, Color32) {
+    let mut fg = match (attrs.foreground(), attrs.intensity()) {
+        // Bold text in one of the eight base colors is shown in the bright variant
+        (ColorAttribute::PaletteIndex(index), Intensity::Bold) if index < 8 => {
+            palette_color(index + 8)
+        }
+        (ColorAttribute::Default, Intensity::Bold) => Color32::WHITE,
+        (color, _) => resolve(color, DEFAULT_FG),
+    };
+    if attrs.intensity() == Intensity::Half {
+        fg = fg.gamma_multiply(0.6);
+    }
+    let mut bg = resolve(attrs.background(), DEFAULT_BG);
+
+    if attrs.reverse() {
+        let reversed_fg = if bg == DEFAULT_BG { Color32::from_gray(24) } else { bg };
+        bg = fg;
+        fg = reversed_fg;
+    }
+    if attrs.invisible() {
+        fg = Color32::TRANSPARENT;
+    }
+    (fg, bg)
+}
+
+pub fn text_format(attrs: &CellAttributes, font_id: &FontId) -> TextFormat {
+    let (fg, bg) = cell_colors(attrs);
+    let underline = if attrs.underline() == Underline::None {
+        Stroke::NONE
+    } else {
+        Stroke::new(1.0, resolve(attrs.underline_color(), fg))
+    };
+    TextFormat {
+        font_id: font_id.clone(),
+        color: fg,
+        background: bg,
+        italics: attrs.italic(),
+        underline,
+        strikethrough: if attrs.strikethrough() { Stroke::new(1.0, fg) } else { Stroke::NONE },
+        ..Default::default()
+    }
+}
+
+/// Apply one SGR rendition change to `attrs`.
+pub fn apply_sgr(attrs: &mut CellAttributes, sgr: Sgr) {
+    match sgr {
+        // OSC 8 hyperlinks are not part of the SGR rendition
+        Sgr::Reset => {
+            let link = attrs.hyperlink().cloned();
+            *attrs = CellAttributes::default();
+            attrs.set_hyperlink(link);
+        }
+        Sgr::Intensity(intensity) => {
+            attrs.set_intensity(intensity);
+        }
+        Sgr::Underline(underline) => {
+            attrs.set_underline(underline);
+        }
+        Sgr::UnderlineColor(color) => {
+            attrs.set_underline_color(color);
+        }
+        Sgr::Blink(blink) => {
+            attrs.set_blink(blink);
+        }
+        Sgr::Italic(on) => {
+            attrs.set_italic(on);
+        }
+        Sgr::Inverse(on) => {
+            attrs.set_reverse(on);
+        }
+        Sgr::Invisible(on) => {
+            attrs.set_invisible(on);
+        }
+        Sgr::StrikeThrough(on) => {
+            attrs.set_strikethrough(on);
+        }
+        Sgr::Foreground(color) => {
+            attrs.set_foreground(color);
+        }
+        Sgr::Background(color) => {
+            attrs.set_background(color);
+        }
+        _ => {}
+    }
+}
+
+fn color_spec(color: ColorAttribute) -> ColorSpec {
+    match color {
+        ColorAttribute::Default => ColorSpec::Default,
+        ColorAttribute::PaletteIndex(index) => ColorSpec::PaletteIndex(index),
+        ColorAttribute::TrueColorWithPaletteFallback(rgba, _)
+        | ColorAttribute::TrueColorWithDefaultFallback(rgba) => ColorSpec::TrueColor(rgba),
+    }
+}
+
+/// The SGR sequence that resets the rendition and then selects `attrs`.
+fn sgr_sequence(attrs: &CellAttributes) -> String {
+    let mut changes = vec![Sgr::Reset];
+    if attrs.intensity() != Intensity::Normal {
+        changes.push(Sgr::Intensity(attrs.intensity()));
+    }
+    if attrs.underline() != Underline::None {
+        changes.push(Sgr::Underline(attrs.underline()));
+    }
+    if attrs.underline_color() != ColorAttribute::Default {
+        changes.push(Sgr::UnderlineColor(color_spec(attrs.underline_color())));
+    }
+    if attrs.blink() != Blink::None {
+        changes.push(Sgr::Blink(attrs.blink()));
+    }
+    if attrs.italic() {
+        changes.push(Sgr::Italic(true));
+    }
+    if attrs.reverse() {
+        changes.push(Sgr::Inverse(true));
+    }
+    if attrs.invisible() {
+        changes.push(Sgr::Invisible(true));
+    }
+    if attrs.strikethrough() {
+        changes.push(Sgr::StrikeThrough(true));
+    }
+    if attrs.foreground() != ColorAttribute::Default {
+        changes.push(Sgr::Foreground(color_spec(attrs.foreground())));
+    }
+    if attrs.background() != ColorAttribute::Default {
+        changes.push(Sgr::Background(color_spec(attrs.background())));
+    }
+    changes.into_iter().map(|sgr| CSI::Sgr(sgr).to_string()).collect()
+}
+
+/// The cells of `line` up to the last one that shows anything.
+fn content_cells(line: &Line) -> Vec<CellRef<'_>> {
+    let mut cells: Vec<_> = line.visible_cells().collect();
+    let len = cells
+        .iter()
+        .rposition(|cell| {
+            cell.str() != " "
+                || cell.attrs().background() != ColorAttribute::Default
+                || cell.attrs().reverse()
+        })
+        .map_or(0, |i| i + 1);
+    cells.truncate(len);
+    cells
+}
+
+/// Encode a line as text with SGR escape sequences, as a program would
+/// have drawn it. Trailing blank cells are dropped.
+pub fn line_to_ansi(line: &Line) -> String {
+    let mut text = String::new();
+    let default = CellAttributes::default();
+    let cells = content_cells(line);
+    let mut current = &default;
+    for cell in &cells {
+        if cell.attrs() != current {
+            if cell.attrs().hyperlink() != current.hyperlink() {
+                let link = cell.attrs().hyperlink().map(|link| (**link).clone());
+                text.push_str(&OperatingSystemCommand::SetHyperlink(link).to_string());
+            }
+            text.push_str(&sgr_sequence(cell.attrs()));
+            current = cell.attrs();
+        }
+        text.push_str(cell.str());
+    }
+    if current.hyperlink().is_some() {
+        text.push_str(&OperatingSystemCommand::SetHyperlink(None).to_string());
+    }
+    if current != &default {
+        text.push_str(&CSI::Sgr(Sgr::Reset).to_string());
+    }
+    text
+}
+
+/// Build a layout job for one terminal line, with one section per run of
+/// cells sharing the same attributes. Trailing blank cells are dropped.
+pub fn line_job(line: &Line, font_id: &FontId) -> LayoutJob {
+    let cells = content_cells(line);
+
+    let mut job = LayoutJob::default();
+    let mut run = String::new();
+    let mut run_attrs: Option<&CellAttributes> = None;
+    for cell in &cells {
+        if let Some(attrs) = run_attrs {
+            if attrs != cell.attrs() {
+                job.append(&run, 0.0, text_format(attrs, font_id));
+                run.clear();
+            }
+        }
+        run.push_str(cell.str());
+        run_attrs = Some(cell.attrs());
+    }
+    if let Some(attrs) = run_attrs {
+        job.append(&run, 0.0, text_format(attrs, font_id));
+    }
+    job
+}
+
+fn css_color(color: Color32) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r(), color.g(), color.b())
+}
+
+/// CSS for a cell's colors and text attributes, empty for plain text.
+fn css_style(attrs: &CellAttributes) -> String {
+    let (fg, bg) = cell_colors(attrs);
+    let mut style = Vec::new();
+    if fg != DEFAULT_FG {
+        style.push(format!("color:{}", css_color(fg)));
+    }
+    if bg != DEFAULT_BG {
+        style.push(format!("background:{}", css_color(bg)));
+    }
+    if attrs.intensity() == Intensity::Bold {
+        style.push("font-weight:bold".to_string());
+    }
+    if attrs.italic() {
+        style.push("font-style:italic".to_string());
+    }
+    let mut decoration = Vec::new();
+    if attrs.underline() != Underline::None {
+        decoration.push("underline");
+    }
+    if attrs.strikethrough() {
+        decoration.push("line-through");
+    }
+    if !decoration.is_empty() {
+        style.push(format!("text-decoration:{}", decoration.join(" ")));
+    }
+    style.join(";")
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// Encode a line as HTML spans, for use inside `<pre>`. Hyperlinks become
+/// anchors. Trailing blank cells are dropped.
+pub fn line_to_html(line: &Line) -> String {
+    let mut html = String::new();
+    let cells = content_cells(line);
+    let mut start = 0;
+    while start < cells.len() {
+        let attrs = cells[start].attrs();
+        let end = cells[start..]
+            .iter()
+            .position(|cell| cell.attrs() != attrs)
+            .map_or(cells.len(), |i| start + i);
+        let text: String = cells[start..end].iter().map(|cell| cell.str()).collect();
+        let mut run = escape_html(&text);
+        let style = css_style(attrs);
+        if !style.is_empty() {
+            run = format!("<span style=\"{}\">{}</span>", style, run);
+        }
+        if let Some(link) = attrs.hyperlink() {
+            run = format!("<a href=\"{}\">{}</a>", escape_html(link.uri()).replace('"', "&quot;"), run);
+        }
+        html.push_str(&run);
+        start = end;
+    }
+    html
+}
+
+/// The page background and text color that `line_to_html` output assumes.
+pub fn html_page_colors() -> (String, String) {
+    (css_color(Color32::from_gray(24)), css_color(DEFAULT_FG))
+}
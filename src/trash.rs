@@ -0,0 +1,628 @@
+use crate::explorer::FileEntry;
+use crate::fileops;
+use crate::jobs::{JobItem, JobKind, JobRequest};
+use chrono::{Local, NaiveDateTime};
+use egui::{Color32, Grid, RichText, ScrollArea, Ui};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+const INFO_EXTENSION: &str = ".trashinfo";
+const DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// The user's own trash: `$XDG_DATA_HOME/Trash`.
+pub fn home_trash() -> Option<PathBuf> {
+    Some(dirs::data_dir()?.join("Trash"))
+}
+
+#[cfg(unix)]
+fn uid() -> u32 {
+    // SAFETY: getuid cannot fail and has no preconditions
+    unsafe { libc::getuid() }
+}
+
+/// The top directory of the mount holding `path`.
+#[cfg(unix)]
+fn mount_root(path: &Path) -> Option<PathBuf> {
+    use std::os::unix::fs::MetadataExt;
+    let device = fs::symlink_metadata(path).ok()?.dev();
+    let mut root = path;
+    while let Some(parent) = root.parent() {
+        if fs::metadata(parent).ok()?.dev() != device {
+            break;
+        }
+        root = parent;
+    }
+    Some(root.to_path_buf())
+}
+
+/// Where to trash `path`: the home trash when it is on the same file
+/// system, otherwise a trash at the top of the path's own mount.
+#[cfg(unix)]
+fn trash_for(path: &Path) -> io::Result<(PathBuf, Option<PathBuf>)> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+    let home = home_trash().ok_or_else(|| io::Error::other("no data directory"))?;
+    fs::create_dir_all(&home)?;
+    let parent = path.parent().unwrap_or(path);
+    if fs::metadata(parent)?.dev() == fs::metadata(&home)?.dev() {
+        return Ok((home, None));
+    }
+
+    let top = mount_root(parent).ok_or_else(|| io::Error::other("cannot find the volume's top directory"))?;
+    // A shared $top/.Trash has to be a real directory with the sticky bit
+    let shared = top.join(".Trash");
+    let usable = fs::symlink_metadata(&shared)
+        .is_ok_and(|m| m.is_dir() && m.permissions().mode() & 0o1000 != 0);
+    if usable {
+        let dir = shared.join(uid().to_string());
+        if fs::DirBuilder::new().recursive(true).mode(0o700).create(&dir).is_ok() {
+            return Ok((dir, Some(top)));
+        }
+    }
+    let dir = top.join(format!(".Trash-{}", uid()));
+    fs::DirBuilder::new().recursive(true).mode(0o700).create(&dir)?;
+    Ok((dir, Some(top)))
+}
+
+#[cfg(not(unix))]
+fn trash_for(_path: &Path) -> io::Result<(PathBuf, Option<PathBuf>)> {
+    let home = home_trash().ok_or_else(|| io::Error::other("no data directory"))?;
+    fs::create_dir_all(&home)?;
+    Ok((home, None))
+}
+
+/// Every trash directory that exists: the home trash, then those at the top
+/// of mounted volumes.
+fn trash_dirs() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = home_trash().into_iter().filter(|dir| dir.is_dir()).collect();
+    #[cfg(unix)]
+    {
+        let mounts = fs::read_to_string("/proc/mounts").unwrap_or_default();
+        for mount in mounts.lines().filter_map(|line| line.split_whitespace().nth(1)) {
+            // Spaces and other characters are octal-escaped in /proc/mounts
+            let top = PathBuf::from(mount.replace("\\040", " ").replace("\\011", "\t"));
+            for dir in [top.join(".Trash").join(uid().to_string()), top.join(format!(".Trash-{}", uid()))] {
+                if dir.join("info").is_dir() && !dirs.contains(&dir) {
+                    dirs.push(dir);
+                }
+            }
+        }
+    }
+    dirs
+}
+
+/// Percent-encode a path for the `Path=` key, leaving `/` alone.
+fn encode_path(path: &Path) -> String {
+    #[cfg(unix)]
+    let bytes = std::os::unix::ffi::OsStrExt::as_bytes(path.as_os_str()).to_vec();
+    #[cfg(not(unix))]
+    let bytes = path.to_string_lossy().into_owned().into_bytes();
+    let mut encoded = String::with_capacity(bytes.len());
+    for byte in bytes {
+        if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+fn decode_path(encoded: &str) -> PathBuf {
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut rest = encoded.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let hex = tail.get(..2).and_then(|hex| std::str::from_utf8(hex).ok());
+        match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+            Some(decoded) if byte == b'%' => {
+                bytes.push(decoded);
+                rest = &tail[2..];
+            }
+            _ => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    #[cfg(unix)]
+    return PathBuf::from(<std::ffi::OsString as std::os::unix::ffi::OsStringExt>::from_vec(bytes));
+    #[cfg(not(unix))]
+    PathBuf::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Move `path` into the trash, recording where it came from. Returns the
+/// trashed item.
+pub fn trash(path: &Path) -> io::Result<TrashItem> {
+    let path = std::path::absolute(path)?;
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::other("cannot trash this path"))?
+        .to_string_lossy()
+        .to_string();
+    let (dir, top) = trash_for(&path)?;
+    if path.starts_with(&dir) {
+        return Err(io::Error::other("already in the trash"));
+    }
+    let files = dir.join("files");
+    let info = dir.join("info");
+    fs::create_dir_all(&files)?;
+    fs::create_dir_all(&info)?;
+
+    // Creating the info file first reserves the name
+    let (trash_name, info_path, mut info_file) = (1..)
+        .map(|n| if n == 1 { name.clone() } else { format!("{}.{}", name, n) })
+        .filter(|candidate| fs::symlink_metadata(files.join(candidate)).is_err())
+        .find_map(|candidate| {
+            let info_path = info.join(format!("{}{}", candidate, INFO_EXTENSION));
+            match OpenOptions::new().write(true).create_new(true).open(&info_path) {
+                Ok(file) => Some(Ok((candidate, info_path, file))),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => None,
+                Err(e) => Some(Err(e)),
+            }
+        })
+        .expect("some trash name is free")?;
+
+    // Trashes on other volumes record paths relative to the volume
+    let recorded = match &top {
+        Some(top) => path.strip_prefix(top).unwrap_or(&path),
+        None => &path,
+    };
+    let deleted = Local::now().naive_local();
+    let result = write!(
+        info_file,
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        encode_path(recorded),
+        deleted.format(DATE_FORMAT)
+    )
+    .and_then(|()| fs::rename(&path, files.join(&trash_name)));
+    if let Err(e) = result {
+        let _ = fs::remove_file(&info_path);
+        return Err(e);
+    }
+    Ok(TrashItem {
+        trash_dir: dir,
+        name: trash_name,
+        original: path,
+        deleted: Some(deleted),
+    })
+}
+
+/// Something in a trash directory.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrashItem {
+    trash_dir: PathBuf,
+    /// Name in the trash's `files` directory.
+    name: String,
+    pub original: PathBuf,
+    pub deleted: Option<NaiveDateTime>,
+}
+
+impl TrashItem {
+    /// The item kept at `path` in a trash's `files` directory.
+    pub fn at(path: &Path) -> Option<Self> {
+        let dir = path.parent()?.parent()?;
+        let name = path.file_name()?.to_string_lossy();
+        read_info(dir, &dir.join("info").join(format!("{}{}", name, INFO_EXTENSION)))
+    }
+
+    /// Where the item is kept while in the trash.
+    pub fn path(&self) -> PathBuf {
+        self.trash_dir.join("files").join(&self.name)
+    }
+
+    fn info_path(&self) -> PathBuf {
+        self.trash_dir.join("info").join(format!("{}{}", self.name, INFO_EXTENSION))
+    }
+
+    /// Put the item back where it was deleted from, recreating missing
+    /// parent directories. Refuses to replace anything there now.
+    pub fn restore(&self) -> io::Result<PathBuf> {
+        self.restore_to(&self.original)
+    }
+
+    /// Like `restore`, but when something else has taken the original path
+    /// the item comes back next to it under a numbered name.
+    pub fn restore_beside(&self) -> io::Result<PathBuf> {
+        if fs::symlink_metadata(&self.original).is_ok() {
+            return self.restore_to(&fileops::numbered_path(&self.original));
+        }
+        self.restore()
+    }
+
+    fn restore_to(&self, path: &Path) -> io::Result<PathBuf> {
+        if fs::symlink_metadata(path).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already exists", path.display()),
+            ));
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::rename(self.path(), path)?;
+        let _ = fs::remove_file(self.info_path());
+        Ok(path.to_path_buf())
+    }
+}
+
+/// Parse an info file of the trash in `dir`.
+fn read_info(dir: &Path, info_path: &Path) -> Option<TrashItem> {
+    let file_name = info_path.file_name()?.to_string_lossy();
+    let name = file_name.strip_suffix(INFO_EXTENSION)?.to_string();
+    let text = fs::read_to_string(info_path).ok()?;
+    let mut original = None;
+    let mut deleted = None;
+    for line in text.lines().skip_while(|line| line.trim() != "[Trash Info]").skip(1) {
+        if line.starts_with('[') {
+            break;
+        }
+        if let Some(value) = line.strip_prefix("Path=") {
+            original = Some(decode_path(value.trim()));
+        } else if let Some(value) = line.strip_prefix("DeletionDate=") {
+            deleted = NaiveDateTime::parse_from_str(value.trim(), DATE_FORMAT).ok();
+        }
+    }
+    let mut original = original?;
+    if original.is_relative() {
+        // Relative to the volume holding `$top/.Trash-$uid` or `$top/.Trash/$uid`
+        let top = if dir.file_name()?.to_string_lossy().starts_with(".Trash-") {
+            dir.parent()?
+        } else {
+            dir.parent()?.parent()?
+        };
+        original = top.join(original);
+    }
+    Some(TrashItem {
+        trash_dir: dir.to_path_buf(),
+        name,
+        original,
+        deleted,
+    })
+}
+
+/// Everything in every trash, most recently deleted first.
+pub fn list() -> Vec<TrashItem> {
+    let mut items: Vec<TrashItem> = trash_dirs()
+        .into_iter()
+        .flat_map(|dir| {
+            let entries = fs::read_dir(dir.join("info")).into_iter().flatten().flatten();
+            entries
+                .filter_map(|entry| read_info(&dir, &entry.path()))
+                .filter(|item| fs::symlink_metadata(item.path()).is_ok())
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    items.sort_by_key(|item| std::cmp::Reverse(item.deleted));
+    items
+}
+
+/// After `path` was deleted for good, drop its info file if it was in a
+/// trash's `files` directory.
+pub fn forget(path: &Path) {
+    let (Some(files), Some(name)) = (path.parent(), path.file_name()) else {
+        return;
+    };
+    if files.file_name().is_none_or(|dir| dir != "files") {
+        return;
+    }
+    let Some(dir) = files.parent() else {
+        return;
+    };
+    if is_trash_dir(dir) {
+        let info = dir
+            .join("info")
+            .join(format!("{}{}", name.to_string_lossy(), INFO_EXTENSION));
+        let _ = fs::remove_file(info);
+    }
+}
+
+/// Whether `dir` is laid out like a trash, judging by its path alone so
+/// that no mounts need to be looked up.
+fn is_trash_dir(dir: &Path) -> bool {
+    if home_trash().is_some_and(|home| home == dir) {
+        return true;
+    }
+    let Some(name) = dir.file_name().map(|name| name.to_string_lossy()) else {
+        return false;
+    };
+    name.starts_with(".Trash-") || dir.parent().and_then(Path::file_name).is_some_and(|parent| parent == ".Trash")
+}
+
+/// What the trash view asks for on confirmation.
+#[derive(Clone, Copy, PartialEq)]
+enum Confirm {
+    DeleteSelected,
+    Empty,
+}
+
+/// The Trash location: lists trashed items and restores or deletes them.
+#[derive(Default)]
+pub struct TrashPanel {
+    items: Vec<TrashItem>,
+    selected: Vec<PathBuf>,
+    confirm: Option<Confirm>,
+    pending_job: Option<JobRequest>,
+    pending_status: Option<Result<String, String>>,
+}
+
+impl TrashPanel {
+    pub fn refresh(&mut self) {
+        self.items = list();
+        let items = &self.items;
+        self.selected.retain(|path| items.iter().any(|item| item.path() == *path));
+    }
+
+    /// Permanent deletion to hand to the job queue.
+    pub fn check_job(&mut self) -> Option<JobRequest> {
+        self.pending_job.take()
+    }
+
+    pub fn check_status(&mut self) -> Option<Result<String, String>> {
+        self.pending_status.take()
+    }
+
+    fn delete_items(&mut self, items: Vec<TrashItem>) {
+        if items.is_empty() {
+            return;
+        }
+        self.pending_job = Some(JobRequest {
+            kind: JobKind::Delete,
+            items: items
+                .into_iter()
+                .map(|item| JobItem {
+                    source: item.path(),
+                    target: None,
+                })
+                .collect(),
+        });
+    }
+
+    fn restore_selected(&mut self) {
+        let mut restored = 0;
+        let mut renamed = Vec::new();
+        let mut errors = Vec::new();
+        for item in self.items.iter().filter(|item| self.selected.contains(&item.path())) {
+            match item.restore_beside() {
+                Ok(path) => {
+                    restored += 1;
+                    if path != item.original {
+                        renamed.push(path.file_name().unwrap_or_default().to_string_lossy().to_string());
+                    }
+                }
+                Err(e) => errors.push(format!("Failed to restore {}: {}", item.original.display(), e)),
+            }
+        }
+        self.pending_status = Some(match errors.as_slice() {
+            [] if renamed.is_empty() => Ok(format!("Restored {} items", restored)),
+            [] => Ok(format!(
+                "Restored {} items; already taken, so restored as {}",
+                restored,
+                renamed.join(", ")
+            )),
+            [error] => Err(error.clone()),
+            [first, rest @ ..] => Err(format!("{} (and {} more errors)", first, rest.len())),
+        });
+        self.refresh();
+    }
+
+    pub fn render(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("🗑 Trash").strong());
+            ui.separator();
+            ui.label(format!("{} items", self.items.len()));
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui.button("🔄").clicked() {
+                    self.refresh();
+                }
+                let empty = RichText::new("Empty Trash").color(Color32::LIGHT_RED);
+                if ui.add_enabled(!self.items.is_empty(), egui::Button::new(empty)).clicked() {
+                    self.confirm = Some(Confirm::Empty);
+                }
+                let has_selection = !self.selected.is_empty();
+                if ui
+                    .add_enabled(has_selection, egui::Button::new("Delete permanently"))
+                    .clicked()
+                {
+                    self.confirm = Some(Confirm::DeleteSelected);
+                }
+                if ui.add_enabled(has_selection, egui::Button::new("↩ Restore")).clicked() {
+                    self.restore_selected();
+                }
+            });
+        });
+        ui.separator();
+
+        if self.items.is_empty() {
+            ui.centered_and_justified(|ui| {
+                ui.label(RichText::new("The trash is empty").weak());
+            });
+        } else {
+            self.render_list(ui);
+        }
+        self.render_confirmation(ui.ctx());
+    }
+
+    fn render_list(&mut self, ui: &mut Ui) {
+        ScrollArea::vertical().show(ui, |ui| {
+            Grid::new("trash_grid").num_columns(4).striped(true).show(ui, |ui| {
+                ui.strong("Name");
+                ui.strong("Original location");
+                ui.strong("Deleted");
+                ui.strong("Size");
+                ui.end_row();
+
+                let mut clicked = None;
+                for item in &self.items {
+                    let path = item.path();
+                    let entry = FileEntry::from_path(path.clone());
+                    let name = item
+                        .original
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_else(|| item.name.clone());
+                    let icon = entry.as_ref().map_or("📄", |entry| entry.icon.as_str());
+                    let selected = self.selected.contains(&path);
+                    if ui.selectable_label(selected, format!("{} {}", icon, name)).clicked() {
+                        clicked = Some(path);
+                    }
+                    let location = item.original.parent().unwrap_or(&item.original);
+                    ui.label(location.display().to_string());
+                    ui.label(
+                        item.deleted
+                            .map(|date| date.format("%Y-%m-%d %H:%M").to_string())
+                            .unwrap_or_else(|| "--".to_string()),
+                    );
+                    ui.label(entry.map(|entry| entry.format_size()).unwrap_or_else(|| "--".to_string()));
+                    ui.end_row();
+                }
+                if let Some(path) = clicked {
+                    let toggle = ui.input(|i| i.modifiers.command);
+                    if toggle {
+                        if let Some(index) = self.selected.iter().position(|p| *p == path) {
+                            self.selected.remove(index);
+                        } else {
+                            self.selected.push(path);
+                        }
+                    } else {
+                        self.selected = vec![path];
+                    }
+                }
+            });
+        });
+    }
+
+    fn render_confirmation(&mut self, ctx: &egui::Context) {
+        let Some(confirm) = self.confirm else {
+            return;
+        };
+        let question = match confirm {
+            Confirm::Empty => format!("Permanently delete all {} items in the trash?", self.items.len()),
+            Confirm::DeleteSelected => format!("Permanently delete {} items?", self.selected.len()),
+        };
+        let mut confirmed = false;
+        let mut cancelled = false;
+        let modal = egui::Modal::new(egui::Id::new("trash_confirm")).show(ctx, |ui| {
+            ui.label(RichText::new(question).strong());
+            ui.label("This cannot be undone.");
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                let delete = egui::Button::new(RichText::new("Delete").color(Color32::LIGHT_RED));
+                if ui.add(delete).clicked() {
+                    confirmed = true;
+                }
+                if ui.button("Cancel").clicked() {
+                    cancelled = true;
+                }
+            });
+        });
+        if confirmed {
+            let items = match confirm {
+                Confirm::Empty => self.items.clone(),
+                Confirm::DeleteSelected => self
+                    .items
+                    .iter()
+                    .filter(|item| self.selected.contains(&item.path()))
+                    .cloned()
+                    .collect(),
+            };
+            self.delete_items(items);
+            self.confirm = None;
+        } else if cancelled || modal.should_close() {
+            self.confirm = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Put `name` into a `.Trash-1000` under `top` as if deleted from
+    /// `original`, which is written to the info file as given.
+    fn fake_trash(top: &Path, name: &str, original: &str) -> PathBuf {
+        let dir = top.join(".Trash-1000");
+        fs::create_dir_all(dir.join("files")).unwrap();
+        fs::create_dir_all(dir.join("info")).unwrap();
+        fs::write(dir.join("files").join(name), "trashed").unwrap();
+        fs::write(
+            dir.join("info").join(format!("{}{}", name, INFO_EXTENSION)),
+            format!("[Trash Info]\nPath={}\nDeletionDate=2024-05-01T12:30:00\n", original),
+        )
+        .unwrap();
+        dir.join("files").join(name)
+    }
+
+    #[test]
+    fn encode_path_escapes_reserved_bytes() {
+        assert_eq!(encode_path(Path::new("/home/me/a-b_c.txt~")), "/home/me/a-b_c.txt~");
+        assert_eq!(encode_path(Path::new("/tmp/100% done")), "/tmp/100%25%20done");
+        assert_eq!(encode_path(Path::new("/tmp/café")), "/tmp/caf%C3%A9");
+    }
+
+    #[test]
+    fn decode_path_reverses_encode_path() {
+        let paths = ["/tmp/100% done", "/tmp/%41 is not A", "/tmp/it's \"quoted\"", "/tmp/ünï côdé/%"];
+        for path in paths {
+            let path = Path::new(path);
+            assert_eq!(decode_path(&encode_path(path)), path);
+        }
+    }
+
+    #[test]
+    fn decode_path_keeps_stray_percent_signs() {
+        assert_eq!(decode_path("/tmp/50%"), Path::new("/tmp/50%"));
+        assert_eq!(decode_path("/tmp/%zz%4"), Path::new("/tmp/%zz%4"));
+        assert_eq!(decode_path("/tmp/a%20b"), Path::new("/tmp/a b"));
+    }
+
+    #[test]
+    fn reads_relative_paths_against_the_volume_top() {
+        let top = tempfile::tempdir().unwrap();
+        let trashed = fake_trash(top.path(), "100% done", "docs/100%25%20done");
+
+        let item = TrashItem::at(&trashed).unwrap();
+        assert_eq!(item.original, top.path().join("docs/100% done"));
+        assert_eq!(item.path(), trashed);
+        assert!(item.deleted.is_some());
+    }
+
+    #[test]
+    fn restore_beside_picks_a_numbered_name_when_taken() {
+        let top = tempfile::tempdir().unwrap();
+        let trashed = fake_trash(top.path(), "notes.txt", "docs/notes.txt");
+        let item = TrashItem::at(&trashed).unwrap();
+        fs::create_dir(top.path().join("docs")).unwrap();
+        fs::write(top.path().join("docs/notes.txt"), "newer").unwrap();
+
+        assert_eq!(item.restore().unwrap_err().kind(), io::ErrorKind::AlreadyExists);
+        let restored = item.restore_beside().unwrap();
+        assert_eq!(restored, top.path().join("docs/notes (2).txt"));
+        assert_eq!(fs::read_to_string(&restored).unwrap(), "trashed");
+        assert_eq!(fs::read_to_string(top.path().join("docs/notes.txt")).unwrap(), "newer");
+        assert!(!item.info_path().exists());
+    }
+
+    #[test]
+    fn forget_drops_the_info_file_of_a_trashed_entry() {
+        let top = tempfile::tempdir().unwrap();
+        let trashed = fake_trash(top.path(), "old.log", "old.log");
+        let info = TrashItem::at(&trashed).unwrap().info_path();
+
+        fs::remove_file(&trashed).unwrap();
+        forget(&trashed);
+        assert!(!info.exists());
+    }
+
+    #[test]
+    fn forget_ignores_folders_that_only_look_like_files() {
+        let top = tempfile::tempdir().unwrap();
+        let files = top.path().join("project/files");
+        fs::create_dir_all(&files).unwrap();
+        fs::create_dir_all(top.path().join("project/info")).unwrap();
+        let info = top.path().join("project/info/a.trashinfo");
+        fs::write(&info, "").unwrap();
+
+        forget(&files.join("a"));
+        assert!(info.exists());
+    }
+}